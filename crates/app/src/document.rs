@@ -0,0 +1,136 @@
+//! Captures open side by side in tabs
+//!
+//! The app shows one capture at a time from its `capture` state, which every panel reads.
+//! The other open captures are parked in `Document`s, and switching tabs swaps the shown
+//! capture's state with the parked one, so each keeps its own selection, filter, marks
+//! and scrubbers.
+
+use crate::time_scrubber::TimeScrubber;
+use crate::ui::diff_panel::Comparison;
+use crate::{PcapViewerApp, Tab};
+use common::ParsedPacket;
+use common::diagnostics::ParseDiagnostic;
+use common::messages::ParsedMessage;
+use common::session::Bookmark;
+use common::weenie::WeenieDatabase;
+use std::collections::HashSet;
+
+/// A capture and the state of the views onto it
+#[derive(Default)]
+pub struct CaptureState {
+    pub messages: Vec<ParsedMessage>,
+    pub packets: Vec<ParsedPacket>,
+    /// The capture file's bytes, which packets' payloads are read from and trimmed
+    /// captures are written from
+    pub bytes: Vec<u8>,
+    pub weenie_db: WeenieDatabase,
+    pub diagnostics: Vec<ParseDiagnostic>,
+    pub current_tab: Tab,
+    pub selected_message: Option<usize>,
+    /// Set when the selected message should be scrolled into view on the next frame
+    pub scroll_to_selected_message: bool,
+    pub selected_packet: Option<usize>,
+    /// Object ID of the selected weenie
+    pub selected_weenie: Option<u32>,
+    /// Property the weenie timeline is narrowed to
+    pub weenie_history_property: Option<String>,
+    pub selected_diagnostic: Option<usize>,
+    pub search_query: String,
+    /// Field of the selected message whose bytes are highlighted, as (message index, path)
+    pub selected_field: Option<(usize, String)>,
+    pub messages_scrubber: TimeScrubber,
+    pub fragments_scrubber: TimeScrubber,
+    pub weenies_scrubber: TimeScrubber,
+    /// Instant the weenies tab was pinned to by clicking its scrubber
+    pub weenie_pinned_time: Option<f64>,
+    /// Weenie state last reconstructed for the scrubber, and the instant it's for
+    pub weenie_state_cache: Option<(f64, WeenieDatabase)>,
+    pub marked_messages: HashSet<usize>,
+    pub marked_packets: HashSet<usize>,
+    /// Capture this one is compared with
+    pub comparison: Option<Comparison>,
+    /// Hash of the capture's bytes, which sessions refer to it by
+    pub hash: String,
+    /// Labeled notes on messages and objects of the capture
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// A capture open in a tab
+#[derive(Default)]
+pub struct Document {
+    /// Tab title, usually the file name
+    pub name: String,
+    /// The capture's state, while another tab is shown
+    pub capture: CaptureState,
+}
+
+impl Document {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Exchange this document's state with the capture the app is showing
+    pub fn swap_with(&mut self, app: &mut PcapViewerApp) {
+        std::mem::swap(&mut self.capture, &mut app.capture);
+    }
+}
+
+impl PcapViewerApp {
+    /// Whether the shown capture has anything in it
+    pub fn has_capture(&self) -> bool {
+        !self.capture.messages.is_empty() || !self.capture.packets.is_empty()
+    }
+
+    /// Show the capture in tab `idx`, parking the one shown now
+    pub fn switch_document(&mut self, idx: usize) {
+        if idx == self.active_document || idx >= self.documents.len() {
+            return;
+        }
+        let mut documents = std::mem::take(&mut self.documents);
+        documents[self.active_document].swap_with(self);
+        documents[idx].swap_with(self);
+        self.documents = documents;
+        self.active_document = idx;
+    }
+
+    /// Open a new, empty tab named `name` and show it
+    ///
+    /// The shown capture is reused instead if it's empty, so loading the first capture
+    /// doesn't leave a blank tab behind.
+    pub fn open_document(&mut self, name: impl Into<String>) {
+        if !self.has_capture() {
+            self.documents[self.active_document].name = name.into();
+            return;
+        }
+        self.documents.push(Document::new(name));
+        self.switch_document(self.documents.len() - 1);
+    }
+
+    /// Close tab `idx`, showing its neighbour if it was the one shown
+    pub fn close_document(&mut self, idx: usize) {
+        if idx >= self.documents.len() {
+            return;
+        }
+        if self.documents.len() == 1 {
+            // Closing the last tab leaves an empty one
+            Document::default().swap_with(self);
+            self.documents[0] = Document::default();
+            return;
+        }
+        if idx == self.active_document {
+            let next = if idx + 1 < self.documents.len() {
+                idx + 1
+            } else {
+                idx - 1
+            };
+            self.switch_document(next);
+        }
+        self.documents.remove(idx);
+        if self.active_document > idx {
+            self.active_document -= 1;
+        }
+    }
+}
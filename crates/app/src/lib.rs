@@ -2,15 +2,18 @@
 //!
 //! Shared egui-based interface for both web and desktop applications.
 
-pub mod filter;
+pub mod document;
+pub mod loader;
+pub mod session;
 pub mod state;
 pub mod time_scrubber;
 pub mod ui;
 
-use common::{ParsedPacket, messages::ParsedMessage};
+use common::export::ExportFormat;
+use common::query::Query;
+use common::session::BookmarkTarget;
 use eframe::egui;
 use std::sync::{Arc, Mutex};
-use time_scrubber::TimeScrubber;
 
 // Re-export state types for convenience
 pub use common::{SortField, Tab, ViewMode};
@@ -21,17 +24,16 @@ pub type SharedData = Arc<Mutex<Option<Vec<u8>>>>;
 pub type SharedError = Arc<Mutex<Option<String>>>;
 
 pub struct PcapViewerApp {
-    // Data
-    pub messages: Vec<ParsedMessage>,
-    pub packets: Vec<ParsedPacket>,
-    pub weenie_db: common::weenie::WeenieDatabase,
+    /// Open captures, one per tab; the shown one's state is in `capture`
+    pub documents: Vec<document::Document>,
+    pub active_document: usize,
+    /// The shown capture and the state of the views onto it
+    pub capture: document::CaptureState,
 
     // UI State
-    pub current_tab: Tab,
-    pub selected_message: Option<usize>,
-    pub selected_packet: Option<usize>,
-    pub selected_weenie: Option<usize>,
-    pub search_query: String,
+    /// Extra messages table columns, each showing what a path picks out of the message data
+    pub extract_columns: Vec<common::extract::FieldPath>,
+    pub extract_input: String,
     pub sort_field: SortField,
     pub sort_ascending: bool,
     pub view_mode: ViewMode,
@@ -39,6 +41,15 @@ pub struct PcapViewerApp {
     // Status
     pub status_message: String,
     pub is_loading: bool,
+    /// Captures being parsed in the background, each with the name of the tab it will
+    /// open in
+    pub parse_jobs: Vec<(String, loader::ParseJob)>,
+    /// Rules weenies are extracted with when a capture is parsed
+    pub extractor_rules: Arc<common::extractor_rules::ExtractorRegistry>,
+    /// Names for enum, flag and spell values in the tree view
+    pub value_names: common::value_names::ValueNames,
+    /// Capture being parsed to compare with, and its file name
+    pub compare_job: Option<(String, loader::ParseJob)>,
 
     // Theme
     pub dark_mode: bool,
@@ -46,12 +57,14 @@ pub struct PcapViewerApp {
     // Responsive layout state
     pub show_detail_panel: bool,
 
-    // Dropped file data
-    pub dropped_file_data: Option<Vec<u8>>,
+    // Dropped file name and data
+    pub dropped_file_data: Option<(String, Vec<u8>)>,
 
     // Async loaded data (from fetch)
     pub fetched_data: SharedData,
     pub fetched_error: SharedError,
+    /// Tab name for the capture being fetched
+    pub fetched_name: String,
 
     // Initial URL to load from query params (consumed on first update)
     pub initial_url: Option<String>,
@@ -78,13 +91,8 @@ pub struct PcapViewerApp {
     pub show_error_dialog: bool,
     pub error_dialog_message: String,
 
-    // Time scrubbers (separate for messages and fragments)
-    pub messages_scrubber: TimeScrubber,
-    pub fragments_scrubber: TimeScrubber,
-
-    // Marking state for filtered items
-    pub marked_messages: std::collections::HashSet<usize>,
-    pub marked_packets: std::collections::HashSet<usize>,
+    /// Whether the bookmarks side panel is open
+    pub show_bookmarks: bool,
 
     // Desktop: pending file from file dialog
     #[cfg(feature = "desktop")]
@@ -94,24 +102,26 @@ pub struct PcapViewerApp {
 impl Default for PcapViewerApp {
     fn default() -> Self {
         Self {
-            messages: Vec::new(),
-            packets: Vec::new(),
-            weenie_db: common::weenie::WeenieDatabase::new(),
-            current_tab: Tab::Messages,
-            selected_message: None,
-            selected_packet: None,
-            selected_weenie: None,
-            search_query: String::new(),
+            documents: vec![document::Document::default()],
+            active_document: 0,
+            capture: document::CaptureState::default(),
+            extract_columns: Vec::new(),
+            extract_input: String::new(),
             sort_field: SortField::Id,
             sort_ascending: true,
             view_mode: ViewMode::Tree,
             status_message: "Drag & drop a PCAP file or click 'Load Example'".to_string(),
             is_loading: false,
+            parse_jobs: Vec::new(),
+            extractor_rules: common::extractor_rules::ExtractorRegistry::builtin(),
+            value_names: common::value_names::ValueNames::new(),
+            compare_job: None,
             dark_mode: true,
             show_detail_panel: false,
             dropped_file_data: None,
             fetched_data: Arc::new(Mutex::new(None)),
             fetched_error: Arc::new(Mutex::new(None)),
+            fetched_name: String::new(),
             initial_url: None,
             initial_discord_load: false,
             base_pixels_per_point: None,
@@ -125,10 +135,7 @@ impl Default for PcapViewerApp {
             discord_load_error: None,
             show_error_dialog: false,
             error_dialog_message: String::new(),
-            messages_scrubber: TimeScrubber::new(),
-            fragments_scrubber: TimeScrubber::new(),
-            marked_messages: std::collections::HashSet::new(),
-            marked_packets: std::collections::HashSet::new(),
+            show_bookmarks: false,
             #[cfg(feature = "desktop")]
             pending_file_path: None,
         }
@@ -149,97 +156,83 @@ impl PcapViewerApp {
         self.show_error_dialog = true;
     }
 
-    /// Mark all currently filtered items for visual tracking (replaces previous marks)
-    fn mark_filtered_items(&mut self) {
-        let search = self.search_query.to_lowercase();
-
-        match self.current_tab {
-            Tab::Messages => {
-                // Clear previous marks before setting new ones
-                self.marked_messages.clear();
-
-                let time_filter = self.messages_scrubber.get_selected_range().cloned();
-
-                // Filter messages based on search and time
-                let filtered_indices: Vec<usize> = self
-                    .messages
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, m)| {
-                        // Apply search filter with rich filter support
-                        let matches_search = if search.is_empty() {
-                            true
-                        } else {
-                            // Parse search string into rich filters (supports hex, decimal, and text)
-                            let filters = crate::filter::parse_filter_string(&search);
-
-                            // Check if any filter matches
-                            let mut matches = false;
-
-                            // Search in message ID
-                            if crate::filter::matches_any_filter(&filters, &m.id.to_string()) {
-                                matches = true;
-                            }
-
-                            // Check opcode match
-                            if !matches && crate::filter::matches_any_filter(&filters, &m.opcode) {
-                                matches = true;
-                            }
-
-                            // Check direction match
-                            if !matches && crate::filter::matches_any_filter(&filters, &m.direction)
-                            {
-                                matches = true;
-                            }
-
-                            // Check if filter matches in data fields
-                            if !matches {
-                                let data_str = serde_json::to_string(&m.data).unwrap_or_default();
-                                if crate::filter::matches_any_filter(&filters, &data_str) {
-                                    matches = true;
-                                }
-                            }
-
-                            // Always also do text search (type and data)
-                            if !matches {
-                                let type_matches = m.message_type.to_lowercase().contains(&search);
-                                let data_matches =
-                                    crate::state::json_contains_string(&m.data, &search);
-                                matches = type_matches || data_matches;
-                            }
+    /// Index in `messages` of the message with `message_id`
+    pub fn message_index(&self, message_id: usize) -> Option<usize> {
+        // Messages are kept in the order they were numbered
+        self.capture
+            .messages
+            .binary_search_by_key(&message_id, |m| m.id)
+            .ok()
+    }
 
-                            matches
-                        };
+    /// Select the message with `message_id` in the Messages tab, scrolling it into view
+    /// and centering the time scrubber on it
+    pub fn go_to_message(&mut self, message_id: usize) {
+        let Some(idx) = self.message_index(message_id) else {
+            return;
+        };
+        self.capture
+            .messages_scrubber
+            .center_on(self.capture.messages[idx].timestamp);
+        self.capture.current_tab = Tab::Messages;
+        self.capture.selected_message = Some(idx);
+        self.capture.scroll_to_selected_message = true;
+    }
 
-                        // Apply time filter
-                        let matches_time = if let Some(ref range) = time_filter {
-                            range.contains(m.timestamp)
-                        } else {
-                            true
-                        };
+    /// Select the object with `object_id` in the Weenies tab
+    pub fn go_to_weenie(&mut self, object_id: u32) {
+        self.capture.current_tab = Tab::Weenies;
+        self.capture.selected_weenie = Some(object_id);
+    }
 
-                        matches_search && matches_time
-                    })
-                    .map(|(idx, _)| idx)
-                    .collect();
+    /// Indices of the messages matching the search and the messages scrubber's time range
+    pub fn filtered_message_indices(&self) -> Vec<usize> {
+        let (query, _) = Query::parse_or_text(&self.capture.search_query);
+        let start_time = self.capture.packets.first().map_or(0.0, |p| p.timestamp);
+        let time_filter = self.capture.messages_scrubber.get_selected_range();
+
+        self.capture
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| {
+                query.matches(m, start_time)
+                    && time_filter.is_none_or(|range| range.contains(m.timestamp))
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
 
+    /// Mark all currently filtered items for visual tracking (replaces previous marks)
+    fn mark_filtered_items(&mut self) {
+        match self.capture.current_tab {
+            Tab::Messages => {
                 // Set marked_messages to only the filtered indices
-                self.marked_messages = filtered_indices.into_iter().collect();
+                self.capture.marked_messages =
+                    self.filtered_message_indices().into_iter().collect();
 
                 // Update scrubber with marked timestamps
                 let marked_timestamps: Vec<f64> = self
+                    .capture
                     .messages
                     .iter()
                     .enumerate()
-                    .filter(|(idx, _)| self.marked_messages.contains(idx))
+                    .filter(|(idx, _)| self.capture.marked_messages.contains(idx))
                     .map(|(_, m)| m.timestamp)
                     .collect();
-                self.messages_scrubber
+                self.capture
+                    .messages_scrubber
                     .set_marked_timestamps(marked_timestamps);
             }
+            Tab::Fragments => {
+                // TODO: Implement packet search (only the time filter applies to packets)
+            }
             Tab::Weenies => {
                 // TODO: Implement weenie marking (weenies don't have timestamps yet)
             }
+            Tab::Diagnostics => {
+                // Diagnostics are filtered by the search box directly
+            }
         }
     }
 }
@@ -255,11 +248,14 @@ impl eframe::App for PcapViewerApp {
                         if let Some(path) = &file.path {
                             self.status_message = format!("Loading {}...", path.display());
                             match std::fs::read(path) {
-                                Ok(data) => self.dropped_file_data = Some(data),
+                                Ok(data) => {
+                                    self.dropped_file_data =
+                                        Some((ui::file_panel::file_name(path), data))
+                                }
                                 Err(e) => self.show_error(format!("Error reading file: {e}")),
                             }
                         } else if let Some(bytes) = &file.bytes {
-                            self.dropped_file_data = Some(bytes.to_vec());
+                            self.dropped_file_data = Some((file.name.clone(), bytes.to_vec()));
                         } else {
                             self.show_error(
                                 "Failed to read dropped file as path or byte array. Please report a bug.s",
@@ -269,7 +265,7 @@ impl eframe::App for PcapViewerApp {
                     #[cfg(not(feature = "desktop"))]
                     {
                         if let Some(bytes) = &file.bytes {
-                            self.dropped_file_data = Some(bytes.to_vec());
+                            self.dropped_file_data = Some((file.name.clone(), bytes.to_vec()));
                         } else {
                             self.show_error(
                                 "Failed to read dropped file as byte array. Please report a bug.s",
@@ -281,8 +277,12 @@ impl eframe::App for PcapViewerApp {
         });
 
         // Process dropped file data outside the input closure
-        if let Some(data) = self.dropped_file_data.take() {
-            ui::file_panel::parse_pcap_data(self, &data);
+        if let Some((name, data)) = self.dropped_file_data.take() {
+            if ui::file_panel::is_session_file(&name) {
+                self.open_session(&data);
+            } else {
+                ui::file_panel::parse_pcap_data(self, name, data, ctx);
+            }
         }
 
         // Desktop: process file from file dialog
@@ -290,7 +290,10 @@ impl eframe::App for PcapViewerApp {
         if let Some(path) = self.pending_file_path.take() {
             self.status_message = format!("Loading {}...", path.display());
             match std::fs::read(&path) {
-                Ok(data) => ui::file_panel::parse_pcap_data(self, &data),
+                Ok(data) => {
+                    let name = ui::file_panel::file_name(&path);
+                    ui::file_panel::parse_pcap_data(self, name, data, ctx)
+                }
                 Err(e) => self.show_error(format!("Error reading file: {e}")),
             }
         }
@@ -302,9 +305,14 @@ impl eframe::App for PcapViewerApp {
             None
         };
         if let Some(data) = fetched_data {
-            ui::file_panel::parse_pcap_data(self, &data);
+            let name = std::mem::take(&mut self.fetched_name);
+            ui::file_panel::parse_pcap_data(self, name, data, ctx);
         }
 
+        // Pick up the result of a background parse
+        ui::file_panel::poll_parse_jobs(self);
+        ui::diff_panel::poll_compare_job(self);
+
         // Check for async fetch errors
         let fetched_error = if let Ok(mut error) = self.fetched_error.try_lock() {
             error.take()
@@ -374,7 +382,7 @@ impl eframe::App for PcapViewerApp {
         let screen_height = screen_rect.height();
         let is_mobile = is_mobile_viewport;
         let is_tablet = (MOBILE_BREAKPOINT..TABLET_BREAKPOINT).contains(&viewport_width);
-        let has_data = !self.messages.is_empty() || !self.packets.is_empty();
+        let has_data = self.has_capture();
 
         // Debug mode string
         let mode_str = if is_mobile {
@@ -405,6 +413,88 @@ impl eframe::App for PcapViewerApp {
                         }
                     });
 
+                    #[cfg(all(not(target_arch = "wasm32"), feature = "desktop"))]
+                    if ui.button("Load Extractor Rules...").clicked() {
+                        ui::file_panel::open_rules_dialog(self);
+                        ui.close_menu();
+                    }
+                    #[cfg(all(not(target_arch = "wasm32"), feature = "desktop"))]
+                    if ui.button("Load Spell Table...").clicked() {
+                        ui::file_panel::open_spell_table_dialog(self);
+                        ui.close_menu();
+                    }
+                    #[cfg(all(not(target_arch = "wasm32"), feature = "desktop"))]
+                    if ui
+                        .add_enabled(has_data, egui::Button::new("Compare With..."))
+                        .clicked()
+                    {
+                        ui::diff_panel::open_compare_dialog(self, ctx);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    #[cfg(all(not(target_arch = "wasm32"), feature = "desktop"))]
+                    if ui.button("Open Session...").clicked() {
+                        ui::file_panel::open_session_dialog(self);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(has_data, egui::Button::new("Save Session..."))
+                        .on_hover_text("Save marks, bookmarks, notes and filter to share")
+                        .clicked()
+                    {
+                        ui::file_panel::save_session(self);
+                        ui.close_menu();
+                    }
+                    ui.add_enabled_ui(has_data, |ui| {
+                        ui.menu_button("Export", |ui| {
+                            for format in ExportFormat::ALL {
+                                if ui
+                                    .button(format!("Messages as {}...", format.label()))
+                                    .on_hover_text("Messages matching the search and time range")
+                                    .clicked()
+                                {
+                                    ui::file_panel::export_messages(self, format);
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            for format in [ExportFormat::Json, ExportFormat::Csv] {
+                                if ui
+                                    .button(format!("Weenies as {}...", format.label()))
+                                    .clicked()
+                                {
+                                    ui::file_panel::export_weenies(self, format);
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            for selection in ui::file_panel::PacketSelection::ALL {
+                                if ui
+                                    .button(format!("{} as PCAP...", selection.label()))
+                                    .on_hover_text(
+                                        "The original frames, to re-open here or in Wireshark",
+                                    )
+                                    .clicked()
+                                {
+                                    ui::file_panel::save_packets(self, selection);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+
+                    ui.separator();
+
+                    if ui
+                        .add_enabled(has_data, egui::Button::new("Close Tab"))
+                        .clicked()
+                    {
+                        self.close_document(self.active_document);
+                        ui.close_menu();
+                    }
+
                     ui.separator();
 
                     if ui.button("Settings...").clicked() {
@@ -420,6 +510,25 @@ impl eframe::App for PcapViewerApp {
                     }
                 });
 
+                ui.menu_button("Bookmarks", |ui| {
+                    ui.checkbox(&mut self.show_bookmarks, "Show Bookmarks Panel");
+                    if !self.capture.bookmarks.is_empty() {
+                        ui.separator();
+                    }
+                    let mut go_to = None;
+                    for bookmark in &self.capture.bookmarks {
+                        if ui.button(&bookmark.label).clicked() {
+                            go_to = Some(bookmark.target);
+                            ui.close_menu();
+                        }
+                    }
+                    match go_to {
+                        Some(BookmarkTarget::Message(id)) => self.go_to_message(id),
+                        Some(BookmarkTarget::Object(id)) => self.go_to_weenie(id),
+                        None => {}
+                    }
+                });
+
                 ui.menu_button("About", |ui| {
                     if ui.button("About AC PCAP Viewer").clicked() {
                         self.show_about = true;
@@ -438,6 +547,9 @@ impl eframe::App for PcapViewerApp {
             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
         }
 
+        // Open captures, when there's more than one
+        ui::file_panel::show_document_tabs(self, ctx);
+
         // Top panel with tabs and controls - responsive
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             if is_mobile {
@@ -469,31 +581,50 @@ impl eframe::App for PcapViewerApp {
                     // Second row: Tabs + minimal controls
                     ui.horizontal(|ui| {
                         if ui
-                            .selectable_label(self.current_tab == Tab::Messages, "Msg")
+                            .selectable_label(self.capture.current_tab == Tab::Messages, "Msg")
+                            .clicked()
+                        {
+                            self.capture.current_tab = Tab::Messages;
+                        }
+                        if ui
+                            .selectable_label(self.capture.current_tab == Tab::Fragments, "Frag")
                             .clicked()
                         {
-                            self.current_tab = Tab::Messages;
+                            self.capture.current_tab = Tab::Fragments;
                         }
                         if ui
-                            .selectable_label(self.current_tab == Tab::Weenies, "Obj")
+                            .selectable_label(self.capture.current_tab == Tab::Weenies, "Obj")
                             .clicked()
                         {
-                            self.current_tab = Tab::Weenies;
+                            self.capture.current_tab = Tab::Weenies;
+                        }
+                        if !self.capture.diagnostics.is_empty()
+                            && ui
+                                .selectable_label(
+                                    self.capture.current_tab == Tab::Diagnostics,
+                                    "Diag",
+                                )
+                                .clicked()
+                        {
+                            self.capture.current_tab = Tab::Diagnostics;
                         }
 
                         ui.separator();
 
                         // Search section
                         ui.add(
-                            egui::TextEdit::singleline(&mut self.search_query)
+                            egui::TextEdit::singleline(&mut self.capture.search_query)
                                 .hint_text("Filter...")
                                 .desired_width(60.0),
                         );
+                        if self.capture.current_tab == Tab::Messages {
+                            ui::packet_list::draw_query_error(ui, &self.capture.search_query);
+                        }
 
                         // Reset search button
-                        ui.add_enabled_ui(!self.search_query.is_empty(), |ui| {
+                        ui.add_enabled_ui(!self.capture.search_query.is_empty(), |ui| {
                             if ui.button("✕").on_hover_text("Clear filter").clicked() {
-                                self.search_query.clear();
+                                self.capture.search_query.clear();
                             }
                         });
 
@@ -503,7 +634,7 @@ impl eframe::App for PcapViewerApp {
                         ui.label("Mark:");
 
                         // Mark button (enabled when filter is active)
-                        ui.add_enabled_ui(!self.search_query.is_empty(), |ui| {
+                        ui.add_enabled_ui(!self.capture.search_query.is_empty(), |ui| {
                             if ui
                                 .button("◉")
                                 .on_hover_text("Mark filtered items")
@@ -514,20 +645,27 @@ impl eframe::App for PcapViewerApp {
                         });
 
                         // Reset marks button (enabled when there are marks)
-                        let has_marks = match self.current_tab {
-                            Tab::Messages => !self.marked_messages.is_empty(),
+                        let has_marks = match self.capture.current_tab {
+                            Tab::Messages => !self.capture.marked_messages.is_empty(),
+                            Tab::Fragments => !self.capture.marked_packets.is_empty(),
                             Tab::Weenies => false, // TODO: Implement weenie marking
+                            Tab::Diagnostics => false,
                         };
                         ui.add_enabled_ui(has_marks, |ui| {
                             if ui.button("✕").on_hover_text("Clear marks").clicked() {
-                                match self.current_tab {
+                                match self.capture.current_tab {
                                     Tab::Messages => {
-                                        self.marked_messages.clear();
-                                        self.messages_scrubber.clear_marked_timestamps();
+                                        self.capture.marked_messages.clear();
+                                        self.capture.messages_scrubber.clear_marked_timestamps();
+                                    }
+                                    Tab::Fragments => {
+                                        self.capture.marked_packets.clear();
+                                        self.capture.fragments_scrubber.clear_marked_timestamps();
                                     }
                                     Tab::Weenies => {
                                         // TODO: Implement weenie marking
                                     }
+                                    Tab::Diagnostics => {}
                                 }
                             }
                         });
@@ -559,16 +697,32 @@ impl eframe::App for PcapViewerApp {
 
                     // Tab buttons
                     if ui
-                        .selectable_label(self.current_tab == Tab::Messages, "Messages")
+                        .selectable_label(self.capture.current_tab == Tab::Messages, "Messages")
                         .clicked()
                     {
-                        self.current_tab = Tab::Messages;
+                        self.capture.current_tab = Tab::Messages;
                     }
                     if ui
-                        .selectable_label(self.current_tab == Tab::Weenies, "Weenies")
+                        .selectable_label(self.capture.current_tab == Tab::Fragments, "Fragments")
                         .clicked()
                     {
-                        self.current_tab = Tab::Weenies;
+                        self.capture.current_tab = Tab::Fragments;
+                    }
+                    if ui
+                        .selectable_label(self.capture.current_tab == Tab::Weenies, "Weenies")
+                        .clicked()
+                    {
+                        self.capture.current_tab = Tab::Weenies;
+                    }
+                    if !self.capture.diagnostics.is_empty()
+                        && ui
+                            .selectable_label(
+                                self.capture.current_tab == Tab::Diagnostics,
+                                format!("Diagnostics ({})", self.capture.diagnostics.len()),
+                            )
+                            .clicked()
+                    {
+                        self.capture.current_tab = Tab::Diagnostics;
                     }
 
                     ui.separator();
@@ -578,15 +732,18 @@ impl eframe::App for PcapViewerApp {
                         ui.label("Search:");
                     }
                     ui.add(
-                        egui::TextEdit::singleline(&mut self.search_query)
+                        egui::TextEdit::singleline(&mut self.capture.search_query)
                             .hint_text("Filter...")
                             .desired_width(if is_tablet { 100.0 } else { 120.0 }),
                     );
+                    if self.capture.current_tab == Tab::Messages {
+                        ui::packet_list::draw_query_error(ui, &self.capture.search_query);
+                    }
 
                     // Reset search button
-                    ui.add_enabled_ui(!self.search_query.is_empty(), |ui| {
+                    ui.add_enabled_ui(!self.capture.search_query.is_empty(), |ui| {
                         if ui.button("Reset").on_hover_text("Clear filter").clicked() {
-                            self.search_query.clear();
+                            self.capture.search_query.clear();
                         }
                     });
 
@@ -596,7 +753,7 @@ impl eframe::App for PcapViewerApp {
                     ui.label("Mark");
 
                     // Mark button (enabled when filter is active)
-                    ui.add_enabled_ui(!self.search_query.is_empty(), |ui| {
+                    ui.add_enabled_ui(!self.capture.search_query.is_empty(), |ui| {
                         if ui
                             .button("Mark")
                             .on_hover_text("Mark filtered items")
@@ -607,20 +764,27 @@ impl eframe::App for PcapViewerApp {
                     });
 
                     // Reset marks button (enabled when there are marks)
-                    let has_marks = match self.current_tab {
-                        Tab::Messages => !self.marked_messages.is_empty(),
+                    let has_marks = match self.capture.current_tab {
+                        Tab::Messages => !self.capture.marked_messages.is_empty(),
+                        Tab::Fragments => !self.capture.marked_packets.is_empty(),
                         Tab::Weenies => false, // TODO: Implement weenie marking
+                        Tab::Diagnostics => false,
                     };
                     ui.add_enabled_ui(has_marks, |ui| {
                         if ui.button("Reset").on_hover_text("Clear marks").clicked() {
-                            match self.current_tab {
+                            match self.capture.current_tab {
                                 Tab::Messages => {
-                                    self.marked_messages.clear();
-                                    self.messages_scrubber.clear_marked_timestamps();
+                                    self.capture.marked_messages.clear();
+                                    self.capture.messages_scrubber.clear_marked_timestamps();
+                                }
+                                Tab::Fragments => {
+                                    self.capture.marked_packets.clear();
+                                    self.capture.fragments_scrubber.clear_marked_timestamps();
                                 }
                                 Tab::Weenies => {
                                     // TODO: Implement weenie marking
                                 }
+                                Tab::Diagnostics => {}
                             }
                         }
                     });
@@ -636,7 +800,22 @@ impl eframe::App for PcapViewerApp {
         // Bottom panel with status - responsive
         egui::TopBottomPanel::bottom("status_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                if self.is_loading {
+                for (name, job) in &mut self.parse_jobs {
+                    ui.add(
+                        egui::ProgressBar::new(job.progress())
+                            .desired_width(if is_mobile { 80.0 } else { 160.0 })
+                            .text(format!(
+                                "{:.1} / {:.1} MB",
+                                job.bytes_read() as f64 / 1_048_576.0,
+                                job.total_bytes() as f64 / 1_048_576.0
+                            )),
+                    )
+                    .on_hover_text(name.as_str());
+                    if ui.small_button("Cancel").clicked() {
+                        job.cancel();
+                    }
+                }
+                if self.parse_jobs.is_empty() && self.is_loading {
                     ui.spinner();
                 }
 
@@ -650,7 +829,11 @@ impl eframe::App for PcapViewerApp {
                 );
 
                 if is_mobile {
-                    ui.label(format!("{} msgs {}", self.messages.len(), debug_info));
+                    ui.label(format!(
+                        "{} msgs {}",
+                        self.capture.messages.len(),
+                        debug_info
+                    ));
                 } else {
                     ui.label(format!("{} {}", &self.status_message, debug_info));
                 }
@@ -697,8 +880,8 @@ impl eframe::App for PcapViewerApp {
 
                         ui.label(format!(
                             "Messages: {} | Packets: {}",
-                            self.messages.len(),
-                            self.packets.len()
+                            self.capture.messages.len(),
+                            self.capture.packets.len()
                         ));
                     }
                 });
@@ -772,14 +955,23 @@ impl eframe::App for PcapViewerApp {
             }
         }
 
+        // Bookmarks panel, and their ticks on the time scrubbers
+        if has_data {
+            ui::bookmark_panel::show_bookmark_panel(self, ctx);
+        }
+        ui::bookmark_panel::update_scrubber_ticks(self);
+
         // Time scrubber panel (only show if we have data)
         // This panel is shown BELOW the central panel (list/detail pane)
         let mut clicked_time: Option<f64> = None;
         if has_data {
             // Check which scrubber has data
-            let scrubber_has_data = match self.current_tab {
-                Tab::Messages => self.messages_scrubber.has_data(),
-                Tab::Weenies => false, // Weenies don't have time scrubbers
+            let scrubber_has_data = match self.capture.current_tab {
+                Tab::Messages => self.capture.messages_scrubber.has_data(),
+                Tab::Fragments => self.capture.fragments_scrubber.has_data(),
+                Tab::Weenies => self.capture.weenies_scrubber.has_data(),
+                // Diagnostics don't have a time scrubber
+                Tab::Diagnostics => false,
             };
 
             if scrubber_has_data {
@@ -787,29 +979,42 @@ impl eframe::App for PcapViewerApp {
                     .resizable(false)
                     .show(ctx, |ui| {
                         // Show appropriate scrubber
-                        let result = match self.current_tab {
-                            Tab::Messages => self.messages_scrubber.show(ui),
-                            Tab::Weenies => unreachable!("Weenies don't have time scrubbers"),
+                        let result = match self.capture.current_tab {
+                            Tab::Messages => self.capture.messages_scrubber.show(ui),
+                            Tab::Fragments => self.capture.fragments_scrubber.show(ui),
+                            Tab::Weenies => self.capture.weenies_scrubber.show(ui),
+                            Tab::Diagnostics => {
+                                unreachable!("Diagnostics don't have a time scrubber")
+                            }
                         };
 
                         // Check if user clicked
                         if result.clicked_index.is_some() {
-                            clicked_time = match self.current_tab {
-                                Tab::Messages => self.messages_scrubber.get_hover_time(),
-                                Tab::Weenies => unreachable!("Weenies don't have time scrubbers"),
+                            clicked_time = match self.capture.current_tab {
+                                Tab::Messages => self.capture.messages_scrubber.get_hover_time(),
+                                Tab::Fragments => self.capture.fragments_scrubber.get_hover_time(),
+                                Tab::Weenies => self.capture.weenies_scrubber.get_hover_time(),
+                                Tab::Diagnostics => {
+                                    unreachable!("Diagnostics don't have a time scrubber")
+                                }
                             };
                         }
 
                         // Handle reset marks button
                         if result.reset_marks_clicked {
-                            match self.current_tab {
+                            match self.capture.current_tab {
                                 Tab::Messages => {
-                                    self.marked_messages.clear();
-                                    self.messages_scrubber.clear_marked_timestamps();
+                                    self.capture.marked_messages.clear();
+                                    self.capture.messages_scrubber.clear_marked_timestamps();
+                                }
+                                Tab::Fragments => {
+                                    self.capture.marked_packets.clear();
+                                    self.capture.fragments_scrubber.clear_marked_timestamps();
                                 }
                                 Tab::Weenies => {
                                     // TODO: Implement weenie marking
                                 }
+                                Tab::Diagnostics => {}
                             }
                         }
                     });
@@ -818,28 +1023,30 @@ impl eframe::App for PcapViewerApp {
 
         // Handle click-to-scroll from time scrubber
         if let Some(time) = clicked_time {
-            // Find the closest message to the clicked time
-            let closest_idx = self
-                .messages
-                .iter()
-                .enumerate()
-                .min_by(|(_, a), (_, b)| {
-                    let dist_a = (a.timestamp - time).abs();
-                    let dist_b = (b.timestamp - time).abs();
-                    dist_a
-                        .partial_cmp(&dist_b)
-                        .unwrap_or(std::cmp::Ordering::Equal)
-                })
-                .map(|(idx, _)| idx);
-
-            if let Some(idx) = closest_idx {
-                self.selected_message = Some(idx);
+            match self.capture.current_tab {
+                Tab::Messages => {
+                    // Find the closest message to the clicked time
+                    let timestamps = self.capture.messages.iter().map(|m| m.timestamp);
+                    if let Some(idx) = closest_timestamp_index(timestamps, time) {
+                        self.capture.selected_message = Some(idx);
+                    }
+                }
+                Tab::Fragments => {
+                    // Find the closest packet to the clicked time
+                    let timestamps = self.capture.packets.iter().map(|p| p.timestamp);
+                    if let Some(idx) = closest_timestamp_index(timestamps, time) {
+                        self.capture.selected_packet = Some(idx);
+                    }
+                }
+                // Show every object as it was at that moment until unpinned
+                Tab::Weenies => self.capture.weenie_pinned_time = Some(time),
+                Tab::Diagnostics => {}
             }
         }
 
         // Central panel with list - responsive
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.messages.is_empty() && self.packets.is_empty() {
+            if self.capture.messages.is_empty() && self.capture.packets.is_empty() {
                 // Show drop zone with Load Example button - responsive
                 ui.vertical_centered(|ui| {
                     ui.add_space(ui.available_height() / 3.0);
@@ -974,9 +1181,13 @@ impl eframe::App for PcapViewerApp {
                 });
             } else {
                 // On mobile, auto-show detail when selecting an item
-                match self.current_tab {
+                match self.capture.current_tab {
                     Tab::Messages => ui::packet_list::show_messages_list(self, ui, is_mobile),
+                    Tab::Fragments => ui::packet_list::show_packets_list(self, ui, is_mobile),
                     Tab::Weenies => ui::weenie_panel::show_weenie_panel(self, ui, is_mobile),
+                    Tab::Diagnostics => {
+                        ui::diagnostics_panel::show_diagnostics_list(self, ui, is_mobile)
+                    }
                 }
             }
         });
@@ -996,9 +1207,26 @@ impl eframe::App for PcapViewerApp {
             ui::file_panel::show_about_dialog(self, ctx);
         }
 
+        // Capture comparison window
+        ui::diff_panel::show_compare_window(self, ctx);
+
         // Error dialog
         ui::error_dialog::show_error_dialog(self, ctx);
     }
 }
 
 impl PcapViewerApp {}
+
+/// Find the index of the timestamp closest to `time`
+fn closest_timestamp_index(timestamps: impl Iterator<Item = f64>, time: f64) -> Option<usize> {
+    timestamps
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            let dist_a = (a - time).abs();
+            let dist_b = (b - time).abs();
+            dist_a
+                .partial_cmp(&dist_b)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|(idx, _)| idx)
+}
@@ -0,0 +1,318 @@
+//! Parsing captures without blocking the UI
+//!
+//! On desktop the capture is parsed on a worker thread. The browser has no threads, so on
+//! wasm the stream is advanced a bounded number of events each frame instead. Either way
+//! the UI polls the job every frame for progress and the result. The capture's hash is
+//! computed alongside the parse, off the UI thread on desktop and a chunk per frame on wasm.
+//! The capture's bytes are handed back with the result, since packets point into them.
+
+use common::PacketParser;
+use common::extractor_rules::ExtractorRegistry;
+use common::stream::ParsedCapture;
+use eframe::egui;
+use std::sync::Arc;
+
+/// A parsed capture and the hash of its bytes, which sessions refer to it by
+pub struct LoadedCapture {
+    pub capture: ParsedCapture,
+    pub capture_hash: String,
+    /// The bytes that were parsed
+    pub data: Vec<u8>,
+}
+
+/// State of a parse job after polling
+pub enum ParseStatus {
+    Running,
+    Finished(Result<Box<LoadedCapture>, String>),
+    Cancelled,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod imp {
+    use super::*;
+    use std::any::Any;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+    use std::thread::JoinHandle;
+
+    /// How many events to process between repaint requests
+    const REPAINT_INTERVAL: usize = 5000;
+
+    #[derive(Default)]
+    struct Shared {
+        bytes_read: AtomicU64,
+        cancel: AtomicBool,
+        result: Mutex<Option<Result<Box<LoadedCapture>, String>>>,
+    }
+
+    pub struct Worker {
+        shared: Arc<Shared>,
+        /// Taken once the thread is found to have ended without a result
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Worker {
+        pub fn start(data: Vec<u8>, rules: Arc<ExtractorRegistry>, ctx: &egui::Context) -> Self {
+            let shared = Arc::new(Shared::default());
+            let worker_shared = shared.clone();
+            let ctx = ctx.clone();
+
+            let thread = std::thread::spawn(move || {
+                // Repaint however the thread ends, so a panic is noticed too
+                struct RepaintOnExit(egui::Context);
+                impl Drop for RepaintOnExit {
+                    fn drop(&mut self) {
+                        self.0.request_repaint();
+                    }
+                }
+                let _repaint = RepaintOnExit(ctx.clone());
+
+                let result = parse(data, rules, &worker_shared, &ctx);
+                if let Ok(mut slot) = worker_shared.result.lock() {
+                    *slot = result;
+                }
+            });
+
+            Self {
+                shared,
+                thread: Some(thread),
+            }
+        }
+
+        pub fn bytes_read(&self) -> u64 {
+            self.shared.bytes_read.load(Ordering::Relaxed)
+        }
+
+        pub fn cancel(&mut self) {
+            self.shared.cancel.store(true, Ordering::Relaxed);
+        }
+
+        pub fn poll(&mut self) -> ParseStatus {
+            if self.shared.cancel.load(Ordering::Relaxed) {
+                return ParseStatus::Cancelled;
+            }
+            if let Some(result) = self.take_result() {
+                return ParseStatus::Finished(result);
+            }
+
+            // A thread that ended without leaving a result panicked
+            if !self.thread.as_ref().is_some_and(JoinHandle::is_finished) {
+                return ParseStatus::Running;
+            }
+            if let Some(result) = self.take_result() {
+                return ParseStatus::Finished(result);
+            }
+            let reason = match self.thread.take().map(JoinHandle::join) {
+                Some(Err(panic)) => panic_message(panic.as_ref()),
+                _ => "the parser stopped without a result".to_string(),
+            };
+            ParseStatus::Finished(Err(format!("Parsing failed: {reason}")))
+        }
+
+        fn take_result(&self) -> Option<Result<Box<LoadedCapture>, String>> {
+            self.shared.result.try_lock().ok()?.take()
+        }
+    }
+
+    /// The message a thread panicked with
+    fn panic_message(panic: &(dyn Any + Send)) -> String {
+        panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "the parser panicked".to_string())
+    }
+
+    /// Parse on the worker thread, returning `None` if cancelled
+    fn parse(
+        data: Vec<u8>,
+        rules: Arc<ExtractorRegistry>,
+        shared: &Shared,
+        ctx: &egui::Context,
+    ) -> Option<Result<Box<LoadedCapture>, String>> {
+        let capture_hash = common::session::capture_hash(&data);
+
+        let mut stream = match PacketParser::with_rules(rules).stream(data.as_slice()) {
+            Ok(stream) => stream,
+            Err(e) => return Some(Err(format!("{e:#}"))),
+        };
+        let mut capture = ParsedCapture::new();
+        let mut count = 0;
+
+        while let Some(event) = stream.next() {
+            if shared.cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            capture.push(event);
+            shared
+                .bytes_read
+                .store(stream.bytes_read(), Ordering::Relaxed);
+
+            count += 1;
+            if count % REPAINT_INTERVAL == 0 {
+                ctx.request_repaint();
+            }
+        }
+
+        Some(Ok(Box::new(LoadedCapture {
+            capture,
+            capture_hash,
+            data,
+        })))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    use super::*;
+    use common::session::CaptureHasher;
+    use common::stream::PacketStream;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    /// How many events to process each frame
+    const EVENTS_PER_FRAME: usize = 2000;
+
+    /// How many bytes of the capture to hash each frame
+    const HASH_BYTES_PER_FRAME: usize = 1 << 20;
+
+    /// The capture's bytes, read by the stream and the hasher alike
+    #[derive(Clone)]
+    struct SharedBytes(Rc<Vec<u8>>);
+
+    impl AsRef<[u8]> for SharedBytes {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    pub struct Worker {
+        data: SharedBytes,
+        stream: Option<PacketStream<Cursor<SharedBytes>>>,
+        capture: ParsedCapture,
+        hasher: CaptureHasher,
+        /// Bytes of `data` hashed so far
+        hashed: usize,
+        error: Option<String>,
+        bytes_read: u64,
+        cancelled: bool,
+        ctx: egui::Context,
+    }
+
+    impl Worker {
+        pub fn start(data: Vec<u8>, rules: Arc<ExtractorRegistry>, ctx: &egui::Context) -> Self {
+            let data = SharedBytes(Rc::new(data));
+            let parser = PacketParser::with_rules(rules);
+            let (stream, error) = match parser.stream(Cursor::new(data.clone())) {
+                Ok(stream) => (Some(stream), None),
+                Err(e) => (None, Some(format!("{e:#}"))),
+            };
+            Self {
+                data,
+                stream,
+                capture: ParsedCapture::new(),
+                hasher: CaptureHasher::default(),
+                hashed: 0,
+                error,
+                bytes_read: 0,
+                cancelled: false,
+                ctx: ctx.clone(),
+            }
+        }
+
+        pub fn bytes_read(&self) -> u64 {
+            self.bytes_read
+        }
+
+        pub fn cancel(&mut self) {
+            self.cancelled = true;
+            self.stream = None;
+        }
+
+        pub fn poll(&mut self) -> ParseStatus {
+            if self.cancelled {
+                return ParseStatus::Cancelled;
+            }
+            if let Some(error) = self.error.take() {
+                return ParseStatus::Finished(Err(error));
+            }
+
+            let data = self.data.as_ref();
+            let end = data.len().min(self.hashed + HASH_BYTES_PER_FRAME);
+            self.hasher.update(&data[self.hashed..end]);
+            self.hashed = end;
+
+            if let Some(stream) = self.stream.as_mut() {
+                let mut exhausted = false;
+                for _ in 0..EVENTS_PER_FRAME {
+                    let Some(event) = stream.next() else {
+                        exhausted = true;
+                        break;
+                    };
+                    self.capture.push(event);
+                }
+                self.bytes_read = stream.bytes_read();
+                if exhausted {
+                    self.stream = None;
+                }
+            }
+
+            if self.stream.is_some() || self.hashed < self.data.as_ref().len() {
+                // Keep frames coming until the stream is exhausted and the hash complete
+                self.ctx.request_repaint();
+                return ParseStatus::Running;
+            }
+            // The stream is gone, so the bytes are only shared with this worker
+            let data = std::mem::take(&mut self.data.0);
+            ParseStatus::Finished(Ok(Box::new(LoadedCapture {
+                capture: std::mem::take(&mut self.capture),
+                capture_hash: std::mem::take(&mut self.hasher).finish(),
+                data: Rc::try_unwrap(data).unwrap_or_else(|data| data.as_ref().clone()),
+            })))
+        }
+    }
+}
+
+/// A capture being parsed in the background
+pub struct ParseJob {
+    worker: imp::Worker,
+    total_bytes: u64,
+}
+
+impl ParseJob {
+    /// Start parsing `data` with the weenie extractor `rules`, requesting repaints on `ctx`
+    /// as progress is made
+    pub fn start(data: Vec<u8>, rules: Arc<ExtractorRegistry>, ctx: &egui::Context) -> Self {
+        let total_bytes = data.len() as u64;
+        Self {
+            worker: imp::Worker::start(data, rules, ctx),
+            total_bytes,
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.worker.bytes_read()
+    }
+
+    /// Fraction of the capture parsed, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        (self.bytes_read() as f64 / self.total_bytes as f64) as f32
+    }
+
+    /// Stop parsing; the next poll reports `Cancelled`
+    pub fn cancel(&mut self) {
+        self.worker.cancel();
+    }
+
+    /// Check on the job, taking the result once it has finished
+    pub fn poll(&mut self) -> ParseStatus {
+        self.worker.poll()
+    }
+}
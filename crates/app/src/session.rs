@@ -0,0 +1,140 @@
+//! Saving the shown capture's analysis to a session and restoring it
+
+use crate::PcapViewerApp;
+use crate::time_scrubber::TimeRange;
+use common::session::{FilterState, Session};
+use std::collections::HashMap;
+
+impl PcapViewerApp {
+    /// The marks, bookmarks and filter of the shown capture
+    pub fn session(&self) -> Session {
+        let name = &self.documents[self.active_document].name;
+        let mut session = Session::new(&self.capture.hash, name.as_str());
+
+        session.marked_messages = self
+            .capture
+            .marked_messages
+            .iter()
+            .filter_map(|&idx| self.capture.messages.get(idx).map(|m| m.id))
+            .collect();
+        session.marked_messages.sort_unstable();
+        session.marked_packets = self
+            .capture
+            .marked_packets
+            .iter()
+            .filter_map(|&idx| self.capture.packets.get(idx).map(|p| p.id))
+            .collect();
+        session.marked_packets.sort_unstable();
+        session.bookmarks = self.capture.bookmarks.clone();
+
+        let scrubber = &self.capture.messages_scrubber;
+        let time_range = match (scrubber.get_selected_range(), &scrubber.data_range) {
+            (Some(selected), Some(data)) if !selected.is_full_range(data.min, data.max) => {
+                Some((selected.min, selected.max))
+            }
+            _ => None,
+        };
+        session.filter = FilterState {
+            tab: self.capture.current_tab,
+            search_query: self.capture.search_query.clone(),
+            time_range,
+            selected_message: self
+                .capture
+                .selected_message
+                .and_then(|idx| self.capture.messages.get(idx))
+                .map(|m| m.id),
+        };
+        session
+    }
+
+    /// Restore `session` onto the shown capture, replacing its marks and bookmarks
+    pub fn apply_session(&mut self, session: Session) {
+        self.capture.marked_messages = session
+            .marked_messages
+            .iter()
+            .filter_map(|&id| self.message_index(id))
+            .collect();
+        let packet_index: HashMap<usize, usize> = self
+            .capture
+            .packets
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| (p.id, idx))
+            .collect();
+        self.capture.marked_packets = session
+            .marked_packets
+            .iter()
+            .filter_map(|id| packet_index.get(id).copied())
+            .collect();
+        let marked_timestamps = self
+            .capture
+            .marked_messages
+            .iter()
+            .map(|&idx| self.capture.messages[idx].timestamp)
+            .collect();
+        self.capture
+            .messages_scrubber
+            .set_marked_timestamps(marked_timestamps);
+
+        self.capture.bookmarks = session.bookmarks;
+
+        let filter = session.filter;
+        self.capture.current_tab = filter.tab;
+        self.capture.search_query = filter.search_query;
+        match filter.time_range {
+            Some((min, max)) => {
+                self.capture.messages_scrubber.selected_range = Some(TimeRange::new(min, max))
+            }
+            None => self.capture.messages_scrubber.reset_selection(),
+        }
+        self.capture.selected_field = None;
+        self.capture.selected_message = filter
+            .selected_message
+            .and_then(|id| self.message_index(id));
+        self.capture.scroll_to_selected_message = self.capture.selected_message.is_some();
+    }
+
+    /// Open a session from the bytes of an `.acsession` file, showing the capture it's
+    /// for if that capture is open
+    pub fn open_session(&mut self, data: &[u8]) {
+        let session = match std::str::from_utf8(data)
+            .map_err(anyhow::Error::from)
+            .and_then(Session::from_json)
+        {
+            Ok(session) => session,
+            Err(e) => {
+                self.show_error(format!("Error opening session: {e:#}"));
+                return;
+            }
+        };
+
+        if !session.is_for(&self.capture.hash) {
+            let open = self
+                .documents
+                .iter()
+                .position(|d| session.is_for(&d.capture.hash));
+            match open {
+                Some(idx) => self.switch_document(idx),
+                None => {
+                    self.show_error(format!(
+                        "This session is for {}, which isn't open. Open that capture first, \
+                         then the session.",
+                        if session.capture_name.is_empty() {
+                            "another capture"
+                        } else {
+                            session.capture_name.as_str()
+                        }
+                    ));
+                    return;
+                }
+            }
+        }
+
+        self.status_message = format!(
+            "Opened session with {} marks and {} bookmarks",
+            session.marked_messages.len() + session.marked_packets.len(),
+            session.bookmarks.len()
+        );
+        self.apply_session(session);
+    }
+}
@@ -34,6 +34,14 @@ impl TimeRange {
     }
 }
 
+/// A labeled line drawn on the scrubber, e.g. for a bookmark
+#[derive(Clone, Debug)]
+pub struct ScrubberTick {
+    pub time: f64,
+    pub label: String,
+    pub color: egui::Color32,
+}
+
 /// Time scrubber state
 pub struct TimeScrubber {
     /// Current selected time range
@@ -50,6 +58,8 @@ pub struct TimeScrubber {
     highlighted_timestamps: Vec<f64>,
     /// Marked timestamps (purple indicators)
     marked_timestamps: Vec<f64>,
+    /// Labeled ticks, in time order
+    ticks: Vec<ScrubberTick>,
 }
 
 impl Default for TimeScrubber {
@@ -68,6 +78,7 @@ impl TimeScrubber {
             hover_time: None,
             highlighted_timestamps: Vec::new(),
             marked_timestamps: Vec::new(),
+            ticks: Vec::new(),
         }
     }
 
@@ -117,6 +128,22 @@ impl TimeScrubber {
         }
     }
 
+    /// Move the selection to be centered on `time`, keeping its width
+    ///
+    /// A selection of all the data already shows every instant, so it's left alone.
+    pub fn center_on(&mut self, time: f64) {
+        let (Some(selected), Some(data)) = (self.selected_range.as_mut(), self.data_range.as_ref())
+        else {
+            return;
+        };
+        if selected.is_full_range(data.min, data.max) {
+            return;
+        }
+        let width = selected.max - selected.min;
+        let min = (time - width / 2.0).clamp(data.min, (data.max - width).max(data.min));
+        *selected = TimeRange::new(min, min + width);
+    }
+
     /// Check if we have data
     pub fn has_data(&self) -> bool {
         self.data_range.is_some() && !self.density_data.is_empty()
@@ -139,6 +166,12 @@ impl TimeScrubber {
         self.marked_timestamps.clear();
     }
 
+    /// Set the labeled ticks to draw
+    pub fn set_ticks(&mut self, mut ticks: Vec<ScrubberTick>) {
+        ticks.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.ticks = ticks;
+    }
+
     /// Check if there are any marked timestamps
     pub fn has_marked_timestamps(&self) -> bool {
         !self.marked_timestamps.is_empty()
@@ -262,6 +295,26 @@ impl TimeScrubber {
                 }
             }
 
+            // Draw labeled ticks, labelling each unless it would overlap the label before
+            let tick_x = |time: f64| {
+                rect.min.x + ((time - data_range.min) / time_range) as f32 * rect.width()
+            };
+            let mut label_end = f32::NEG_INFINITY;
+            for tick in &self.ticks {
+                let x = tick_x(tick.time);
+                painter.vline(x, rect.y_range(), egui::Stroke::new(2.0, tick.color));
+                if x > label_end {
+                    let galley = painter.layout_no_wrap(
+                        tick.label.clone(),
+                        egui::FontId::proportional(11.0),
+                        tick.color,
+                    );
+                    let pos = egui::pos2(x + 3.0, rect.min.y + 1.0);
+                    label_end = pos.x + galley.size().x + 4.0;
+                    painter.galley(pos, galley, tick.color);
+                }
+            }
+
             // Draw selected range overlay
             if !selected_range.is_full_range(data_range.min, data_range.max) {
                 let sel_start_x = rect.min.x
@@ -313,9 +366,15 @@ impl TimeScrubber {
                         egui::Stroke::new(1.0, hover_color.gamma_multiply(0.5)),
                     );
 
-                    // Prepare tooltip
+                    // Prepare tooltip, naming the ticks under the pointer
                     show_tooltip = true;
                     tooltip_text = format!("Time: {:.3}s", hover_time_val - data_range.min);
+                    for tick in &self.ticks {
+                        if (tick_x(tick.time) - pointer_pos.x).abs() <= 4.0 {
+                            tooltip_text.push('\n');
+                            tooltip_text.push_str(&tick.label);
+                        }
+                    }
                 }
             } else {
                 self.hover_time = None;
@@ -373,6 +432,15 @@ impl TimeScrubber {
         self.selected_range.as_ref()
     }
 
+    /// The instant the scrubber points at: the hovered time, else the end of the selection
+    pub fn focus_time(&self) -> Option<f64> {
+        if self.hover_time.is_some() {
+            return self.hover_time;
+        }
+        let (selected, data) = (self.selected_range.as_ref()?, self.data_range.as_ref()?);
+        (!selected.is_full_range(data.min, data.max)).then_some(selected.max)
+    }
+
     /// Get the last hover time (used for click-to-scroll)
     pub fn get_hover_time(&self) -> Option<f64> {
         self.hover_time
@@ -395,6 +463,62 @@ mod tests {
         assert!(scrubber.highlighted_timestamps.is_empty());
     }
 
+    #[test]
+    fn test_focus_time_follows_selection() {
+        let mut scrubber = TimeScrubber::new();
+        assert_eq!(scrubber.focus_time(), None);
+
+        scrubber.update_density(&[1.0, 2.0, 3.0, 4.0]);
+        // The full range doesn't point anywhere in particular
+        assert_eq!(scrubber.focus_time(), None);
+
+        scrubber.selected_range = Some(TimeRange::new(1.5, 2.5));
+        assert_eq!(scrubber.focus_time(), Some(2.5));
+
+        scrubber.hover_time = Some(3.5);
+        assert_eq!(scrubber.focus_time(), Some(3.5));
+    }
+
+    #[test]
+    fn test_center_on_keeps_selection_width() {
+        let mut scrubber = TimeScrubber::new();
+        scrubber.update_density(&[0.0, 10.0, 20.0]);
+
+        // Everything is already in view
+        scrubber.center_on(15.0);
+        assert!(
+            scrubber
+                .selected_range
+                .as_ref()
+                .unwrap()
+                .is_full_range(0.0, 20.0)
+        );
+
+        scrubber.selected_range = Some(TimeRange::new(2.0, 6.0));
+        scrubber.center_on(10.0);
+        let range = scrubber.selected_range.clone().unwrap();
+        assert_eq!((range.min, range.max), (8.0, 12.0));
+
+        // Stays within the data
+        scrubber.center_on(19.0);
+        let range = scrubber.selected_range.clone().unwrap();
+        assert_eq!((range.min, range.max), (16.0, 20.0));
+    }
+
+    #[test]
+    fn test_ticks_are_kept_in_time_order() {
+        let mut scrubber = TimeScrubber::new();
+        let tick = |time: f64, label: &str| ScrubberTick {
+            time,
+            label: label.to_string(),
+            color: egui::Color32::WHITE,
+        };
+
+        scrubber.set_ticks(vec![tick(3.0, "Logout"), tick(1.0, "Login")]);
+        let labels: Vec<&str> = scrubber.ticks.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, ["Login", "Logout"]);
+    }
+
     #[test]
     fn test_set_highlighted_timestamps() {
         let mut scrubber = TimeScrubber::new();
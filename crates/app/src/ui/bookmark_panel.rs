@@ -0,0 +1,188 @@
+//! Bookmarks: labeled, colored notes on messages and objects
+
+use crate::PcapViewerApp;
+use crate::time_scrubber::ScrubberTick;
+use crate::ui::weenie_panel::object_label;
+use common::session::{Bookmark, BookmarkTarget};
+use eframe::egui;
+use egui::ScrollArea;
+
+fn color32(color: [u8; 3]) -> egui::Color32 {
+    egui::Color32::from_rgb(color[0], color[1], color[2])
+}
+
+/// Toggle a bookmark on `target`, and edit its label, color and note once it's bookmarked
+pub fn show_bookmark_editor(
+    app: &mut PcapViewerApp,
+    ui: &mut egui::Ui,
+    target: BookmarkTarget,
+    default_label: impl FnOnce() -> String,
+) {
+    let idx = app
+        .capture
+        .bookmarks
+        .iter()
+        .position(|b| b.target == target);
+
+    let mut toggle = false;
+    ui.horizontal(|ui| {
+        let label = if idx.is_some() {
+            "★ Bookmarked"
+        } else {
+            "☆ Bookmark"
+        };
+        toggle = ui.selectable_label(idx.is_some(), label).clicked();
+        if let Some(idx) = idx {
+            let bookmark = &mut app.capture.bookmarks[idx];
+            ui.color_edit_button_srgb(&mut bookmark.color);
+            ui.add(egui::TextEdit::singleline(&mut bookmark.label).hint_text("Label"));
+        }
+    });
+    if let Some(idx) = idx
+        && !toggle
+    {
+        ui.add(
+            egui::TextEdit::multiline(&mut app.capture.bookmarks[idx].note)
+                .hint_text("Note")
+                .desired_rows(1)
+                .desired_width(f32::INFINITY),
+        );
+    }
+
+    if toggle {
+        match idx {
+            Some(idx) => {
+                app.capture.bookmarks.remove(idx);
+            }
+            None => app
+                .capture
+                .bookmarks
+                .push(Bookmark::new(target, default_label())),
+        }
+    }
+}
+
+/// Where a bookmark is, e.g. `#12 Item_CreateObject at 3.250s`
+fn describe(app: &PcapViewerApp, bookmark: &Bookmark, start: f64) -> String {
+    match bookmark.target {
+        BookmarkTarget::Message(id) => match app.message_index(id) {
+            Some(idx) => {
+                let message = &app.capture.messages[idx];
+                format!(
+                    "#{id} {} at {:.3}s",
+                    message.message_type,
+                    message.timestamp - start
+                )
+            }
+            None => format!("Message #{id}, not in this capture"),
+        },
+        BookmarkTarget::Object(id) => object_label(id, app.capture.weenie_db.name_of(id)),
+    }
+}
+
+/// Show the bookmarks side panel, if it's open
+pub fn show_bookmark_panel(app: &mut PcapViewerApp, ctx: &egui::Context) {
+    if !app.show_bookmarks {
+        return;
+    }
+    let start = app.capture.messages.first().map_or(0.0, |m| m.timestamp);
+    let places: Vec<String> = app
+        .capture
+        .bookmarks
+        .iter()
+        .map(|b| describe(app, b, start))
+        .collect();
+    let mut go_to = None;
+    let mut remove = None;
+
+    egui::SidePanel::left("bookmarks_panel")
+        .resizable(true)
+        .default_width(260.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Bookmarks");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("×").clicked() {
+                        app.show_bookmarks = false;
+                    }
+                });
+            });
+            ui.separator();
+
+            if app.capture.bookmarks.is_empty() {
+                ui.label("Bookmark a message or object from its details to list it here");
+                return;
+            }
+
+            ScrollArea::vertical()
+                .id_salt("bookmarks_scroll")
+                .show(ui, |ui| {
+                    for (idx, bookmark) in app.capture.bookmarks.iter_mut().enumerate() {
+                        ui.push_id(idx, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.color_edit_button_srgb(&mut bookmark.color);
+                                if ui
+                                    .link(egui::RichText::new(&bookmark.label).strong())
+                                    .clicked()
+                                {
+                                    go_to = Some(bookmark.target);
+                                }
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        if ui.small_button("×").on_hover_text("Remove").clicked() {
+                                            remove = Some(idx);
+                                        }
+                                    },
+                                );
+                            });
+                            ui.small(&places[idx]);
+                            if !bookmark.note.is_empty() {
+                                ui.label(&bookmark.note);
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+        });
+
+    if let Some(idx) = remove {
+        app.capture.bookmarks.remove(idx);
+    }
+    match go_to {
+        Some(BookmarkTarget::Message(id)) => app.go_to_message(id),
+        Some(BookmarkTarget::Object(id)) => app.go_to_weenie(id),
+        None => {}
+    }
+}
+
+/// Draw bookmarks as labeled ticks on the time scrubbers: messages where they were sent,
+/// objects where they were first seen
+pub fn update_scrubber_ticks(app: &mut PcapViewerApp) {
+    let mut message_ticks = Vec::new();
+    let mut object_ticks = Vec::new();
+    for bookmark in &app.capture.bookmarks {
+        let tick = |time| ScrubberTick {
+            time,
+            label: bookmark.label.clone(),
+            color: color32(bookmark.color),
+        };
+        match bookmark.target {
+            BookmarkTarget::Message(id) => {
+                if let Some(idx) = app.message_index(id) {
+                    message_ticks.push(tick(app.capture.messages[idx].timestamp));
+                }
+            }
+            BookmarkTarget::Object(id) => {
+                if let Some(weenie) = app.capture.weenie_db.get(id) {
+                    object_ticks.push(tick(weenie.first_seen));
+                }
+            }
+        }
+    }
+    app.capture
+        .fragments_scrubber
+        .set_ticks(message_ticks.clone());
+    app.capture.messages_scrubber.set_ticks(message_ticks);
+    app.capture.weenies_scrubber.set_ticks(object_ticks);
+}
@@ -1,14 +1,19 @@
 //! Detail panel UI components for displaying message/packet details
 
+use crate::ui::bookmark_panel::show_bookmark_editor;
 use crate::ui::hyper_tree::AcJsonTree;
+use crate::ui::weenie_panel::object_label;
 use crate::{PcapViewerApp, Tab, ViewMode};
+use common::extract::FieldPath;
 use common::messages::ParsedMessage;
+use common::packet_flags::format_packet_flags;
+use common::session::BookmarkTarget;
 use eframe::egui;
 
 /// Show detail content in the detail panel
 pub fn show_detail_content(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
     // For Weenies tab, show custom weenie detail view
-    if app.current_tab == Tab::Weenies {
+    if app.capture.current_tab == Tab::Weenies {
         super::weenie_panel::show_weenie_detail(app, ui);
         return;
     }
@@ -21,14 +26,26 @@ pub fn show_detail_content(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
     });
     ui.separator();
 
-    // Track filter clicks to update after the match block
+    // For Fragments and Diagnostics tabs, show the decoded transport headers of the
+    // selected packet
+    if matches!(app.capture.current_tab, Tab::Fragments | Tab::Diagnostics) {
+        show_packet_detail(app, ui);
+        return;
+    }
+
+    show_message_bookmark(app, ui);
+    show_message_objects(app, ui);
+
+    // Track filter and field clicks to update after the match block
     let mut filter_value: Option<String> = None;
+    let mut field_clicked: Option<(usize, String)> = None;
+    let mut object_clicked: Option<u32> = None;
 
     match app.view_mode {
         ViewMode::JSON => {
-            if let Some(idx) = app.selected_message {
-                if idx < app.messages.len() {
-                    show_pretty_json(ui, &app.messages[idx].data);
+            if let Some(idx) = app.capture.selected_message {
+                if idx < app.capture.messages.len() {
+                    show_pretty_json(ui, &app.capture.messages[idx].data);
                 } else {
                     ui.label("No message selected");
                 }
@@ -37,13 +54,27 @@ pub fn show_detail_content(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
             }
         }
         ViewMode::Tree => {
-            if let Some(idx) = app.selected_message {
-                if idx < app.messages.len() {
+            if let Some(idx) = app.capture.selected_message {
+                if idx < app.capture.messages.len() {
+                    app.capture.messages[idx].ensure_field_offsets();
+                    let message = &app.capture.messages[idx];
                     let tree_id = format!("message_tree_{idx}");
-                    let response = AcJsonTree::new(&tree_id).show(ui, &app.messages[idx].data);
+                    let mut tree = AcJsonTree::new(&tree_id);
+                    if let Some(offsets) = &message.field_offsets {
+                        tree = tree.with_field_offsets(offsets);
+                    }
+                    let response = tree
+                        .with_selected_field(selected_field_path(app, idx))
+                        .with_weenies(&app.capture.weenie_db)
+                        .with_value_names(&app.value_names)
+                        .show(ui, &message.data);
+                    object_clicked = response.object_clicked;
                     if let Some(value) = response.filter_clicked {
                         filter_value = Some(value);
                     }
+                    if let Some(path) = response.field_clicked {
+                        field_clicked = Some((idx, path));
+                    }
                 } else {
                     ui.label("No message selected");
                 }
@@ -52,9 +83,9 @@ pub fn show_detail_content(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
             }
         }
         ViewMode::Binary => {
-            if let Some(idx) = app.selected_message {
-                if idx < app.messages.len() {
-                    show_hex_dump(ui, &app.messages[idx]);
+            if let Some(idx) = app.capture.selected_message {
+                if idx < app.capture.messages.len() {
+                    show_hex_dump(app, ui, idx);
                 } else {
                     ui.label("No message selected");
                 }
@@ -66,7 +97,230 @@ pub fn show_detail_content(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
 
     // Handle filter click - update search query
     if let Some(value) = filter_value {
-        app.search_query = value;
+        app.capture.search_query = value;
+    }
+
+    // Handle field click - show the field's bytes
+    if let Some(field) = field_clicked {
+        app.capture.selected_field = Some(field);
+        app.view_mode = ViewMode::Binary;
+    }
+
+    if let Some(object_id) = object_clicked {
+        app.go_to_weenie(object_id);
+    }
+}
+
+/// Bookmark on the selected message
+fn show_message_bookmark(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
+    let Some(message) = app
+        .capture
+        .selected_message
+        .and_then(|idx| app.capture.messages.get(idx))
+    else {
+        return;
+    };
+    let id = message.id;
+    let default_label = format!("{} #{id}", message.message_type);
+    show_bookmark_editor(app, ui, BookmarkTarget::Message(id), || default_label);
+    ui.separator();
+}
+
+/// Objects the selected message referenced, linking to each in the Weenies tab
+fn show_message_objects(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
+    let Some(message) = app
+        .capture
+        .selected_message
+        .and_then(|idx| app.capture.messages.get(idx))
+    else {
+        return;
+    };
+    let objects: Vec<(u32, String)> = app
+        .capture
+        .weenie_db
+        .objects_in_message(message.id)
+        .into_iter()
+        .map(|w| (w.object_id, object_label(w.object_id, w.name.as_deref())))
+        .collect();
+    if objects.is_empty() {
+        return;
+    }
+
+    let mut clicked = None;
+    egui::CollapsingHeader::new(format!("Objects in this message ({})", objects.len()))
+        .id_salt("message_objects")
+        .default_open(true)
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (object_id, label) in &objects {
+                    if ui.link(label).clicked() {
+                        clicked = Some(*object_id);
+                    }
+                }
+            });
+        });
+    ui.separator();
+
+    if let Some(object_id) = clicked {
+        app.go_to_weenie(object_id);
+    }
+}
+
+/// Path of the selected field if it belongs to the message at `idx`
+fn selected_field_path(app: &PcapViewerApp, idx: usize) -> Option<&str> {
+    app.capture
+        .selected_field
+        .as_ref()
+        .filter(|(message_idx, _)| *message_idx == idx)
+        .map(|(_, path)| path.as_str())
+}
+
+/// Show the transport header, optional headers and messages of the selected packet
+fn show_packet_detail(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
+    let Some(packet) = app
+        .capture
+        .selected_packet
+        .and_then(|idx| app.capture.packets.get(idx))
+    else {
+        ui.label("No packet selected");
+        return;
+    };
+
+    match app.view_mode {
+        ViewMode::JSON => {
+            let value = serde_json::to_value(packet).unwrap_or_default();
+            show_pretty_json(ui, &value);
+            return;
+        }
+        ViewMode::Binary => {
+            let payload = packet.raw_payload(&app.capture.bytes);
+            if payload.is_empty() {
+                ui.label("No binary data available for this packet");
+            } else {
+                ui.label(format!("{} bytes", payload.len()));
+                render_hex_dump(ui, payload, &HexHighlight::default());
+            }
+            return;
+        }
+        ViewMode::Tree => {}
+    }
+
+    let header = &packet.header;
+    egui::Grid::new("packet_header_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Packet ID:");
+            ui.label(packet.id.to_string());
+            ui.end_row();
+
+            ui.label("Direction:");
+            ui.label(&packet.direction);
+            ui.end_row();
+
+            ui.label("Sequence:");
+            ui.label(header.sequence.to_string());
+            ui.end_row();
+
+            ui.label("Flags:");
+            ui.label(format!(
+                "0x{:08X} ({})",
+                header.flags.bits(),
+                format_packet_flags(header.flags)
+            ));
+            ui.end_row();
+
+            ui.label("Checksum:");
+            ui.label(format!("0x{:08X}", header.checksum));
+            ui.end_row();
+
+            ui.label("Recipient:");
+            ui.label(header.recipient_id.to_string());
+            ui.end_row();
+
+            ui.label("Time:");
+            ui.label(header.time.to_string());
+            ui.end_row();
+
+            ui.label("Size:");
+            ui.label(header.size.to_string());
+            ui.end_row();
+
+            ui.label("Iteration:");
+            ui.label(header.iteration.to_string());
+            ui.end_row();
+        });
+    ui.separator();
+
+    // Blob fragments and the messages they were reassembled into
+    let mut goto_message: Option<usize> = None;
+    if !packet.fragments.is_empty() {
+        ui.strong(format!("Fragments ({})", packet.fragments.len()));
+        egui::Grid::new("packet_fragments_grid")
+            .num_columns(6)
+            .spacing([10.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Sequence");
+                ui.strong("Id");
+                ui.strong("Index");
+                ui.strong("Received");
+                ui.strong("Bytes");
+                ui.strong("Message");
+                ui.end_row();
+
+                for fragment in &packet.fragments {
+                    ui.label(fragment.sequence.to_string());
+                    ui.label(format!("0x{:08X}", fragment.id));
+                    ui.label(format!("{}/{}", fragment.index + 1, fragment.count));
+                    ui.label(format!("{}/{}", fragment.received, fragment.count));
+                    ui.label(fragment.length.to_string())
+                        .on_hover_text(format!("Header at offset 0x{:04X}", fragment.offset));
+                    match (fragment.complete, fragment.message_id) {
+                        (true, Some(message_id)) => {
+                            if ui
+                                .link(format!("#{message_id}"))
+                                .on_hover_text("Show message")
+                                .clicked()
+                            {
+                                goto_message = Some(message_id);
+                            }
+                        }
+                        (true, None) => {
+                            ui.label("Complete (not decoded)");
+                        }
+                        (false, _) => {
+                            ui.colored_label(ui.visuals().warn_fg_color, "Incomplete");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        ui.separator();
+    }
+
+    // Optional headers and messages are shown as a tree
+    let optional = serde_json::to_value(&packet.optional).unwrap_or_default();
+    let messages = serde_json::Value::Array(packet.messages.clone());
+    let tree_value = serde_json::json!({
+        "Optional": optional,
+        "Messages": messages,
+    });
+    let tree_id = format!("packet_tree_{}", packet.id);
+    let response = AcJsonTree::new(&tree_id)
+        .with_weenies(&app.capture.weenie_db)
+        .with_value_names(&app.value_names)
+        .show(ui, &tree_value);
+    if let Some(value) = response.filter_clicked {
+        app.capture.search_query = value;
+    }
+    if let Some(object_id) = response.object_clicked {
+        app.go_to_weenie(object_id);
+    }
+
+    if let Some(message_id) = goto_message {
+        app.go_to_message(message_id);
     }
 }
 
@@ -79,21 +333,144 @@ fn extract_message_binary(message: &ParsedMessage) -> Option<Vec<u8>> {
     None
 }
 
-/// Display hex dump for a message
-fn show_hex_dump(ui: &mut egui::Ui, message: &ParsedMessage) {
-    if let Some(data) = extract_message_binary(message) {
-        render_hex_dump(ui, &data);
-    } else {
+/// Display hex dump for a message, highlighting the selected field and the field under
+/// the pointer
+fn show_hex_dump(app: &mut PcapViewerApp, ui: &mut egui::Ui, idx: usize) {
+    app.capture.messages[idx].ensure_field_offsets();
+    let message = &app.capture.messages[idx];
+    let Some(data) = extract_message_binary(message) else {
         ui.label("No binary data available for this message");
+        return;
+    };
+
+    ui.label(format!(
+        "{} bytes, OpCode 0x{} at offset 0x0000",
+        data.len(),
+        message.opcode
+    ));
+
+    let selected = selected_field_path(app, idx).and_then(|path| message.field_span(path));
+    let mut object_clicked = None;
+    if let Some(span) = selected {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{}: inferred bytes 0x{:04X}..0x{:04X} ({} bytes)",
+                span.path,
+                span.start,
+                span.end,
+                span.len()
+            ));
+            if let Some((object_id, name)) = field_object(app, message, &span.path)
+                && ui
+                    .link(object_label(object_id, Some(name)))
+                    .on_hover_text("Click to open this object")
+                    .clicked()
+            {
+                object_clicked = Some(object_id);
+            }
+        });
+    }
+
+    // The hovered field is only known after layout, so it's highlighted on the next frame
+    let hover_id = ui.id().with(("hex_hovered_field", idx));
+    let hovered: Option<(usize, usize)> = ui.data(|d| d.get_temp(hover_id));
+    let highlight = HexHighlight {
+        selected: selected.map(|span| (span.start, span.end)),
+        hovered,
+    };
+    let dump = render_hex_dump(ui, &data, &highlight);
+
+    let hovered_field = dump
+        .hovered_offset
+        .and_then(|offset| message.field_at(offset));
+    let hovered = hovered_field.map(|span| (span.start, span.end));
+    ui.data_mut(|d| d.insert_temp(hover_id, hovered));
+    if hovered != highlight.hovered {
+        ui.ctx().request_repaint();
+    }
+
+    let mut clicked_path = None;
+    if let Some(span) = hovered_field {
+        let object = field_object(app, message, &span.path);
+        let response = dump.response.on_hover_ui(|ui| {
+            ui.label(&span.path);
+            ui.label(format!(
+                "Inferred bytes 0x{:04X}..0x{:04X}",
+                span.start, span.end
+            ));
+            if let Some((object_id, name)) = object {
+                ui.label(object_label(object_id, Some(name)));
+            }
+        });
+        if response.clicked() {
+            clicked_path = Some(span.path.clone());
+        }
+    }
+    if let Some(path) = clicked_path {
+        app.capture.selected_field = Some((idx, path));
+    }
+    if let Some(object_id) = object_clicked {
+        app.go_to_weenie(object_id);
+    }
+}
+
+/// The named object an ID field of the message refers to
+fn field_object<'a>(
+    app: &'a PcapViewerApp,
+    message: &ParsedMessage,
+    path: &str,
+) -> Option<(u32, &'a str)> {
+    if !(path.ends_with("Id") || path.ends_with("_id")) {
+        return None;
+    }
+    let path = FieldPath::parse(path).ok()?;
+    let object_id = u32::try_from(path.select(&message.data).first()?.as_u64()?).ok()?;
+    Some((object_id, app.capture.weenie_db.name_of(object_id)?))
+}
+
+/// Byte ranges to emphasise in a hex dump, as `(start, end)`
+#[derive(Default)]
+struct HexHighlight {
+    selected: Option<(usize, usize)>,
+    hovered: Option<(usize, usize)>,
+}
+
+/// Result of rendering a hex dump
+struct HexDumpResponse {
+    response: egui::Response,
+    /// Offset of the byte under the pointer, in either the hex or ASCII column
+    hovered_offset: Option<usize>,
+}
+
+const HEX_BYTES_PER_LINE: usize = 16;
+/// Width of the offset column, including its trailing spaces
+const HEX_OFFSET_WIDTH: usize = 10;
+/// Column where the ASCII representation starts
+const HEX_ASCII_COLUMN: usize = HEX_OFFSET_WIDTH + HEX_BYTES_PER_LINE * 3 + 3;
+
+/// Map a character column of a hex dump line to the byte within the line
+fn hex_column_to_byte(column: usize) -> Option<usize> {
+    let first_half = HEX_OFFSET_WIDTH..HEX_OFFSET_WIDTH + 8 * 3;
+    let second_half = first_half.end + 1..first_half.end + 1 + 8 * 3;
+    let ascii = HEX_ASCII_COLUMN..HEX_ASCII_COLUMN + HEX_BYTES_PER_LINE;
+
+    if first_half.contains(&column) {
+        Some((column - first_half.start) / 3)
+    } else if second_half.contains(&column) {
+        Some(8 + (column - second_half.start) / 3)
+    } else if ascii.contains(&column) {
+        Some(column - ascii.start)
+    } else {
+        None
     }
 }
 
 /// Render a hex dump view of binary data
-fn render_hex_dump(ui: &mut egui::Ui, data: &[u8]) {
+fn render_hex_dump(ui: &mut egui::Ui, data: &[u8], highlight: &HexHighlight) -> HexDumpResponse {
     use egui::text::LayoutJob;
     use egui::{Color32, FontId, TextFormat};
 
-    let bytes_per_line = 16;
+    let bytes_per_line = HEX_BYTES_PER_LINE;
     let mut job = LayoutJob::default();
 
     // Use monospace font for the entire hex dump
@@ -109,6 +486,20 @@ fn render_hex_dump(ui: &mut egui::Ui, data: &[u8]) {
     } else {
         Color32::from_rgb(64, 150, 64)
     };
+    let selected_bg = ui.visuals().selection.bg_fill;
+    let hovered_bg = ui.visuals().widgets.hovered.weak_bg_fill;
+    let background = |offset: usize| {
+        let within = |range: Option<(usize, usize)>| {
+            range.is_some_and(|(start, end)| start <= offset && offset < end)
+        };
+        if within(highlight.selected) {
+            selected_bg
+        } else if within(highlight.hovered) {
+            hovered_bg
+        } else {
+            Color32::TRANSPARENT
+        }
+    };
 
     for (i, chunk) in data.chunks(bytes_per_line).enumerate() {
         let offset = i * bytes_per_line;
@@ -127,11 +518,30 @@ fn render_hex_dump(ui: &mut egui::Ui, data: &[u8]) {
         // Hex bytes
         for (j, byte) in chunk.iter().enumerate() {
             job.append(
-                &format!("{byte:02x} "),
+                &format!("{byte:02x}"),
                 0.0,
                 TextFormat {
                     font_id: font_id.clone(),
                     color: hex_color,
+                    background: background(offset + j),
+                    ..Default::default()
+                },
+            );
+            // Keep the highlight continuous across a multi-byte field
+            job.append(
+                " ",
+                0.0,
+                TextFormat {
+                    font_id: font_id.clone(),
+                    color: hex_color,
+                    background: if j + 1 < chunk.len()
+                        && j != 7
+                        && background(offset + j) == background(offset + j + 1)
+                    {
+                        background(offset + j)
+                    } else {
+                        Color32::TRANSPARENT
+                    },
                     ..Default::default()
                 },
             );
@@ -177,7 +587,7 @@ fn render_hex_dump(ui: &mut egui::Ui, data: &[u8]) {
             },
         );
 
-        for byte in chunk.iter() {
+        for (j, byte) in chunk.iter().enumerate() {
             let ch = if *byte >= 32 && *byte < 127 {
                 *byte as char
             } else {
@@ -189,6 +599,7 @@ fn render_hex_dump(ui: &mut egui::Ui, data: &[u8]) {
                 TextFormat {
                     font_id: font_id.clone(),
                     color: ascii_color,
+                    background: background(offset + j),
                     ..Default::default()
                 },
             );
@@ -205,15 +616,34 @@ fn render_hex_dump(ui: &mut egui::Ui, data: &[u8]) {
         );
     }
 
+    let row_height = ui.fonts(|f| f.row_height(&font_id));
+    let char_width = ui.fonts(|f| f.glyph_width(&font_id, '0'));
+
     // Wrap in horizontal and vertical scroll area
-    egui::ScrollArea::both()
+    let response = egui::ScrollArea::both()
         .auto_shrink([false, false])
         .show(ui, |ui| {
             // Set layout to prevent wrapping
             ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
-                ui.add(egui::Label::new(job).extend());
-            });
-        });
+                ui.add(egui::Label::new(job).extend().sense(egui::Sense::click()))
+            })
+            .inner
+        })
+        .inner;
+
+    // Work out which byte is under the pointer from the monospace grid
+    let hovered_offset = response.hover_pos().and_then(|pos| {
+        let rel = pos - response.rect.min;
+        let row = (rel.y / row_height) as usize;
+        let byte = hex_column_to_byte((rel.x / char_width) as usize)?;
+        let offset = row * bytes_per_line + byte;
+        (offset < data.len()).then_some(offset)
+    });
+
+    HexDumpResponse {
+        response,
+        hovered_offset,
+    }
 }
 
 /// Show pretty-printed JSON
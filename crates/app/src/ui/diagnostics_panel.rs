@@ -0,0 +1,201 @@
+//! Diagnostics tab listing packets and messages the parser couldn't decode
+
+use crate::PcapViewerApp;
+use crate::ui::packet_list::{desktop_marked_cell, mobile_cell};
+use common::diagnostics::DiagnosticKind;
+use eframe::egui;
+use egui_extras::Column;
+use std::collections::BTreeMap;
+
+/// Show the diagnostics list, filtered by the search box
+pub fn show_diagnostics_list(app: &mut PcapViewerApp, ui: &mut egui::Ui, is_mobile: bool) {
+    if app.capture.diagnostics.is_empty() {
+        ui.label("No problems found while parsing this capture");
+        return;
+    }
+
+    let search = app.capture.search_query.to_lowercase();
+    let start_time = app.capture.packets.first().map_or(0.0, |p| p.timestamp);
+    let filtered: Vec<usize> = app
+        .capture
+        .diagnostics
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| {
+            search.is_empty()
+                || d.message.to_lowercase().contains(&search)
+                || d.kind.label().to_lowercase().contains(&search)
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+
+    // Count of each kind across the whole capture
+    let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    for d in &app.capture.diagnostics {
+        *counts.entry(d.kind.label()).or_default() += 1;
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(format!(
+            "{}/{} diagnostics",
+            filtered.len(),
+            app.capture.diagnostics.len()
+        ));
+        for (label, count) in &counts {
+            ui.separator();
+            ui.label(format!("{label}: {count}"));
+        }
+    });
+    ui.separator();
+
+    let mut clicked: Option<usize> = None;
+    let mut table = egui_extras::TableBuilder::new(ui)
+        .striped(true)
+        .resizable(!is_mobile)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .column(Column::initial(60.0).range(40.0..=100.0));
+    if !is_mobile {
+        table = table
+            .column(Column::initial(80.0).range(60.0..=120.0))
+            .column(Column::initial(150.0).range(80.0..=250.0))
+            .column(Column::initial(70.0).range(50.0..=100.0))
+            .column(Column::initial(70.0).range(50.0..=100.0));
+    } else {
+        table = table.column(Column::auto());
+    }
+    let table = table.column(Column::remainder()).min_scrolled_height(0.0);
+
+    let header_height = 20.0;
+    table
+        .header(header_height, |mut header| {
+            header.col(|ui| {
+                ui.strong("Packet");
+            });
+            if !is_mobile {
+                header.col(|ui| {
+                    ui.strong("Time");
+                });
+            }
+            header.col(|ui| {
+                ui.strong("Kind");
+            });
+            if !is_mobile {
+                header.col(|ui| {
+                    ui.strong("OpCode");
+                });
+                header.col(|ui| {
+                    ui.strong("Offset");
+                });
+            }
+            header.col(|ui| {
+                ui.strong("Message");
+            });
+        })
+        .body(|body| {
+            body.rows(20.0, filtered.len(), |mut row| {
+                let idx = filtered[row.index()];
+                let d = &app.capture.diagnostics[idx];
+                let is_selected = app.capture.selected_diagnostic == Some(idx);
+                let kind_color = kind_color(app.dark_mode, d.kind);
+
+                // Records dropped before becoming a packet are shown by record number
+                let packet = match (d.packet_index, d.record_index) {
+                    (Some(p), _) => p.to_string(),
+                    (None, Some(r)) => format!("rec {r}"),
+                    (None, None) => String::new(),
+                };
+                row.col(|ui| {
+                    if cell(ui, is_mobile, is_selected, packet).clicked() {
+                        clicked = Some(idx);
+                    }
+                });
+                if !is_mobile {
+                    row.col(|ui| {
+                        let time = format!("{:.3}", d.timestamp - start_time);
+                        if cell(ui, is_mobile, is_selected, time).clicked() {
+                            clicked = Some(idx);
+                        }
+                    });
+                }
+                row.col(|ui| {
+                    let kind = egui::RichText::new(d.kind.label()).color(kind_color);
+                    if cell(ui, is_mobile, is_selected, kind).clicked() {
+                        clicked = Some(idx);
+                    }
+                });
+                if !is_mobile {
+                    row.col(|ui| {
+                        let opcode = d.opcode.map(|o| format!("0x{o:04X}")).unwrap_or_default();
+                        if cell(ui, is_mobile, is_selected, opcode).clicked() {
+                            clicked = Some(idx);
+                        }
+                    });
+                    row.col(|ui| {
+                        let offset = d.offset.map(|o| format!("0x{o:04X}")).unwrap_or_default();
+                        if cell(ui, is_mobile, is_selected, offset).clicked() {
+                            clicked = Some(idx);
+                        }
+                    });
+                }
+                row.col(|ui| {
+                    if cell(ui, is_mobile, is_selected, &d.message)
+                        .on_hover_text(&d.message)
+                        .clicked()
+                    {
+                        clicked = Some(idx);
+                    }
+                });
+            });
+        });
+
+    // Selecting a diagnostic shows its packet in the detail panel
+    if let Some(idx) = clicked {
+        app.capture.selected_diagnostic = Some(idx);
+        if let Some(packet_idx) = app.capture.diagnostics[idx].packet_index
+            && packet_idx < app.capture.packets.len()
+        {
+            app.capture.selected_packet = Some(packet_idx);
+        }
+        app.show_detail_panel = true;
+    }
+}
+
+fn cell(
+    ui: &mut egui::Ui,
+    is_mobile: bool,
+    is_selected: bool,
+    text: impl Into<egui::WidgetText>,
+) -> egui::Response {
+    if is_mobile {
+        let width = ui.available_width();
+        mobile_cell(ui, width, false, is_selected, false, text)
+    } else {
+        desktop_marked_cell(ui, is_selected, false, text)
+    }
+}
+
+/// Color for each kind: red for data loss, orange for decoding problems
+fn kind_color(dark_mode: bool, kind: DiagnosticKind) -> egui::Color32 {
+    match kind {
+        DiagnosticKind::TruncatedCapture
+        | DiagnosticKind::UnsupportedLinkType
+        | DiagnosticKind::IncompleteMessage
+        | DiagnosticKind::InvalidHeader => {
+            if dark_mode {
+                egui::Color32::from_rgb(255, 120, 120)
+            } else {
+                egui::Color32::from_rgb(190, 30, 30)
+            }
+        }
+        DiagnosticKind::InvalidOptionalHeaders
+        | DiagnosticKind::InvalidFragment
+        | DiagnosticKind::UnknownOpcode
+        | DiagnosticKind::DecodeFailed => {
+            if dark_mode {
+                egui::Color32::from_rgb(255, 190, 100)
+            } else {
+                egui::Color32::from_rgb(180, 100, 0)
+            }
+        }
+    }
+}
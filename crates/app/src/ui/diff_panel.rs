@@ -0,0 +1,299 @@
+//! Comparing the loaded capture with another, message by message
+
+use crate::PcapViewerApp;
+use crate::loader::{ParseJob, ParseStatus};
+use crate::ui::hyper_tree::AcJsonTree;
+use common::diff::{CaptureDiff, DiffKind, DiffOptions, diff_captures};
+use common::messages::ParsedMessage;
+use eframe::egui;
+use egui::ScrollArea;
+
+/// The capture being compared against and how it differs from the loaded one
+pub struct Comparison {
+    /// File name of the other capture
+    pub name: String,
+    pub messages: Vec<ParsedMessage>,
+    pub diff: CaptureDiff,
+    /// Index into `diff.entries`
+    pub selected: Option<usize>,
+    /// Comma-separated field names to leave out of the comparison
+    pub ignore_input: String,
+    pub open: bool,
+}
+
+impl Comparison {
+    fn new(name: String, messages: Vec<ParsedMessage>) -> Self {
+        Self {
+            name,
+            messages,
+            diff: CaptureDiff::default(),
+            selected: None,
+            ignore_input: String::new(),
+            open: true,
+        }
+    }
+
+    fn options(&self) -> DiffOptions {
+        DiffOptions {
+            ignore_fields: self
+                .ignore_input
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// Message of the other capture with `message_id`
+    fn message(&self, message_id: usize) -> Option<&ParsedMessage> {
+        self.messages
+            .binary_search_by_key(&message_id, |m| m.id)
+            .ok()
+            .map(|idx| &self.messages[idx])
+    }
+}
+
+/// Start parsing `data` as the capture to compare with, replacing any comparison running
+pub fn compare_with(app: &mut PcapViewerApp, name: String, data: Vec<u8>, ctx: &egui::Context) {
+    if let Some((_, mut job)) = app.compare_job.take() {
+        job.cancel();
+    }
+    app.status_message = format!("Parsing {name} to compare...");
+    let job = ParseJob::start(data, app.extractor_rules.clone(), ctx);
+    app.compare_job = Some((name, job));
+}
+
+/// Check on the capture being parsed for comparison, comparing once it finishes
+pub fn poll_compare_job(app: &mut PcapViewerApp) {
+    let Some((_, job)) = app.compare_job.as_mut() else {
+        return;
+    };
+    let status = job.poll();
+    if matches!(status, ParseStatus::Running) {
+        return;
+    }
+    let Some((name, _)) = app.compare_job.take() else {
+        return;
+    };
+    match status {
+        ParseStatus::Running => {}
+        ParseStatus::Finished(Ok(loaded)) => {
+            let mut comparison = Comparison::new(name, loaded.capture.messages);
+            comparison.diff = diff_captures(
+                &app.capture.messages,
+                &comparison.messages,
+                &Default::default(),
+            );
+            app.status_message = format!(
+                "Compared with {}: {} differences",
+                comparison.name,
+                comparison.diff.entries.len()
+            );
+            app.capture.comparison = Some(comparison);
+        }
+        ParseStatus::Finished(Err(e)) => {
+            app.show_error(format!("Error parsing {name} to compare: {e}"))
+        }
+        ParseStatus::Cancelled => app.status_message = "Comparison cancelled".to_string(),
+    }
+}
+
+/// Compare the other capture with the loaded one again, after the loaded one changes
+pub fn refresh_comparison(app: &mut PcapViewerApp) {
+    if let Some(comparison) = app.capture.comparison.as_mut() {
+        comparison.diff = diff_captures(
+            &app.capture.messages,
+            &comparison.messages,
+            &comparison.options(),
+        );
+        comparison.selected = None;
+    }
+}
+
+/// Pick a capture to compare the loaded one with (desktop only)
+#[cfg(feature = "desktop")]
+pub fn open_compare_dialog(app: &mut PcapViewerApp, ctx: &egui::Context) {
+    use rfd::FileDialog;
+
+    let Some(path) = FileDialog::new()
+        .add_filter("PCAP files", &["pcap", "pcapng"])
+        .pick_file()
+    else {
+        return;
+    };
+    let name = crate::ui::file_panel::file_name(&path);
+    match std::fs::read(&path) {
+        Ok(data) => compare_with(app, name, data, ctx),
+        Err(e) => app.show_error(format!("Error reading file: {e}")),
+    }
+}
+
+/// Show the comparison window, if a comparison is open
+pub fn show_compare_window(app: &mut PcapViewerApp, ctx: &egui::Context) {
+    let Some(mut comparison) = app.capture.comparison.take() else {
+        return;
+    };
+    let mut open = comparison.open;
+    let mut go_to = None;
+
+    egui::Window::new(format!("Compare with {}", comparison.name))
+        .id(egui::Id::new("compare_window"))
+        .open(&mut open)
+        .default_size([900.0, 600.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let diff = &comparison.diff;
+                ui.label(format!(
+                    "{} same, {} changed, {} missing, {} extra",
+                    diff.same,
+                    diff.count(DiffKind::Changed),
+                    diff.count(DiffKind::Missing),
+                    diff.count(DiffKind::Extra)
+                ));
+                ui.separator();
+                ui.label("Ignore:");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut comparison.ignore_input)
+                        .hint_text("Sequence, ObjectId")
+                        .desired_width(200.0),
+                );
+                let apply = ui.button("Apply").clicked()
+                    || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+                if apply {
+                    comparison.diff = diff_captures(
+                        &app.capture.messages,
+                        &comparison.messages,
+                        &comparison.options(),
+                    );
+                    comparison.selected = None;
+                }
+            });
+            ui.separator();
+
+            if comparison.diff.is_empty() {
+                ui.label("The captures have the same messages");
+                return;
+            }
+
+            egui::SidePanel::left("compare_entries")
+                .resizable(true)
+                .default_width(320.0)
+                .show_inside(ui, |ui| {
+                    show_entries(ui, &mut comparison);
+                });
+
+            egui::CentralPanel::default().show_inside(ui, |ui| {
+                go_to = show_entry(app, ui, &comparison);
+            });
+        });
+
+    comparison.open = open;
+    if open {
+        app.capture.comparison = Some(comparison);
+    }
+    if let Some(message_id) = go_to {
+        app.go_to_message(message_id);
+    }
+}
+
+fn kind_color(kind: DiffKind) -> egui::Color32 {
+    match kind {
+        DiffKind::Missing => egui::Color32::from_rgb(220, 90, 90),
+        DiffKind::Extra => egui::Color32::from_rgb(90, 170, 90),
+        DiffKind::Changed => egui::Color32::from_rgb(220, 170, 60),
+    }
+}
+
+fn show_entries(ui: &mut egui::Ui, comparison: &mut Comparison) {
+    ScrollArea::vertical()
+        .id_salt("compare_entries_scroll")
+        .show(ui, |ui| {
+            for (idx, entry) in comparison.diff.entries.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(kind_color(entry.kind), entry.kind.label());
+                    let text = format!(
+                        "{:.3}s {} {}",
+                        entry.time, entry.direction, entry.message_type
+                    );
+                    if ui
+                        .selectable_label(comparison.selected == Some(idx), text)
+                        .clicked()
+                    {
+                        comparison.selected = Some(idx);
+                    }
+                });
+            }
+        });
+}
+
+/// Show the selected entry's field differences and both messages side by side, returning
+/// the loaded capture's message to go to if its link was clicked
+fn show_entry(app: &PcapViewerApp, ui: &mut egui::Ui, comparison: &Comparison) -> Option<usize> {
+    let Some(entry) = comparison
+        .selected
+        .and_then(|idx| comparison.diff.entries.get(idx))
+    else {
+        ui.label("Select a difference to compare the messages");
+        return None;
+    };
+    let mut go_to = None;
+
+    ScrollArea::vertical()
+        .id_salt("compare_entry_scroll")
+        .show(ui, |ui| {
+            if !entry.fields.is_empty() {
+                egui::Grid::new("compare_fields")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Field");
+                        ui.strong("This capture");
+                        ui.strong(&comparison.name);
+                        ui.end_row();
+                        let show = |value: &Option<serde_json::Value>| {
+                            value.as_ref().map_or("—".to_string(), |v| v.to_string())
+                        };
+                        for field in &entry.fields {
+                            ui.monospace(&field.path);
+                            ui.label(show(&field.left));
+                            ui.label(show(&field.right));
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+            }
+
+            let left = entry
+                .left_id
+                .and_then(|id| app.message_index(id))
+                .map(|idx| &app.capture.messages[idx]);
+            let right = entry.right_id.and_then(|id| comparison.message(id));
+            ui.columns(2, |columns| {
+                if let Some(message) = left {
+                    if columns[0]
+                        .link(format!("Message #{}", message.id))
+                        .on_hover_text("Go to message")
+                        .clicked()
+                    {
+                        go_to = Some(message.id);
+                    }
+                    AcJsonTree::new(format!("compare_left_{}", message.id))
+                        .with_value_names(&app.value_names)
+                        .show(&mut columns[0], &message.data);
+                } else {
+                    columns[0].label("Not in this capture");
+                }
+                if let Some(message) = right {
+                    columns[1].label(format!("Message #{}", message.id));
+                    AcJsonTree::new(format!("compare_right_{}", message.id))
+                        .with_value_names(&app.value_names)
+                        .show(&mut columns[1], &message.data);
+                } else {
+                    columns[1].label(format!("Not in {}", comparison.name));
+                }
+            });
+        });
+
+    go_to
+}
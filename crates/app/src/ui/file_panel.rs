@@ -1,57 +1,136 @@
 //! File loading and management UI components
 
-use crate::PcapViewerApp;
-use common::PacketParser;
+use crate::loader::{ParseJob, ParseStatus};
+use crate::{PcapViewerApp, Tab};
+use common::ParsedPacket;
+use common::capture;
+use common::export::{self, ExportFormat};
+use common::stream::ParsedCapture;
 use eframe::egui;
+use std::collections::HashSet;
 
 #[allow(dead_code)]
 static BOT_BASE_URL: &str = env!("BOT_BASE_URL");
 
-/// Parse PCAP data and update the app state
-pub fn parse_pcap_data(app: &mut PcapViewerApp, data: &[u8]) {
-    app.is_loading = true;
-    app.status_message = "Parsing PCAP file...".to_string();
-
-    let mut parser = PacketParser::new();
-    match parser.parse_pcap_bytes(data) {
-        Ok((packets, messages, weenie_db)) => {
-            app.status_message = format!(
-                "Loaded {} packets, {} messages, {} weenies",
-                packets.len(),
-                messages.len(),
-                weenie_db.count()
-            );
-            app.packets = packets;
-            app.messages = messages;
-            app.weenie_db = weenie_db;
-
-            // Clear any URL load errors on success
-            app.url_load_error = None;
-            app.selected_message = if app.messages.is_empty() {
-                None
-            } else {
-                Some(0)
-            };
-            app.selected_packet = if app.packets.is_empty() {
-                None
-            } else {
-                Some(0)
-            };
+/// Name of the file at `path`, for tab titles
+pub fn file_name(path: &std::path::Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    )
+}
 
-            // Update time scrubbers
-            // Messages scrubber uses message timestamps
-            let message_timestamps: Vec<f64> = app.messages.iter().map(|m| m.timestamp).collect();
-            app.messages_scrubber.update_density(&message_timestamps);
+/// Start parsing PCAP data in the background to open in a tab named `name`, alongside any
+/// other captures already being parsed
+pub fn parse_pcap_data(
+    app: &mut PcapViewerApp,
+    name: impl Into<String>,
+    data: Vec<u8>,
+    ctx: &egui::Context,
+) {
+    let name = name.into();
+    app.is_loading = true;
+    app.status_message = format!("Parsing {name}...");
+    let job = ParseJob::start(data, app.extractor_rules.clone(), ctx);
+    app.parse_jobs.push((name, job));
+}
 
-            // Fragments scrubber uses packet timestamps
-            let packet_timestamps: Vec<f64> = app.packets.iter().map(|p| p.timestamp).collect();
-            app.fragments_scrubber.update_density(&packet_timestamps);
+/// Check on the running parses, opening a tab for each one that finishes
+pub fn poll_parse_jobs(app: &mut PcapViewerApp) {
+    let mut idx = 0;
+    while idx < app.parse_jobs.len() {
+        let status = app.parse_jobs[idx].1.poll();
+        if matches!(status, ParseStatus::Running) {
+            idx += 1;
+            continue;
         }
-        Err(e) => {
-            app.show_error(format!("Error parsing PCAP: {e}"));
+        let (name, _) = app.parse_jobs.remove(idx);
+        match status {
+            ParseStatus::Running => {}
+            ParseStatus::Finished(Ok(loaded)) => {
+                app.open_document(name);
+                app.capture.hash = loaded.capture_hash;
+                app.capture.bytes = loaded.data;
+                apply_parsed_capture(app, loaded.capture);
+            }
+            ParseStatus::Finished(Err(e)) => app.show_error(format!("Error parsing {name}: {e}")),
+            ParseStatus::Cancelled => app.status_message = format!("Parsing {name} cancelled"),
         }
+        app.is_loading = !app.parse_jobs.is_empty();
+    }
+}
+
+/// Fill the shown tab with a freshly parsed capture
+fn apply_parsed_capture(app: &mut PcapViewerApp, capture: ParsedCapture) {
+    let ParsedCapture {
+        packets,
+        messages,
+        weenie_db,
+        mut diagnostics,
+    } = capture;
+    diagnostics.sort_by_key(|d| d.record_index);
+
+    app.status_message = format!(
+        "Loaded {} packets, {} messages, {} weenies",
+        packets.len(),
+        messages.len(),
+        weenie_db.count()
+    );
+    if !diagnostics.is_empty() {
+        app.status_message
+            .push_str(&format!(", {} diagnostics", diagnostics.len()));
     }
-    app.is_loading = false;
+    app.capture.packets = packets;
+    app.capture.messages = messages;
+    app.capture.weenie_db = weenie_db;
+    app.capture.diagnostics = diagnostics;
+    app.capture.selected_diagnostic = None;
+    if app.capture.diagnostics.is_empty() && app.capture.current_tab == Tab::Diagnostics {
+        app.capture.current_tab = Tab::Messages;
+    }
+
+    // Clear any URL load errors on success
+    app.url_load_error = None;
+    app.capture.selected_message = if app.capture.messages.is_empty() {
+        None
+    } else {
+        Some(0)
+    };
+    app.capture.selected_field = None;
+    app.capture.selected_packet = if app.capture.packets.is_empty() {
+        None
+    } else {
+        Some(0)
+    };
+
+    // Update time scrubbers
+    // Messages scrubber uses message timestamps
+    let message_timestamps: Vec<f64> = app.capture.messages.iter().map(|m| m.timestamp).collect();
+    app.capture
+        .messages_scrubber
+        .update_density(&message_timestamps);
+
+    // Fragments scrubber uses packet timestamps
+    let packet_timestamps: Vec<f64> = app.capture.packets.iter().map(|p| p.timestamp).collect();
+    app.capture
+        .fragments_scrubber
+        .update_density(&packet_timestamps);
+
+    // Weenies scrubber uses the times object properties changed
+    let change_timestamps: Vec<f64> = app
+        .capture
+        .weenie_db
+        .weenies()
+        .values()
+        .flat_map(|w| w.history.iter().map(|c| c.timestamp))
+        .collect();
+    app.capture
+        .weenies_scrubber
+        .update_density(&change_timestamps);
+    app.capture.weenie_pinned_time = None;
+    app.capture.weenie_state_cache = None;
+    app.capture.selected_weenie = None;
+    crate::ui::diff_panel::refresh_comparison(app);
 }
 
 /// Load example PCAP file
@@ -62,9 +141,9 @@ pub fn load_example(app: &mut PcapViewerApp, ctx: &egui::Context) {
 
 /// Load example PCAP file (native)
 #[cfg(not(target_arch = "wasm32"))]
-pub fn load_example(app: &mut PcapViewerApp, _ctx: &egui::Context) {
+pub fn load_example(app: &mut PcapViewerApp, ctx: &egui::Context) {
     match std::fs::read("static/example.pcap") {
-        Ok(data) => parse_pcap_data(app, &data),
+        Ok(data) => parse_pcap_data(app, "example.pcap", data, ctx),
         Err(e) => app.show_error(format!("Failed to load example PCAP: {e}")),
     }
 }
@@ -78,6 +157,7 @@ pub fn load_from_url(app: &mut PcapViewerApp, url: String, ctx: &egui::Context)
 
     app.is_loading = true;
     app.status_message = format!("Loading PCAP from {}...", url);
+    app.fetched_name = url.rsplit('/').next().unwrap_or(&url).to_string();
 
     // Clear any previous errors
     if let Ok(mut error) = app.fetched_error.lock() {
@@ -137,6 +217,7 @@ pub fn load_from_discord(
         "Loading PCAP from Discord ({}:{})...",
         channel_id, message_id
     );
+    app.fetched_name = format!("Discord {message_id}");
 
     // Clear any previous errors
     if let Ok(mut error) = app.fetched_error.lock() {
@@ -270,6 +351,328 @@ pub fn open_file_dialog(app: &mut PcapViewerApp) {
     }
 }
 
+/// Pick a rules file and use it for the captures opened from now on (desktop only)
+#[cfg(feature = "desktop")]
+pub fn open_rules_dialog(app: &mut PcapViewerApp) {
+    use common::extractor_rules::ExtractorRegistry;
+    use rfd::FileDialog;
+
+    let Some(path) = FileDialog::new()
+        .add_filter("Extractor rules", &["toml", "json"])
+        .pick_file()
+    else {
+        return;
+    };
+    match ExtractorRegistry::load_over_builtin(&path) {
+        Ok(rules) => {
+            app.status_message = format!(
+                "Loaded extractor rules from {}; they apply to captures opened from now on",
+                path.display()
+            );
+            app.extractor_rules = std::sync::Arc::new(rules);
+        }
+        Err(e) => app.show_error(format!("Error loading extractor rules: {e:#}")),
+    }
+}
+
+/// Pick a spell table to name spell IDs with (desktop only)
+#[cfg(feature = "desktop")]
+pub fn open_spell_table_dialog(app: &mut PcapViewerApp) {
+    use common::value_names::ValueNames;
+    use rfd::FileDialog;
+
+    let Some(path) = FileDialog::new()
+        .add_filter("Spell tables", &["csv", "json"])
+        .pick_file()
+    else {
+        return;
+    };
+    match ValueNames::load(&path) {
+        Ok(value_names) => {
+            app.status_message = format!(
+                "Loaded {} spell names from {}",
+                value_names.spell_count(),
+                path.display()
+            );
+            app.value_names = value_names;
+        }
+        Err(e) => app.show_error(format!("Error loading spell table: {e:#}")),
+    }
+}
+
+/// Show a tab for each open capture, if more than one is open
+pub fn show_document_tabs(app: &mut PcapViewerApp, ctx: &egui::Context) {
+    if app.documents.len() < 2 {
+        return;
+    }
+    let mut switch_to = None;
+    let mut close = None;
+
+    egui::TopBottomPanel::top("document_tabs").show(ctx, |ui| {
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for (idx, document) in app.documents.iter().enumerate() {
+                    let active = idx == app.active_document;
+                    if ui.selectable_label(active, &document.name).clicked() {
+                        switch_to = Some(idx);
+                    }
+                    if ui.small_button("×").on_hover_text("Close").clicked() {
+                        close = Some(idx);
+                    }
+                    ui.separator();
+                }
+            });
+        });
+    });
+
+    if let Some(idx) = switch_to {
+        app.switch_document(idx);
+    }
+    if let Some(idx) = close {
+        app.close_document(idx);
+    }
+}
+
+/// Save `bytes` to a file the user picks, suggesting `name` (desktop only)
+#[cfg(feature = "desktop")]
+pub fn save_file(app: &mut PcapViewerApp, name: &str, bytes: &[u8]) {
+    use rfd::FileDialog;
+
+    let Some(path) = FileDialog::new().set_file_name(name).save_file() else {
+        return;
+    };
+    match std::fs::write(&path, bytes) {
+        Ok(()) => app.status_message = format!("Saved {}", path.display()),
+        Err(e) => app.show_error(format!("Error saving {}: {e}", path.display())),
+    }
+}
+
+/// Download `bytes` as a file named `name` (web)
+#[cfg(all(target_arch = "wasm32", not(feature = "desktop")))]
+pub fn save_file(app: &mut PcapViewerApp, name: &str, bytes: &[u8]) {
+    match download(name, bytes) {
+        Ok(()) => app.status_message = format!("Downloaded {name}"),
+        Err(e) => app.show_error(format!("Error downloading {name}: {e}")),
+    }
+}
+
+/// Saving needs a file dialog or a browser
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "desktop")))]
+pub fn save_file(app: &mut PcapViewerApp, _name: &str, _bytes: &[u8]) {
+    app.status_message = "Saving files not supported in this build".to_string();
+}
+
+/// Have the browser download `bytes` as a file named `name`
+#[cfg(all(target_arch = "wasm32", not(feature = "desktop")))]
+fn download(name: &str, bytes: &[u8]) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+    use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = BlobPropertyBag::new();
+    options.set_type("application/octet-stream");
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+        .map_err(|e| format!("Failed to create blob: {:?}", e))?;
+    let url = Url::create_object_url_with_blob(&blob)
+        .map_err(|e| format!("Failed to create object URL: {:?}", e))?;
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| "No document object".to_string())?;
+    let anchor: HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|e| format!("Failed to create link: {:?}", e))?
+        .dyn_into()
+        .map_err(|_| "Element is not a link".to_string())?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+
+    let _ = Url::revoke_object_url(&url);
+    Ok(())
+}
+
+/// Save the shown capture's marks, bookmarks, notes and filter as an `.acsession` file
+pub fn save_session(app: &mut PcapViewerApp) {
+    use common::session::SESSION_EXTENSION;
+
+    let session = app.session();
+    let json = match session.to_json() {
+        Ok(json) => json,
+        Err(e) => {
+            app.show_error(format!("Error saving session: {e:#}"));
+            return;
+        }
+    };
+    let stem = capture_stem(app);
+    save_file(app, &format!("{stem}.{SESSION_EXTENSION}"), json.as_bytes());
+}
+
+/// Name of the shown capture without its extension, to name files saved from it
+fn capture_stem(app: &PcapViewerApp) -> String {
+    let name = &app.documents[app.active_document].name;
+    let stem = name
+        .rsplit_once('.')
+        .map_or(name.as_str(), |(stem, _)| stem);
+    if stem.is_empty() {
+        "capture".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+/// Save the messages matching the search and time range in `format`
+pub fn export_messages(app: &mut PcapViewerApp, format: ExportFormat) {
+    let indices = app.filtered_message_indices();
+    let messages: Vec<_> = indices
+        .iter()
+        .map(|&idx| &app.capture.messages[idx])
+        .collect();
+    let exported = export::export_messages(&messages, format);
+    match exported {
+        Ok(bytes) => {
+            let name = format!("{}-messages.{}", capture_stem(app), format.extension());
+            save_file(app, &name, &bytes);
+        }
+        Err(e) => app.show_error(format!("Error exporting messages: {e:#}")),
+    }
+}
+
+/// Which packets to write to a trimmed capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketSelection {
+    /// Packets in the time range selected on the current tab's scrubber
+    TimeRange,
+    /// Marked packets, and the packets carrying marked messages
+    Marked,
+    /// Packets carrying the messages matching the search and time range
+    Filtered,
+}
+
+impl PacketSelection {
+    pub const ALL: [PacketSelection; 3] = [
+        PacketSelection::TimeRange,
+        PacketSelection::Marked,
+        PacketSelection::Filtered,
+    ];
+
+    /// Human-readable name for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            PacketSelection::TimeRange => "Packets in Time Range",
+            PacketSelection::Marked => "Marked Packets",
+            PacketSelection::Filtered => "Packets of Filtered Messages",
+        }
+    }
+}
+
+/// Whether `packet` carries a fragment of any message in `message_ids`
+fn carries_message(packet: &ParsedPacket, message_ids: &HashSet<usize>) -> bool {
+    packet
+        .fragments
+        .iter()
+        .any(|f| f.message_id.is_some_and(|id| message_ids.contains(&id)))
+}
+
+/// Indices of the packets in `selection`, in capture order
+fn selected_packets(app: &PcapViewerApp, selection: PacketSelection) -> Vec<usize> {
+    let message_ids = |indices: Vec<usize>| -> HashSet<usize> {
+        indices
+            .into_iter()
+            .map(|idx| app.capture.messages[idx].id)
+            .collect()
+    };
+    let packets = app.capture.packets.iter().enumerate();
+
+    match selection {
+        PacketSelection::TimeRange => {
+            let scrubber = match app.capture.current_tab {
+                Tab::Fragments => &app.capture.fragments_scrubber,
+                Tab::Weenies => &app.capture.weenies_scrubber,
+                Tab::Messages | Tab::Diagnostics => &app.capture.messages_scrubber,
+            };
+            let range = scrubber.get_selected_range();
+            packets
+                .filter(|(_, p)| range.is_none_or(|r| r.contains(p.timestamp)))
+                .map(|(idx, _)| idx)
+                .collect()
+        }
+        PacketSelection::Marked => {
+            let ids = message_ids(app.capture.marked_messages.iter().copied().collect());
+            packets
+                .filter(|(idx, p)| {
+                    app.capture.marked_packets.contains(idx) || carries_message(p, &ids)
+                })
+                .map(|(idx, _)| idx)
+                .collect()
+        }
+        PacketSelection::Filtered => {
+            let ids = message_ids(app.filtered_message_indices());
+            packets
+                .filter(|(_, p)| carries_message(p, &ids))
+                .map(|(idx, _)| idx)
+                .collect()
+        }
+    }
+}
+
+/// Save the original frames of the packets in `selection` as a pcap
+pub fn save_packets(app: &mut PcapViewerApp, selection: PacketSelection) {
+    let indices = selected_packets(app, selection);
+    if indices.is_empty() {
+        app.status_message = format!("No packets to save: {}", selection.label());
+        return;
+    }
+    let records = indices
+        .iter()
+        .map(|&idx| app.capture.packets[idx].record_index);
+    let written = capture::trim(&app.capture.bytes, records);
+    match written {
+        Ok(bytes) => {
+            let name = format!("{}-trimmed.pcap", capture_stem(app));
+            save_file(app, &name, &bytes);
+        }
+        Err(e) => app.show_error(format!("Error writing pcap: {e:#}")),
+    }
+}
+
+/// Save every object in the weenie database in `format`
+pub fn export_weenies(app: &mut PcapViewerApp, format: ExportFormat) {
+    let exported = export::export_weenies(&app.capture.weenie_db.sorted_weenies(), format);
+    match exported {
+        Ok(bytes) => {
+            let name = format!("{}-weenies.{}", capture_stem(app), format.extension());
+            save_file(app, &name, &bytes);
+        }
+        Err(e) => app.show_error(format!("Error exporting weenies: {e:#}")),
+    }
+}
+
+/// Pick an `.acsession` file and open it onto the capture it's for (desktop only)
+#[cfg(feature = "desktop")]
+pub fn open_session_dialog(app: &mut PcapViewerApp) {
+    use common::session::SESSION_EXTENSION;
+    use rfd::FileDialog;
+
+    let Some(path) = FileDialog::new()
+        .add_filter("Sessions", &[SESSION_EXTENSION])
+        .pick_file()
+    else {
+        return;
+    };
+    match std::fs::read(&path) {
+        Ok(data) => app.open_session(&data),
+        Err(e) => app.show_error(format!("Error reading file: {e}")),
+    }
+}
+
+/// Whether a file named `name` is a session rather than a capture
+pub fn is_session_file(name: &str) -> bool {
+    std::path::Path::new(name)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(common::session::SESSION_EXTENSION))
+}
+
 /// Preview files being dropped
 pub fn preview_files_being_dropped(ctx: &egui::Context) {
     use egui::*;
@@ -283,7 +686,7 @@ pub fn preview_files_being_dropped(ctx: &egui::Context) {
         painter.text(
             screen_rect.center(),
             Align2::CENTER_CENTER,
-            "Drop PCAP file here",
+            "Drop PCAP or session file here",
             TextStyle::Heading.resolve(&ctx.style()),
             Color32::WHITE,
         );
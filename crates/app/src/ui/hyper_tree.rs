@@ -1,5 +1,8 @@
 //! AC Protocol-aware JSON tree viewer with hex display and click-to-filter
 
+use common::messages::field_offsets::FieldOffsets;
+use common::value_names::ValueNames;
+use common::weenie::WeenieDatabase;
 use eframe::egui;
 use serde_json::Value;
 use std::collections::HashSet;
@@ -16,24 +19,60 @@ pub enum DisplayFormat {
 #[derive(Default)]
 pub struct TreeResponse {
     pub filter_clicked: Option<String>,
+    /// Path of a field whose name was clicked to locate its bytes
+    pub field_clicked: Option<String>,
+    /// Object whose name was clicked to open it
+    pub object_clicked: Option<u32>,
 }
 
 /// AC Protocol-aware JSON tree viewer
-pub struct AcJsonTree {
+pub struct AcJsonTree<'a> {
     id: String,
     expanded_paths: HashSet<String>,
     response: TreeResponse,
+    field_offsets: Option<&'a FieldOffsets>,
+    selected_field: Option<&'a str>,
+    weenies: Option<&'a WeenieDatabase>,
+    value_names: Option<&'a ValueNames>,
 }
 
-impl AcJsonTree {
+impl<'a> AcJsonTree<'a> {
     pub fn new(id: impl Into<String>) -> Self {
         Self {
             id: id.into(),
             expanded_paths: HashSet::new(),
             response: TreeResponse::default(),
+            field_offsets: None,
+            selected_field: None,
+            weenies: None,
+            value_names: None,
         }
     }
 
+    /// Decode enum, flag and spell values to their names
+    pub fn with_value_names(mut self, value_names: &'a ValueNames) -> Self {
+        self.value_names = Some(value_names);
+        self
+    }
+
+    /// Name object IDs after the objects they refer to, clickable to open them
+    pub fn with_weenies(mut self, weenies: &'a WeenieDatabase) -> Self {
+        self.weenies = Some(weenies);
+        self
+    }
+
+    /// Make field names clickable when their byte range is known
+    pub fn with_field_offsets(mut self, field_offsets: &'a FieldOffsets) -> Self {
+        self.field_offsets = Some(field_offsets);
+        self
+    }
+
+    /// Highlight the field at this path
+    pub fn with_selected_field(mut self, path: Option<&'a str>) -> Self {
+        self.selected_field = path;
+        self
+    }
+
     /// Show the tree and return interaction result
     pub fn show(mut self, ui: &mut egui::Ui, value: &Value) -> TreeResponse {
         self.show_value(ui, value, "", 0);
@@ -50,6 +89,11 @@ impl AcJsonTree {
                     } else {
                         format!("{path}.{key}")
                     };
+                    // Keys of property tables are property numbers
+                    let label = match self.value_names.and_then(|n| n.describe_key(path, key)) {
+                        Some(name) => format!("{key} ({name})"),
+                        None => key.clone(),
+                    };
 
                     match val {
                         Value::Object(_) | Value::Array(_) => {
@@ -57,7 +101,7 @@ impl AcJsonTree {
                             let header_id = egui::Id::new(format!("{}_{}", self.id, item_path));
                             let is_expanded = self.expanded_paths.contains(&item_path) || depth < 1; // Auto-expand first level
 
-                            let header_response = egui::CollapsingHeader::new(key)
+                            let header_response = egui::CollapsingHeader::new(label)
                                 .id_salt(header_id)
                                 .default_open(is_expanded)
                                 .show(ui, |ui| {
@@ -75,8 +119,8 @@ impl AcJsonTree {
                         _ => {
                             // Leaf value - show key and clickable value
                             ui.horizontal(|ui| {
-                                ui.label(format!("{key}:"));
-                                self.show_leaf_value(ui, key, val);
+                                self.show_field_name(ui, &format!("{label}:"), &item_path);
+                                self.show_leaf_value(ui, key, &item_path, val);
                             });
                         }
                     }
@@ -108,8 +152,8 @@ impl AcJsonTree {
                         }
                         _ => {
                             ui.horizontal(|ui| {
-                                ui.label(format!("[{idx}]:"));
-                                self.show_leaf_value(ui, &idx.to_string(), val);
+                                self.show_field_name(ui, &format!("[{idx}]:"), &item_path);
+                                self.show_leaf_value(ui, &idx.to_string(), &item_path, val);
                             });
                         }
                     }
@@ -117,19 +161,47 @@ impl AcJsonTree {
             }
             _ => {
                 // Top-level primitive (unusual, but handle it)
-                self.show_leaf_value(ui, "", value);
+                self.show_leaf_value(ui, "", path, value);
             }
         }
     }
 
+    /// Show a field name, clickable to locate its bytes when its offset is known
+    fn show_field_name(&mut self, ui: &mut egui::Ui, label: &str, path: &str) {
+        let Some(span) = self.field_offsets.and_then(|offsets| offsets.get(path)) else {
+            ui.label(label);
+            return;
+        };
+
+        let selected = self.selected_field == Some(path);
+        let response = ui.selectable_label(selected, label).on_hover_text(format!(
+            "Inferred bytes 0x{:04X}..0x{:04X} ({} bytes), found by matching the value\n\
+             Click to show in the Binary view",
+            span.start,
+            span.end,
+            span.len()
+        ));
+        if response.clicked() {
+            self.response.field_clicked = Some(path.to_string());
+        }
+    }
+
     /// Show a leaf value with appropriate formatting and click handling
-    fn show_leaf_value(&mut self, ui: &mut egui::Ui, key: &str, value: &Value) {
+    fn show_leaf_value(&mut self, ui: &mut egui::Ui, key: &str, path: &str, value: &Value) {
         let format = Self::determine_format(key, value);
 
         match value {
             Value::Number(num) => {
                 if let Some(u) = num.as_u64() {
+                    let name = self.value_names.and_then(|n| n.describe(path, key, u));
+                    if let Some(name) = name {
+                        self.show_named_value(ui, u, &name);
+                        return;
+                    }
                     self.show_number_value(ui, u, format);
+                    if format == DisplayFormat::Hex {
+                        self.show_object_name(ui, key, u);
+                    }
                 } else if let Some(i) = num.as_i64() {
                     self.show_signed_number_value(ui, i, format);
                 } else if let Some(f) = num.as_f64() {
@@ -275,6 +347,48 @@ impl AcJsonTree {
         }
     }
 
+    /// Show an enum, flag or spell value in hex followed by its name
+    fn show_named_value(&mut self, ui: &mut egui::Ui, num: u64, name: &str) {
+        let response = ui
+            .selectable_label(
+                false,
+                egui::RichText::new(format!("0x{num:08X}")).color(ui.visuals().hyperlink_color),
+            )
+            .on_hover_text(format!("{num}\nClick to filter"))
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+
+        if response.clicked() {
+            self.response.filter_clicked = Some(format!("0x{num:X}"));
+        }
+
+        ui.label(format!("({name})"));
+    }
+
+    /// Show the name of the object an ID field refers to, if it's known
+    fn show_object_name(&mut self, ui: &mut egui::Ui, key: &str, id: u64) {
+        if !Self::is_id_field(key) {
+            return;
+        }
+        let Some(name) = u32::try_from(id)
+            .ok()
+            .and_then(|id| self.weenies?.name_of(id))
+        else {
+            return;
+        };
+        if ui
+            .link(format!("({name})"))
+            .on_hover_text("Click to open this object")
+            .clicked()
+        {
+            self.response.object_clicked = Some(id as u32);
+        }
+    }
+
+    /// Whether a field holds an object ID, e.g. `ObjectId`, `ContainerId` or `caster_id`
+    fn is_id_field(key: &str) -> bool {
+        key.ends_with("Id") || key.ends_with("_id")
+    }
+
     /// Show a signed number value
     fn show_signed_number_value(&mut self, ui: &mut egui::Ui, num: i64, format: DisplayFormat) {
         match format {
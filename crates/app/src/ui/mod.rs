@@ -2,7 +2,10 @@
 //!
 //! This module contains all UI-related components and panels.
 
+pub mod bookmark_panel;
 pub mod detail_panel;
+pub mod diagnostics_panel;
+pub mod diff_panel;
 pub mod error_dialog;
 pub mod file_panel;
 pub mod hyper_tree;
@@ -1,8 +1,11 @@
 //! Packet and message list UI components
 
-use crate::filter::{matches_any_filter, parse_filter_string};
-use crate::state::json_contains_string;
+use crate::ui::weenie_panel::object_label;
 use crate::{PcapViewerApp, SortField};
+use common::PacketHeaderFlags;
+use common::extract::{FieldPath, format_values};
+use common::packet_flags::format_packet_flags;
+use common::query::Query;
 // TODO: Re-enable this import when needed
 // use ac_parser::messages::ParsedMessage;
 use eframe::egui;
@@ -213,112 +216,93 @@ fn mobile_header_cell(
     .inner
 }
 
+/// Warn next to the search box when the search isn't a valid query
+///
+/// The list still filters on the whole search as plain text meanwhile.
+pub fn draw_query_error(ui: &mut egui::Ui, search: &str) {
+    if let Err(e) = Query::parse(search) {
+        ui.colored_label(egui::Color32::from_rgb(255, 190, 100), "⚠")
+            .on_hover_text(format!("{e}\nSearching for the text as typed instead"));
+    }
+}
+
+/// Add and remove messages table columns that show a field picked out by a path
+fn draw_column_builder(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
+    ui.label("Columns:");
+    let mut remove = None;
+    for (i, path) in app.extract_columns.iter().enumerate() {
+        if ui
+            .small_button(format!("{path} ✕"))
+            .on_hover_text("Remove column")
+            .clicked()
+        {
+            remove = Some(i);
+        }
+    }
+    if let Some(i) = remove {
+        app.extract_columns.remove(i);
+    }
+
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut app.extract_input)
+            .hint_text("Enchantment.Id.SpellId")
+            .desired_width(160.0),
+    );
+    let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+    match FieldPath::parse(&app.extract_input) {
+        Ok(path) => {
+            let can_add = !path.is_empty();
+            let clicked = ui
+                .add_enabled(can_add, egui::Button::new("+ Column"))
+                .on_hover_text("Show this path of each message's data as a column")
+                .clicked();
+            if can_add && (clicked || submitted) {
+                app.extract_columns.push(path);
+                app.extract_input.clear();
+            }
+        }
+        Err(e) => {
+            ui.colored_label(egui::Color32::from_rgb(255, 190, 100), "⚠")
+                .on_hover_text(e.to_string());
+        }
+    }
+}
+
 /// Show messages list with virtual scrolling for performance
 pub fn show_messages_list(app: &mut PcapViewerApp, ui: &mut egui::Ui, is_mobile: bool) {
     // Pre-collect data to avoid borrow issues
-    let search = app.search_query.to_lowercase();
+    let (query, _) = Query::parse_or_text(&app.capture.search_query);
+    let start_time = app.capture.packets.first().map_or(0.0, |p| p.timestamp);
     let sort_field = app.sort_field;
     let sort_ascending = app.sort_ascending;
-    let total = app.messages.len();
-    let time_filter = app.messages_scrubber.get_selected_range().cloned();
+    let total = app.capture.messages.len();
+    let time_filter = app.capture.messages_scrubber.get_selected_range().cloned();
 
     // Collect timestamps of messages matching search (for highlighting on scrubber)
-    if !search.is_empty() {
-        let filters = parse_filter_string(&search);
+    if !query.is_empty() {
         let search_matched_timestamps: Vec<f64> = app
+            .capture
             .messages
             .iter()
-            .filter(|m| {
-                // Use the same matching logic as the table filtering
-                let mut matches = false;
-
-                // Search in message ID
-                if matches_any_filter(&filters, &m.id.to_string()) {
-                    matches = true;
-                }
-
-                // Check opcode match
-                if !matches && matches_any_filter(&filters, &m.opcode) {
-                    matches = true;
-                }
-
-                // Check direction match
-                if !matches && matches_any_filter(&filters, &m.direction) {
-                    matches = true;
-                }
-
-                // Check if filter matches in data fields
-                if !matches {
-                    let data_str = serde_json::to_string(&m.data).unwrap_or_default();
-                    if matches_any_filter(&filters, &data_str) {
-                        matches = true;
-                    }
-                }
-
-                // Always also do text search (type and data)
-                if !matches {
-                    let type_matches = m.message_type.to_lowercase().contains(&search);
-                    let data_matches = json_contains_string(&m.data, &search);
-                    matches = type_matches || data_matches;
-                }
-
-                matches
-            })
+            .filter(|m| query.matches(m, start_time))
             .map(|m| m.timestamp)
             .collect();
-        app.messages_scrubber
+        app.capture
+            .messages_scrubber
             .set_highlighted_timestamps(search_matched_timestamps);
     } else {
-        app.messages_scrubber.set_highlighted_timestamps(Vec::new());
+        app.capture
+            .messages_scrubber
+            .set_highlighted_timestamps(Vec::new());
     }
 
     let mut filtered: Vec<(usize, usize, String, String, String)> = app
+        .capture
         .messages
         .iter()
         .enumerate()
         .filter(|(_, m)| {
-            // Apply search filter with rich filter support
-            let matches_search = if search.is_empty() {
-                true
-            } else {
-                // Parse search string into rich filters (supports hex, decimal, and text)
-                let filters = parse_filter_string(&search);
-
-                // Check if any filter matches
-                let mut matches = false;
-
-                // Search in message ID
-                if matches_any_filter(&filters, &m.id.to_string()) {
-                    matches = true;
-                }
-
-                // Check opcode match
-                if !matches && matches_any_filter(&filters, &m.opcode) {
-                    matches = true;
-                }
-
-                // Check direction match
-                if !matches && matches_any_filter(&filters, &m.direction) {
-                    matches = true;
-                }
-
-                // Check if filter matches in data fields
-                if !matches {
-                    let data_str = serde_json::to_string(&m.data).unwrap_or_default();
-                    if matches_any_filter(&filters, &data_str) {
-                        matches = true;
-                    }
-                }
-
-                // Always also do text search (type and data)
-                if !matches {
-                    let type_matches = m.message_type.to_lowercase().contains(&search);
-                    let data_matches = json_contains_string(&m.data, &search);
-                    matches = type_matches || data_matches;
-                }
-
-                matches
-            };
+            let matches_search = query.matches(m, start_time);
 
             // Apply time filter
             let matches_time = if let Some(ref range) = time_filter {
@@ -352,6 +336,10 @@ pub fn show_messages_list(app: &mut PcapViewerApp, ui: &mut egui::Ui, is_mobile:
 
     ui.horizontal(|ui| {
         ui.label(format!("{}/{} messages", filtered.len(), total));
+        if !is_mobile {
+            ui.separator();
+            draw_column_builder(app, ui);
+        }
     });
     ui.separator();
 
@@ -368,6 +356,15 @@ fn show_messages_table(
 ) {
     let available_width = ui.available_width();
 
+    // Row to bring into view after jumping to a message from elsewhere
+    let scroll_row = if std::mem::take(&mut app.capture.scroll_to_selected_message) {
+        filtered
+            .iter()
+            .position(|(idx, ..)| app.capture.selected_message == Some(*idx))
+    } else {
+        None
+    };
+
     if is_mobile {
         let widths = [
             available_width * 0.12,
@@ -445,19 +442,23 @@ fn show_messages_table(
             .column(Column::auto())
             .column(Column::auto())
             .min_scrolled_height(0.0);
+        let table = match scroll_row {
+            Some(row) => table.scroll_to_row(row, Some(egui::Align::Center)),
+            None => table,
+        };
 
         table.body(|body| {
             body.rows(20.0, filtered.len(), |mut row| {
                 let idx = row.index();
                 let (original_idx, id, msg_type, direction, _opcode) = &filtered[idx];
-                let is_selected = app.selected_message == Some(*original_idx);
-                let is_marked = app.marked_messages.contains(original_idx);
+                let is_selected = app.capture.selected_message == Some(*original_idx);
+                let is_marked = app.capture.marked_messages.contains(original_idx);
 
                 row.col(|ui| {
                     if mobile_cell(ui, widths[0], false, is_selected, is_marked, id.to_string())
                         .clicked()
                     {
-                        app.selected_message = Some(*original_idx);
+                        app.capture.selected_message = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
@@ -471,7 +472,7 @@ fn show_messages_table(
                     if mobile_cell(ui, widths[1], false, is_selected, is_marked, display_type)
                         .clicked()
                     {
-                        app.selected_message = Some(*original_idx);
+                        app.capture.selected_message = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
@@ -493,7 +494,7 @@ fn show_messages_table(
                     )
                     .clicked()
                     {
-                        app.selected_message = Some(*original_idx);
+                        app.capture.selected_message = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
@@ -540,10 +541,15 @@ fn show_messages_table(
                     app.sort_ascending = true;
                 }
             }
+            ui.label(egui::RichText::new("Object").strong());
+            for path in &app.extract_columns {
+                ui.label(egui::RichText::new(path.as_str()).strong());
+            }
         });
         ui.separator();
 
         // Desktop table
+        let extract_columns = app.extract_columns.clone();
         let table = egui_extras::TableBuilder::new(ui)
             .striped(true)
             .resizable(true)
@@ -552,18 +558,28 @@ fn show_messages_table(
             .column(Column::initial(200.0).range(100.0..=(available_width - 200.0).max(100.0)))
             .column(Column::initial(60.0).range(40.0..=100.0))
             .column(Column::initial(100.0).range(60.0..=150.0))
+            .column(Column::initial(180.0).range(80.0..=400.0))
+            .columns(
+                Column::initial(140.0).range(60.0..=400.0),
+                extract_columns.len(),
+            )
             .min_scrolled_height(0.0);
+        let table = match scroll_row {
+            Some(row) => table.scroll_to_row(row, Some(egui::Align::Center)),
+            None => table,
+        };
 
+        let mut open_object = None;
         table.body(|body| {
             body.rows(20.0, filtered.len(), |mut row| {
                 let idx = row.index();
                 let (original_idx, id, msg_type, direction, opcode) = &filtered[idx];
-                let is_selected = app.selected_message == Some(*original_idx);
-                let is_marked = app.marked_messages.contains(original_idx);
+                let is_selected = app.capture.selected_message == Some(*original_idx);
+                let is_marked = app.capture.marked_messages.contains(original_idx);
 
                 row.col(|ui| {
                     if desktop_marked_cell(ui, is_selected, is_marked, id.to_string()).clicked() {
-                        app.selected_message = Some(*original_idx);
+                        app.capture.selected_message = Some(*original_idx);
                     }
                 });
 
@@ -571,7 +587,7 @@ fn show_messages_table(
                     if desktop_marked_cell(ui, is_selected, is_marked, msg_type.to_string())
                         .clicked()
                     {
-                        app.selected_message = Some(*original_idx);
+                        app.capture.selected_message = Some(*original_idx);
                     }
                 });
 
@@ -589,30 +605,72 @@ fn show_messages_table(
                     )
                     .clicked()
                     {
-                        app.selected_message = Some(*original_idx);
+                        app.capture.selected_message = Some(*original_idx);
                     }
                 });
 
                 row.col(|ui| {
                     if desktop_marked_cell(ui, is_selected, is_marked, opcode.to_string()).clicked()
                     {
-                        app.selected_message = Some(*original_idx);
+                        app.capture.selected_message = Some(*original_idx);
+                    }
+                });
+
+                // The objects the message referenced, the first clickable to open it
+                row.col(|ui| {
+                    let objects = app.capture.weenie_db.objects_in_message(*id);
+                    let Some(first) = objects.first() else {
+                        return;
+                    };
+                    let mut text = object_label(first.object_id, first.name.as_deref());
+                    if objects.len() > 1 {
+                        text.push_str(&format!(" +{}", objects.len() - 1));
+                    }
+                    if ui
+                        .link(text)
+                        .on_hover_text("Click to open this object")
+                        .clicked()
+                    {
+                        open_object = Some(first.object_id);
                     }
                 });
+
+                for path in &extract_columns {
+                    let text =
+                        format_values(path.select(&app.capture.messages[*original_idx].data));
+                    row.col(|ui| {
+                        if desktop_marked_cell(ui, is_selected, is_marked, text).clicked() {
+                            app.capture.selected_message = Some(*original_idx);
+                        }
+                    });
+                }
             });
         });
+
+        if let Some(object_id) = open_object {
+            app.go_to_weenie(object_id);
+        }
     }
 }
 
+/// Number of blob fragments a packet carried and how many belong to messages that
+/// never completed
+#[derive(Clone, Copy)]
+struct FragmentCounts {
+    total: usize,
+    incomplete: usize,
+}
+
 /// Show packets list with virtual scrolling for performance
 pub fn show_packets_list(app: &mut PcapViewerApp, ui: &mut egui::Ui, is_mobile: bool) {
     // Pre-collect data to avoid borrow issues
     let sort_field = app.sort_field;
     let sort_ascending = app.sort_ascending;
-    let total = app.packets.len();
-    let time_filter = app.fragments_scrubber.get_selected_range().cloned();
+    let total = app.capture.packets.len();
+    let time_filter = app.capture.fragments_scrubber.get_selected_range().cloned();
 
-    let mut filtered: Vec<(usize, usize, u32, String, u32, u16)> = app
+    let mut filtered: Vec<(usize, usize, u32, String, u32, u16, FragmentCounts)> = app
+        .capture
         .packets
         .iter()
         .enumerate()
@@ -629,9 +687,13 @@ pub fn show_packets_list(app: &mut PcapViewerApp, ui: &mut egui::Ui, is_mobile:
                 idx,
                 p.id,
                 p.header.sequence,
-                format!("{:?}", p.direction),
+                p.direction.clone(),
                 p.header.flags.bits(),
                 p.header.size,
+                FragmentCounts {
+                    total: p.fragments.len(),
+                    incomplete: p.fragments.iter().filter(|f| !f.complete).count(),
+                },
             )
         })
         .collect();
@@ -659,7 +721,7 @@ fn show_packets_table(
     app: &mut PcapViewerApp,
     ui: &mut egui::Ui,
     is_mobile: bool,
-    filtered: &[(usize, usize, u32, String, u32, u16)],
+    filtered: &[(usize, usize, u32, String, u32, u16, FragmentCounts)],
     sort_field: SortField,
     sort_ascending: bool,
 ) {
@@ -769,15 +831,15 @@ fn show_packets_table(
         table.body(|body| {
             body.rows(20.0, filtered.len(), |mut row| {
                 let idx = row.index();
-                let (original_idx, id, seq, direction, flags, size) = &filtered[idx];
-                let is_selected = app.selected_packet == Some(*original_idx);
-                let is_marked = app.marked_packets.contains(original_idx);
+                let (original_idx, id, seq, direction, flags, size, _) = &filtered[idx];
+                let is_selected = app.capture.selected_packet == Some(*original_idx);
+                let is_marked = app.capture.marked_packets.contains(original_idx);
 
                 row.col(|ui| {
                     if mobile_cell(ui, widths[0], false, is_selected, is_marked, id.to_string())
                         .clicked()
                     {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
@@ -793,18 +855,18 @@ fn show_packets_table(
                     )
                     .clicked()
                     {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
 
                 row.col(|ui| {
-                    let dir_color = if direction == "ClientToServer" {
+                    let dir_color = if direction == "Send" {
                         egui::Color32::from_rgb(100, 200, 255)
                     } else {
                         egui::Color32::from_rgb(100, 255, 150)
                     };
-                    let dir_text = if direction == "ClientToServer" {
+                    let dir_text = if direction == "Send" {
                         "C→S"
                     } else {
                         "S→C"
@@ -819,7 +881,7 @@ fn show_packets_table(
                     )
                     .clicked()
                     {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
@@ -835,7 +897,7 @@ fn show_packets_table(
                     )
                     .clicked()
                     {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
@@ -851,7 +913,7 @@ fn show_packets_table(
                     )
                     .clicked()
                     {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                         app.show_detail_panel = true;
                     }
                 });
@@ -894,9 +956,10 @@ fn show_packets_table(
                     app.sort_ascending = true;
                 }
             }
-            // Flags, Size are not sortable
+            // Flags, Size and Fragments are not sortable
             ui.strong("Flags");
             ui.strong("Size");
+            ui.strong("Fragments");
         });
         ui.separator();
 
@@ -908,31 +971,32 @@ fn show_packets_table(
             .column(Column::initial(60.0).range(40.0..=100.0))
             .column(Column::initial(100.0).range(60.0..=150.0))
             .column(Column::initial(100.0).range(60.0..=150.0))
-            .column(Column::initial(80.0).range(50.0..=120.0))
+            .column(Column::initial(160.0).range(80.0..=300.0))
             .column(Column::initial(60.0).range(40.0..=100.0))
+            .column(Column::initial(120.0).range(60.0..=200.0))
             .min_scrolled_height(0.0);
 
         table.body(|body| {
             body.rows(20.0, filtered.len(), |mut row| {
                 let idx = row.index();
-                let (original_idx, id, seq, direction, flags, size) = &filtered[idx];
-                let is_selected = app.selected_packet == Some(*original_idx);
-                let is_marked = app.marked_packets.contains(original_idx);
+                let (original_idx, id, seq, direction, flags, size, fragments) = &filtered[idx];
+                let is_selected = app.capture.selected_packet == Some(*original_idx);
+                let is_marked = app.capture.marked_packets.contains(original_idx);
 
                 row.col(|ui| {
                     if desktop_marked_cell(ui, is_selected, is_marked, id.to_string()).clicked() {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                     }
                 });
 
                 row.col(|ui| {
                     if desktop_marked_cell(ui, is_selected, is_marked, seq.to_string()).clicked() {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                     }
                 });
 
                 row.col(|ui| {
-                    let dir_color = if direction == "ClientToServer" {
+                    let dir_color = if direction == "Send" {
                         egui::Color32::from_rgb(100, 200, 255)
                     } else {
                         egui::Color32::from_rgb(100, 255, 150)
@@ -945,21 +1009,41 @@ fn show_packets_table(
                     )
                     .clicked()
                     {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                     }
                 });
 
                 row.col(|ui| {
-                    if desktop_marked_cell(ui, is_selected, is_marked, format!("{flags:08X}"))
+                    let flag_names =
+                        format_packet_flags(PacketHeaderFlags::from_bits_retain(*flags));
+                    if desktop_marked_cell(ui, is_selected, is_marked, flag_names)
+                        .on_hover_text(format!("0x{flags:08X}"))
                         .clicked()
                     {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
                     }
                 });
 
                 row.col(|ui| {
                     if desktop_marked_cell(ui, is_selected, is_marked, size.to_string()).clicked() {
-                        app.selected_packet = Some(*original_idx);
+                        app.capture.selected_packet = Some(*original_idx);
+                    }
+                });
+
+                row.col(|ui| {
+                    let text = if fragments.total == 0 {
+                        egui::RichText::new("")
+                    } else if fragments.incomplete > 0 {
+                        egui::RichText::new(format!(
+                            "{} ({} incomplete)",
+                            fragments.total, fragments.incomplete
+                        ))
+                        .color(ui.visuals().warn_fg_color)
+                    } else {
+                        egui::RichText::new(fragments.total.to_string())
+                    };
+                    if desktop_marked_cell(ui, is_selected, is_marked, text).clicked() {
+                        app.capture.selected_packet = Some(*original_idx);
                     }
                 });
             });
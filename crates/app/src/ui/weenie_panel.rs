@@ -1,21 +1,85 @@
 use crate::PcapViewerApp;
-use common::Tab;
+use crate::ui::bookmark_panel::show_bookmark_editor;
+use common::session::BookmarkTarget;
+use common::weenie::Weenie;
 use eframe::egui;
 use egui::ScrollArea;
 use egui_extras::{Column, TableBuilder};
 
+/// An object ID with the object's name when it's known, e.g. `0x5000A1B2 (Pantaloons)`
+pub fn object_label(object_id: u32, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("0x{object_id:08X} ({name})"),
+        None => format!("0x{object_id:08X}"),
+    }
+}
+
+/// The instant the weenies tab shows objects as of, if not their latest state
+///
+/// Hovering or selecting a range on the scrubber takes precedence over a pinned instant.
+fn view_time(app: &PcapViewerApp) -> Option<f64> {
+    app.capture
+        .weenies_scrubber
+        .focus_time()
+        .or(app.capture.weenie_pinned_time)
+}
+
+/// Weenies sorted by object ID, as of the scrubber's instant
+fn visible_weenies(app: &mut PcapViewerApp) -> Vec<Weenie> {
+    let Some(time) = view_time(app) else {
+        return app
+            .capture
+            .weenie_db
+            .sorted_weenies()
+            .into_iter()
+            .cloned()
+            .collect();
+    };
+    // Reconstructing replays every change, so only redo it when the instant moves
+    if app
+        .capture
+        .weenie_state_cache
+        .as_ref()
+        .is_none_or(|(cached, _)| *cached != time)
+    {
+        app.capture.weenie_state_cache = Some((time, app.capture.weenie_db.state_at(time)));
+    }
+    app.capture
+        .weenie_state_cache
+        .as_ref()
+        .map(|(_, db)| db.sorted_weenies().into_iter().cloned().collect())
+        .unwrap_or_default()
+}
+
 pub fn show_weenie_panel(app: &mut PcapViewerApp, ui: &mut egui::Ui, is_mobile: bool) {
     // Clone weenies to avoid borrow checker issues
-    let weenies: Vec<common::weenie::Weenie> = app
-        .weenie_db
-        .sorted_weenies()
-        .into_iter()
-        .cloned()
-        .collect();
+    let weenies = visible_weenies(app);
 
     ui.horizontal(|ui| {
         ui.heading("Weenies");
         ui.label(format!("({} objects)", weenies.len()));
+
+        if let Some(time) = view_time(app) {
+            let start = app
+                .capture
+                .weenies_scrubber
+                .data_range
+                .as_ref()
+                .map_or(time, |r| r.min);
+            ui.separator();
+            ui.label(
+                egui::RichText::new(format!("As of {:.3}s", time - start))
+                    .color(egui::Color32::from_rgb(255, 190, 100)),
+            );
+            if ui
+                .small_button("Latest")
+                .on_hover_text("Show every object as it was at the end of the capture")
+                .clicked()
+            {
+                app.capture.weenie_pinned_time = None;
+                app.capture.weenies_scrubber.reset_selection();
+            }
+        }
     });
 
     ui.separator();
@@ -23,16 +87,16 @@ pub fn show_weenie_panel(app: &mut PcapViewerApp, ui: &mut egui::Ui, is_mobile:
     // Filter input
     ui.horizontal(|ui| {
         ui.label("Filter:");
-        ui.text_edit_singleline(&mut app.search_query);
+        ui.text_edit_singleline(&mut app.capture.search_query);
         if ui.button("Clear").clicked() {
-            app.search_query.clear();
+            app.capture.search_query.clear();
         }
     });
 
     ui.separator();
 
     // Filter weenies
-    let filter_lower = app.search_query.to_lowercase();
+    let filter_lower = app.capture.search_query.to_lowercase();
     let filtered_weenies: Vec<&common::weenie::Weenie> = weenies
         .iter()
         .filter(|w| {
@@ -69,7 +133,7 @@ fn show_weenie_table(
                 body.rows(20.0, weenies.len(), |mut row| {
                     let row_index = row.index();
                     let weenie = weenies[row_index];
-                    let is_selected = app.selected_weenie == Some(row_index);
+                    let is_selected = app.capture.selected_weenie == Some(weenie.object_id);
 
                     row.set_selected(is_selected);
 
@@ -85,7 +149,7 @@ fn show_weenie_table(
                             )
                             .clicked()
                         {
-                            app.selected_weenie = Some(row_index);
+                            app.capture.selected_weenie = Some(weenie.object_id);
                         }
                     });
                 });
@@ -119,7 +183,7 @@ fn show_weenie_table(
                 body.rows(20.0, weenies.len(), |mut row| {
                     let row_index = row.index();
                     let weenie = weenies[row_index];
-                    let is_selected = app.selected_weenie == Some(row_index);
+                    let is_selected = app.capture.selected_weenie == Some(weenie.object_id);
 
                     row.set_selected(is_selected);
 
@@ -128,14 +192,14 @@ fn show_weenie_table(
                             .selectable_label(is_selected, format!("{}", weenie.object_id))
                             .clicked()
                         {
-                            app.selected_weenie = Some(row_index);
+                            app.capture.selected_weenie = Some(weenie.object_id);
                         }
                     });
 
                     row.col(|ui| {
                         let name = weenie.name.as_deref().unwrap_or("<unknown>");
                         if ui.selectable_label(is_selected, name).clicked() {
-                            app.selected_weenie = Some(row_index);
+                            app.capture.selected_weenie = Some(weenie.object_id);
                         }
                     });
 
@@ -152,7 +216,7 @@ fn show_weenie_table(
                             .selectable_label(is_selected, format!("{}", prop_count))
                             .clicked()
                         {
-                            app.selected_weenie = Some(row_index);
+                            app.capture.selected_weenie = Some(weenie.object_id);
                         }
                     });
 
@@ -161,7 +225,7 @@ fn show_weenie_table(
                             .selectable_label(is_selected, format!("{}", weenie.message_count))
                             .clicked()
                         {
-                            app.selected_weenie = Some(row_index);
+                            app.capture.selected_weenie = Some(weenie.object_id);
                         }
                     });
                 });
@@ -171,37 +235,17 @@ fn show_weenie_table(
 
 pub fn show_weenie_detail(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
     // Clone weenies to avoid borrow checker issues
-    let weenies: Vec<common::weenie::Weenie> = app
-        .weenie_db
-        .sorted_weenies()
-        .into_iter()
-        .cloned()
-        .collect();
-
-    // Filter weenies
-    let filter_lower = app.search_query.to_lowercase();
-    let filtered_weenies: Vec<&common::weenie::Weenie> = weenies
-        .iter()
-        .filter(|w| {
-            if filter_lower.is_empty() {
-                true
-            } else {
-                w.object_id.to_string().contains(&filter_lower)
-                    || w.name
-                        .as_ref()
-                        .map(|n| n.to_lowercase().contains(&filter_lower))
-                        .unwrap_or(false)
-            }
-        })
-        .collect();
+    let weenies = visible_weenies(app);
 
-    if let Some(idx) = app.selected_weenie {
-        if let Some(weenie) = filtered_weenies.get(idx) {
+    if let Some(object_id) = app.capture.selected_weenie {
+        if let Some(weenie) = weenies.iter().find(|w| w.object_id == object_id) {
             ScrollArea::vertical().show(ui, |ui| {
                 ui.heading(format!(
                     "Weenie: {}",
                     weenie.name.as_deref().unwrap_or("<unknown>")
                 ));
+                let default_label = object_label(object_id, weenie.name.as_deref());
+                show_bookmark_editor(app, ui, BookmarkTarget::Object(object_id), || default_label);
                 ui.separator();
 
                 egui::Grid::new("weenie_detail_grid")
@@ -223,6 +267,18 @@ pub fn show_weenie_detail(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
                         ui.label(format!("{:.3}s", weenie.first_seen));
                         ui.end_row();
 
+                        if let Some(created_at) = weenie.created_at() {
+                            ui.label("Created:");
+                            ui.label(format!("{:.3}s", created_at));
+                            ui.end_row();
+                        }
+
+                        if let Some(deleted_at) = weenie.deleted_at() {
+                            ui.label("Deleted:");
+                            ui.label(format!("{:.3}s", deleted_at));
+                            ui.end_row();
+                        }
+
                         ui.label("Last Updated:");
                         ui.label(format!("{:.3}s", weenie.last_updated));
                         ui.end_row();
@@ -234,20 +290,7 @@ pub fn show_weenie_detail(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
 
                 ui.separator();
 
-                // Message IDs section with clickable links
-                if !weenie.message_ids.is_empty() {
-                    ui.heading("Referenced in Messages:");
-                    ui.horizontal_wrapped(|ui| {
-                        for &msg_id in &weenie.message_ids {
-                            if ui.link(format!("#{}", msg_id)).clicked() {
-                                // Switch to Messages tab and select this message
-                                app.current_tab = Tab::Messages;
-                                app.selected_message = Some(msg_id);
-                            }
-                        }
-                    });
-                    ui.separator();
-                }
+                show_messages(app, ui, weenie);
 
                 // Properties sections
                 show_property_section(ui, "Int Properties", &weenie.int_properties);
@@ -257,6 +300,12 @@ pub fn show_weenie_detail(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
                 show_property_section(ui, "String Properties", &weenie.string_properties);
                 show_property_section(ui, "DataId Properties", &weenie.data_id_properties);
                 show_property_section(ui, "InstanceId Properties", &weenie.instance_id_properties);
+
+                show_history(app, ui, weenie);
+            });
+        } else {
+            ui.vertical_centered(|ui| {
+                ui.label("Not seen yet at this point in the capture");
             });
         }
     } else {
@@ -266,6 +315,114 @@ pub fn show_weenie_detail(app: &mut PcapViewerApp, ui: &mut egui::Ui) {
     }
 }
 
+/// Every message that referenced the object, linking to each in the Messages tab
+fn show_messages(app: &mut PcapViewerApp, ui: &mut egui::Ui, weenie: &Weenie) {
+    let messages = weenie.messages();
+    if messages.is_empty() {
+        return;
+    }
+
+    egui::CollapsingHeader::new(format!("Referenced in {} messages", messages.len()))
+        .id_salt("weenie_messages")
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid::new("weenie_messages_grid")
+                .num_columns(3)
+                .spacing([10.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Time");
+                    ui.strong("Message");
+                    ui.strong("Type");
+                    ui.end_row();
+
+                    for (message_id, timestamp) in messages {
+                        ui.label(format!("{timestamp:.3}s"));
+                        if ui.link(format!("#{message_id}")).clicked() {
+                            app.go_to_message(message_id);
+                        }
+                        let message_type = app
+                            .message_index(message_id)
+                            .map_or("", |idx| app.capture.messages[idx].message_type.as_str());
+                        ui.label(message_type);
+                        ui.end_row();
+                    }
+                });
+        });
+
+    ui.separator();
+}
+
+/// Timeline of the object's property changes, linking to the message behind each
+fn show_history(app: &mut PcapViewerApp, ui: &mut egui::Ui, weenie: &Weenie) {
+    if weenie.history.is_empty() {
+        return;
+    }
+
+    let mut properties: Vec<&str> = weenie.history.iter().map(|c| c.property.as_str()).collect();
+    properties.sort_unstable();
+    properties.dedup();
+    // A property picked for another object may not exist on this one
+    let selected = app
+        .capture
+        .weenie_history_property
+        .as_deref()
+        .filter(|p| properties.contains(p))
+        .map(str::to_string);
+
+    ui.horizontal(|ui| {
+        ui.heading(format!("Timeline ({} changes)", weenie.history.len()));
+        egui::ComboBox::from_id_salt("weenie_history_property")
+            .selected_text(selected.as_deref().unwrap_or("All properties"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut app.capture.weenie_history_property,
+                    None,
+                    "All properties",
+                );
+                for property in &properties {
+                    ui.selectable_value(
+                        &mut app.capture.weenie_history_property,
+                        Some(property.to_string()),
+                        *property,
+                    );
+                }
+            });
+    });
+
+    egui::Grid::new("weenie_history_grid")
+        .num_columns(4)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Time");
+            ui.strong("Message");
+            ui.strong("Property");
+            ui.strong("Change");
+            ui.end_row();
+
+            for change in weenie
+                .history
+                .iter()
+                .filter(|c| selected.as_ref().is_none_or(|p| &c.property == p))
+            {
+                ui.label(format!("{:.3}s", change.timestamp));
+                if ui.link(format!("#{}", change.message_id)).clicked() {
+                    app.go_to_message(change.message_id);
+                }
+                ui.label(&change.property)
+                    .on_hover_text(format!("{:?} property", change.kind));
+                match &change.old_value {
+                    Some(old) => ui.label(format!("{old} → {}", change.new_value)),
+                    None => ui.label(change.new_value.to_string()),
+                };
+                ui.end_row();
+            }
+        });
+
+    ui.separator();
+}
+
 fn show_property_section<K, V>(
     ui: &mut egui::Ui,
     title: &str,
@@ -0,0 +1,851 @@
+//! Command-line interface for AC PCAP Viewer
+//!
+//! Parses a capture with the same `PacketParser` as the GUI and prints messages, packets,
+//! weenies or summary statistics, so captures can be analyzed in scripts and pipelines,
+//! or browses them interactively with the `tui` subcommand. JSONL messages and packets in
+//! capture order are written as the capture is decoded; everything else needs it all.
+
+mod output;
+mod tui;
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use common::diff::{DiffOptions, diff_captures};
+use common::extract::{FieldPath, format_values};
+use common::extractor_rules::ExtractorRegistry;
+use common::messages::ParsedMessage;
+use common::packet_flags::format_packet_flags;
+use common::query::Query;
+use common::stream::{PacketStream, ParseEvent};
+use common::weenie::{Weenie, WeenieDatabase};
+use common::{PacketParser, ParsedPacket};
+use output::{OutputFormat, Table, relative_time, write_line, write_records};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(
+    name = "ac-pcap-cli",
+    version,
+    about = "Inspect Asheron's Call packet captures"
+)]
+struct Cli {
+    /// Capture file to read
+    #[arg(short, long, global = true, default_value = "capture.pcap")]
+    file: PathBuf,
+
+    /// Weenie extractor rules (TOML or JSON) to use on top of the built-in ones
+    #[arg(long, global = true, value_name = "PATH")]
+    rules: Option<PathBuf>,
+
+    /// Defaults to `messages` with JSONL output
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List decoded game messages
+    Messages(MessagesArgs),
+    /// List transport packets
+    Packets(PacketsArgs),
+    /// List objects seen in the capture with their accumulated properties
+    Weenies(WeeniesArgs),
+    /// Print counts of packets, messages, message types and diagnostics
+    Summary(SummaryArgs),
+    /// Compare the capture's messages with another capture's
+    Diff(DiffArgs),
+    /// Browse messages and packets in an interactive terminal UI
+    Tui,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DirectionArg {
+    /// Client to server
+    Send,
+    /// Server to client
+    Recv,
+}
+
+impl DirectionArg {
+    fn matches(self, direction: &str) -> bool {
+        match self {
+            DirectionArg::Send => direction == "Send",
+            DirectionArg::Recv => direction == "Recv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortArg {
+    Id,
+    Type,
+    Direction,
+}
+
+#[derive(Args)]
+struct MessagesArgs {
+    /// Only messages whose type contains this text (case-insensitive)
+    #[arg(short = 't', long)]
+    filter_type: Option<String>,
+
+    #[arg(short, long, value_enum)]
+    direction: Option<DirectionArg>,
+
+    /// Only messages matching a query, e.g. `type:Magic AND data.ObjectId==0x5000001A`
+    #[arg(short, long)]
+    query: Option<String>,
+
+    /// Print only this field of each message's data, e.g. `Enchantment.Id.SpellId`;
+    /// repeat for more fields. Messages without any of them are skipped
+    #[arg(short = 'x', long = "extract", value_name = "PATH")]
+    extract: Vec<String>,
+
+    #[arg(short, long, value_enum, default_value_t = SortArg::Id)]
+    sort: SortArg,
+
+    /// Reverse the sort order
+    #[arg(short, long)]
+    reverse: bool,
+
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Jsonl)]
+    output: OutputFormat,
+
+    /// Stop after this many results
+    #[arg(short, long)]
+    limit: Option<usize>,
+}
+
+impl Default for MessagesArgs {
+    fn default() -> Self {
+        Self {
+            filter_type: None,
+            direction: None,
+            query: None,
+            extract: Vec::new(),
+            sort: SortArg::Id,
+            reverse: false,
+            output: OutputFormat::Jsonl,
+            limit: None,
+        }
+    }
+}
+
+impl MessagesArgs {
+    /// Whether messages can be written as they're decoded
+    fn streams(&self) -> bool {
+        self.output == OutputFormat::Jsonl && self.sort == SortArg::Id && !self.reverse
+    }
+}
+
+#[derive(Args)]
+struct PacketsArgs {
+    #[arg(short, long, value_enum)]
+    direction: Option<DirectionArg>,
+
+    /// Sorting by type sorts by packet flags
+    #[arg(short, long, value_enum, default_value_t = SortArg::Id)]
+    sort: SortArg,
+
+    /// Reverse the sort order
+    #[arg(short, long)]
+    reverse: bool,
+
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Jsonl)]
+    output: OutputFormat,
+
+    /// Stop after this many results
+    #[arg(short, long)]
+    limit: Option<usize>,
+}
+
+impl PacketsArgs {
+    /// Whether packets can be written as they're decoded
+    fn streams(&self) -> bool {
+        self.output == OutputFormat::Jsonl && self.sort == SortArg::Id && !self.reverse
+    }
+}
+
+#[derive(Args)]
+struct WeeniesArgs {
+    /// Only objects whose name contains this text (case-insensitive)
+    #[arg(short, long)]
+    name: Option<String>,
+
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Jsonl)]
+    output: OutputFormat,
+
+    /// Stop after this many results
+    #[arg(short, long)]
+    limit: Option<usize>,
+}
+
+#[derive(Args)]
+struct SummaryArgs {
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Capture to compare with, e.g. an emulator capture of the same action
+    #[arg(short, long, value_name = "FILE")]
+    against: PathBuf,
+
+    /// Leave this field out of the comparison wherever it appears; repeat for more
+    #[arg(short, long, value_name = "FIELD")]
+    ignore: Vec<String>,
+
+    /// JSON writes the whole report, JSONL one difference per line
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    /// Stop after this many differences
+    #[arg(short, long)]
+    limit: Option<usize>,
+}
+
+/// Everything parsed from the capture
+struct Capture {
+    packets: Vec<ParsedPacket>,
+    messages: Vec<ParsedMessage>,
+    weenie_db: WeenieDatabase,
+    parser: PacketParser,
+}
+
+impl Capture {
+    fn load(path: &PathBuf, rules: Option<&PathBuf>) -> Result<Self> {
+        let mut parser = parser(rules)?;
+        let (packets, messages, weenie_db) = parser
+            .parse_pcap(open(path)?)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Self {
+            packets,
+            messages,
+            weenie_db,
+            parser,
+        })
+    }
+
+    /// Timestamp of the first packet, which displayed times are relative to
+    fn start_time(&self) -> f64 {
+        self.packets.first().map_or(0.0, |p| p.timestamp)
+    }
+}
+
+/// A parser with the built-in weenie extractor rules, and those in `rules` on top
+fn parser(rules: Option<&PathBuf>) -> Result<PacketParser> {
+    Ok(match rules {
+        Some(rules) => {
+            let rules = ExtractorRegistry::load_over_builtin(rules)?;
+            PacketParser::with_rules(Arc::new(rules))
+        }
+        None => PacketParser::new(),
+    })
+}
+
+fn open(path: &PathBuf) -> Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+/// Decode the capture one record at a time
+fn stream(path: &PathBuf, rules: Option<&PathBuf>) -> Result<PacketStream<BufReader<File>>> {
+    parser(rules)?
+        .stream(open(path)?)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn main() -> Result<()> {
+    let Cli {
+        file,
+        rules,
+        command,
+    } = Cli::parse();
+    let command = command.unwrap_or_else(|| Command::Messages(MessagesArgs::default()));
+
+    if matches!(command, Command::Tui) {
+        return tui::run(&Capture::load(&file, rules.as_ref())?);
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    match command {
+        Command::Messages(args) if args.streams() => {
+            stream_messages(&mut out, stream(&file, rules.as_ref())?, &args)?
+        }
+        Command::Packets(args) if args.streams() => {
+            stream_packets(&mut out, stream(&file, rules.as_ref())?, &args)?
+        }
+        command => {
+            let capture = Capture::load(&file, rules.as_ref())?;
+            match command {
+                Command::Messages(args) => messages(&mut out, &capture, &args)?,
+                Command::Packets(args) => packets(&mut out, &capture, &args)?,
+                Command::Weenies(args) => weenies(&mut out, &capture, &args)?,
+                Command::Summary(args) => summary(&mut out, &capture, &args)?,
+                Command::Diff(args) => {
+                    let other = Capture::load(&args.against, rules.as_ref())?;
+                    diff(&mut out, &capture, &other, &args)?
+                }
+                Command::Tui => unreachable!("handled above"),
+            }
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// The messages picked out by `MessagesArgs`
+struct MessageFilter {
+    filter_type: Option<String>,
+    direction: Option<DirectionArg>,
+    query: Query,
+    paths: Vec<FieldPath>,
+}
+
+impl MessageFilter {
+    fn new(args: &MessagesArgs) -> Result<Self> {
+        Ok(Self {
+            filter_type: args.filter_type.as_ref().map(|t| t.to_lowercase()),
+            direction: args.direction,
+            query: Query::parse(args.query.as_deref().unwrap_or("")).context("Invalid query")?,
+            paths: args
+                .extract
+                .iter()
+                .map(|p| FieldPath::parse(p).with_context(|| format!("Invalid path `{p}`")))
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+
+    /// `start` is the capture's first timestamp, which query times are relative to
+    fn matches(&self, m: &ParsedMessage, start: f64) -> bool {
+        self.filter_type
+            .as_ref()
+            .is_none_or(|t| m.message_type.to_lowercase().contains(t))
+            && self.direction.is_none_or(|d| d.matches(&m.direction))
+            && self.query.matches(m, start)
+            && (self.paths.is_empty() || self.paths.iter().any(|p| !p.select(&m.data).is_empty()))
+    }
+}
+
+fn messages(out: &mut impl Write, capture: &Capture, args: &MessagesArgs) -> Result<()> {
+    let filter = MessageFilter::new(args)?;
+    let paths = &filter.paths;
+    let start = capture.start_time();
+    let mut messages: Vec<&ParsedMessage> = capture
+        .messages
+        .iter()
+        .filter(|m| filter.matches(m, start))
+        .collect();
+
+    match args.sort {
+        SortArg::Id => messages.sort_by_key(|m| m.id),
+        SortArg::Type => messages.sort_by(|a, b| a.message_type.cmp(&b.message_type)),
+        SortArg::Direction => messages.sort_by(|a, b| a.direction.cmp(&b.direction)),
+    }
+    if args.reverse {
+        messages.reverse();
+    }
+    if let Some(limit) = args.limit {
+        messages.truncate(limit);
+    }
+    if !paths.is_empty() {
+        return extracted(out, &messages, paths, args.output, start);
+    }
+
+    write_records(out, &messages, args.output, |messages| {
+        let mut table = Table::new(&["Id", "Type", "Dir", "OpCode", "Time"]);
+        for m in messages {
+            table.row(vec![
+                m.id.to_string(),
+                m.message_type.clone(),
+                m.direction.clone(),
+                m.opcode.clone(),
+                relative_time(m.timestamp, start),
+            ]);
+        }
+        table
+    })
+}
+
+/// Write JSONL messages in capture order as they're decoded
+fn stream_messages<R: Read>(
+    out: &mut impl Write,
+    events: PacketStream<R>,
+    args: &MessagesArgs,
+) -> Result<()> {
+    let filter = MessageFilter::new(args)?;
+    let limit = args.limit.unwrap_or(usize::MAX);
+    let mut start = None;
+    let mut written = 0;
+    for event in events {
+        if written >= limit {
+            break;
+        }
+        match event {
+            ParseEvent::Packet(packet) => {
+                start.get_or_insert(packet.timestamp);
+            }
+            // A message always follows the packet that completed it
+            ParseEvent::Message(m) if filter.matches(&m, start.unwrap_or(m.timestamp)) => {
+                if filter.paths.is_empty() {
+                    write_line(out, &m)?;
+                } else {
+                    write_line(out, &ExtractedRow::new(&m, &filter.paths))?;
+                }
+                written += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// What `--extract` paths picked out of one message
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ExtractedRow {
+    id: usize,
+    timestamp: f64,
+    /// Values found at each path, keyed by the path as written
+    values: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ExtractedRow {
+    fn new(m: &ParsedMessage, paths: &[FieldPath]) -> Self {
+        Self {
+            id: m.id,
+            timestamp: m.timestamp,
+            values: paths
+                .iter()
+                .map(|p| {
+                    let values = p.select(&m.data).into_iter().cloned().collect();
+                    (p.to_string(), serde_json::Value::Array(values))
+                })
+                .collect(),
+        }
+    }
+}
+
+fn extracted(
+    out: &mut impl Write,
+    messages: &[&ParsedMessage],
+    paths: &[FieldPath],
+    format: OutputFormat,
+    start: f64,
+) -> Result<()> {
+    let rows: Vec<ExtractedRow> = messages
+        .iter()
+        .map(|m| ExtractedRow::new(m, paths))
+        .collect();
+
+    write_records(out, &rows, format, |rows| {
+        let headers: Vec<&str> = ["Id", "Time"]
+            .into_iter()
+            .chain(paths.iter().map(FieldPath::as_str))
+            .collect();
+        let mut table = Table::new(&headers);
+        for row in rows {
+            let mut cells = vec![row.id.to_string(), relative_time(row.timestamp, start)];
+            cells.extend(paths.iter().map(|p| match &row.values[p.as_str()] {
+                serde_json::Value::Array(values) => format_values(values),
+                _ => String::new(),
+            }));
+            table.row(cells);
+        }
+        table
+    })
+}
+
+fn packets(out: &mut impl Write, capture: &Capture, args: &PacketsArgs) -> Result<()> {
+    let mut packets: Vec<&ParsedPacket> = capture
+        .packets
+        .iter()
+        .filter(|p| args.direction.is_none_or(|d| d.matches(&p.direction)))
+        .collect();
+
+    match args.sort {
+        SortArg::Id => packets.sort_by_key(|p| p.id),
+        SortArg::Type => packets.sort_by_key(|p| p.header.flags.bits()),
+        SortArg::Direction => packets.sort_by(|a, b| a.direction.cmp(&b.direction)),
+    }
+    if args.reverse {
+        packets.reverse();
+    }
+    if let Some(limit) = args.limit {
+        packets.truncate(limit);
+    }
+
+    let start = capture.start_time();
+    write_records(out, &packets, args.output, |packets| {
+        let mut table = Table::new(&[
+            "Id",
+            "Dir",
+            "Sequence",
+            "Flags",
+            "Size",
+            "Fragments",
+            "Time",
+        ]);
+        for p in packets {
+            table.row(vec![
+                p.id.to_string(),
+                p.direction.clone(),
+                p.header.sequence.to_string(),
+                format_packet_flags(p.header.flags),
+                p.header.size.to_string(),
+                p.fragments.len().to_string(),
+                relative_time(p.timestamp, start),
+            ]);
+        }
+        table
+    })
+}
+
+/// Write JSONL packets in capture order, each once no later packet can complete its
+/// fragments
+fn stream_packets<R: Read>(
+    out: &mut impl Write,
+    mut events: PacketStream<R>,
+    args: &PacketsArgs,
+) -> Result<()> {
+    let limit = args.limit.unwrap_or(usize::MAX);
+    // Packets from the first one with fragments of a message still pending
+    let mut held: VecDeque<ParsedPacket> = VecDeque::new();
+    let mut written = 0;
+    let mut finished = false;
+    while !finished && written < limit {
+        let settled = match events.next() {
+            Some(ParseEvent::Packet(packet)) => {
+                held.push_back(packet);
+                events.oldest_pending_packet().unwrap_or(usize::MAX)
+            }
+            Some(ParseEvent::FragmentsCompleted(completion)) => {
+                let first = held.front().map_or(0, |p| p.id);
+                completion.apply_from(first, held.make_contiguous());
+                continue;
+            }
+            Some(_) => continue,
+            None => {
+                finished = true;
+                usize::MAX
+            }
+        };
+
+        let ready = held.partition_point(|p| p.id < settled);
+        for packet in held.drain(..ready) {
+            if args.direction.is_none_or(|d| d.matches(&packet.direction)) {
+                write_line(out, &packet)?;
+                written += 1;
+                if written == limit {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn weenies(out: &mut impl Write, capture: &Capture, args: &WeeniesArgs) -> Result<()> {
+    let name = args.name.as_ref().map(|n| n.to_lowercase());
+    let mut weenies: Vec<&Weenie> = capture
+        .weenie_db
+        .sorted_weenies()
+        .into_iter()
+        .filter(|w| {
+            name.as_ref().is_none_or(|n| {
+                w.name
+                    .as_ref()
+                    .is_some_and(|name| name.to_lowercase().contains(n))
+            })
+        })
+        .collect();
+    if let Some(limit) = args.limit {
+        weenies.truncate(limit);
+    }
+
+    let start = capture.start_time();
+    write_records(out, &weenies, args.output, |weenies| {
+        let mut table = Table::new(&["ObjectId", "Name", "Messages", "FirstSeen", "LastUpdated"]);
+        for w in weenies {
+            table.row(vec![
+                format!("0x{:08X}", w.object_id),
+                w.name.clone().unwrap_or_default(),
+                w.message_count.to_string(),
+                relative_time(w.first_seen, start),
+                relative_time(w.last_updated, start),
+            ]);
+        }
+        table
+    })
+}
+
+fn diff(out: &mut impl Write, capture: &Capture, other: &Capture, args: &DiffArgs) -> Result<()> {
+    let options = DiffOptions {
+        ignore_fields: args.ignore.clone(),
+    };
+    let mut report = diff_captures(&capture.messages, &other.messages, &options);
+    if let Some(limit) = args.limit {
+        report.entries.truncate(limit);
+    }
+
+    if args.output == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut *out, &report)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    write_records(out, &report.entries, args.output, |entries| {
+        let mut table = Table::new(&["Kind", "Type", "Dir", "Left", "Right", "Time", "Fields"]);
+        let id = |id: Option<usize>| id.map(|id| id.to_string()).unwrap_or_default();
+        for e in entries {
+            let fields: Vec<&str> = e.fields.iter().map(|f| f.path.as_str()).collect();
+            table.row(vec![
+                e.kind.label().to_string(),
+                e.message_type.clone(),
+                e.direction.clone(),
+                id(e.left_id),
+                id(e.right_id),
+                format!("{:.3}", e.time),
+                fields.join(", "),
+            ]);
+        }
+        table
+    })
+}
+
+/// Counts describing a whole capture
+#[derive(Serialize)]
+struct Summary {
+    #[serde(rename = "Packets")]
+    packets: DirectionCounts,
+    #[serde(rename = "Messages")]
+    messages: DirectionCounts,
+    #[serde(rename = "Weenies")]
+    weenies: usize,
+    /// Seconds between the first and last packet
+    #[serde(rename = "Duration")]
+    duration: f64,
+    #[serde(rename = "MessageTypes")]
+    message_types: BTreeMap<String, usize>,
+    #[serde(rename = "Diagnostics")]
+    diagnostics: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Default, Serialize)]
+struct DirectionCounts {
+    #[serde(rename = "Total")]
+    total: usize,
+    #[serde(rename = "Send")]
+    send: usize,
+    #[serde(rename = "Recv")]
+    recv: usize,
+}
+
+impl DirectionCounts {
+    fn add(&mut self, direction: &str) {
+        self.total += 1;
+        if direction == "Send" {
+            self.send += 1;
+        } else {
+            self.recv += 1;
+        }
+    }
+}
+
+fn summary(out: &mut impl Write, capture: &Capture, args: &SummaryArgs) -> Result<()> {
+    let mut packets = DirectionCounts::default();
+    for p in &capture.packets {
+        packets.add(&p.direction);
+    }
+    let mut messages = DirectionCounts::default();
+    let mut message_types: HashMap<&str, usize> = HashMap::new();
+    for m in &capture.messages {
+        messages.add(&m.direction);
+        *message_types.entry(&m.message_type).or_default() += 1;
+    }
+    let mut diagnostics = BTreeMap::new();
+    for d in capture.parser.diagnostics() {
+        *diagnostics.entry(d.kind.label()).or_default() += 1;
+    }
+    let duration = capture
+        .packets
+        .last()
+        .map_or(0.0, |p| p.timestamp - capture.start_time());
+
+    let summary = Summary {
+        packets,
+        messages,
+        weenies: capture.weenie_db.count(),
+        duration,
+        message_types: message_types
+            .iter()
+            .map(|(t, count)| (t.to_string(), *count))
+            .collect(),
+        diagnostics,
+    };
+
+    match args.output {
+        OutputFormat::Jsonl => {
+            serde_json::to_writer(&mut *out, &summary)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &summary)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Table => write_summary_text(out, &summary)?,
+    }
+    Ok(())
+}
+
+fn write_summary_text(out: &mut impl Write, summary: &Summary) -> Result<()> {
+    let counts = |c: &DirectionCounts| format!("{} ({} send, {} recv)", c.total, c.send, c.recv);
+    writeln!(out, "Packets:     {}", counts(&summary.packets))?;
+    writeln!(out, "Messages:    {}", counts(&summary.messages))?;
+    writeln!(out, "Weenies:     {}", summary.weenies)?;
+    writeln!(out, "Duration:    {:.3}s", summary.duration)?;
+    writeln!(
+        out,
+        "Diagnostics: {}",
+        summary.diagnostics.values().sum::<usize>()
+    )?;
+
+    // Most frequent message types first
+    let mut types: Vec<(&String, &usize)> = summary.message_types.iter().collect();
+    types.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    writeln!(out)?;
+    let mut table = Table::new(&["Message Type", "Count"]);
+    for (message_type, count) in types {
+        table.row(vec![message_type.clone(), count.to_string()]);
+    }
+    table.write(out)?;
+
+    if !summary.diagnostics.is_empty() {
+        writeln!(out)?;
+        let mut table = Table::new(&["Diagnostic", "Count"]);
+        for (kind, count) in &summary.diagnostics {
+            table.row(vec![kind.to_string(), count.to_string()]);
+        }
+        table.write(out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_path() -> PathBuf {
+        PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../static/example.pcap"
+        ))
+    }
+
+    fn example() -> Capture {
+        Capture::load(&example_path(), None).unwrap()
+    }
+
+    fn run(command: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> String {
+        let mut out = Vec::new();
+        command(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_messages_writes_one_line_per_message() {
+        let capture = example();
+        assert!(!capture.messages.is_empty());
+
+        let output = run(|out| messages(out, &capture, &MessagesArgs::default()));
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), capture.messages.len());
+        assert_eq!(lines[0]["Id"], 0);
+
+        let args = MessagesArgs {
+            output: OutputFormat::Table,
+            limit: Some(3),
+            ..Default::default()
+        };
+        let output = run(|out| messages(out, &capture, &args));
+        assert!(output.starts_with("Id"));
+        // Header, rule and the three messages
+        assert_eq!(output.lines().count(), 2 + 3);
+    }
+
+    #[test]
+    fn test_streamed_output_matches_collected_output() {
+        let capture = example();
+        let events = || stream(&example_path(), None).unwrap();
+
+        let args = MessagesArgs::default();
+        assert!(args.streams());
+        let streamed = run(|out| stream_messages(out, events(), &args));
+        assert_eq!(streamed, run(|out| messages(out, &capture, &args)));
+
+        let args = PacketsArgs {
+            direction: None,
+            sort: SortArg::Id,
+            reverse: false,
+            output: OutputFormat::Jsonl,
+            limit: None,
+        };
+        let streamed = run(|out| stream_packets(out, events(), &args));
+        assert_eq!(streamed.lines().count(), capture.packets.len());
+        assert_eq!(streamed, run(|out| packets(out, &capture, &args)));
+
+        let args = PacketsArgs {
+            direction: Some(DirectionArg::Recv),
+            limit: Some(10),
+            ..args
+        };
+        let streamed = run(|out| stream_packets(out, events(), &args));
+        assert_eq!(streamed.lines().count(), 10);
+        assert_eq!(streamed, run(|out| packets(out, &capture, &args)));
+    }
+
+    #[test]
+    fn test_summary_counts_the_capture() {
+        let capture = example();
+        let args = SummaryArgs {
+            output: OutputFormat::Json,
+        };
+        let output = run(|out| summary(out, &capture, &args));
+        let counts: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(counts["Packets"]["Total"], capture.packets.len());
+        assert_eq!(counts["Messages"]["Total"], capture.messages.len());
+        assert_eq!(counts["Weenies"], capture.weenie_db.count());
+
+        let args = SummaryArgs {
+            output: OutputFormat::Table,
+        };
+        let output = run(|out| summary(out, &capture, &args));
+        assert!(output.starts_with(&format!("Packets:     {}", capture.packets.len())));
+    }
+
+    #[test]
+    fn test_diff_of_a_capture_with_itself_is_empty() {
+        let capture = example();
+        let other = example();
+        let args = DiffArgs {
+            against: PathBuf::new(),
+            ignore: Vec::new(),
+            output: OutputFormat::Json,
+            limit: None,
+        };
+        let output = run(|out| diff(out, &capture, &other, &args));
+        let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(report["Same"], capture.messages.len());
+        assert_eq!(report["Entries"], serde_json::json!([]));
+    }
+}
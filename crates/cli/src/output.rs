@@ -0,0 +1,116 @@
+//! Writing parsed records as JSONL, JSON or an aligned text table
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+
+/// Widest a table cell gets before it's cut short
+const MAX_CELL_WIDTH: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// A single pretty-printed JSON array
+    Json,
+    /// Aligned columns for reading in a terminal
+    Table,
+}
+
+/// A text table built row by row
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// Write the table with each column padded to its widest cell
+    pub fn write(&self, out: &mut impl Write) -> Result<()> {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| truncate(cell)).collect())
+            .collect();
+
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &cells {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        write_row(out, &self.headers, &widths)?;
+        let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+        write_row(out, &rule, &widths)?;
+        for row in &cells {
+            write_row(out, row, &widths)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_row(out: &mut impl Write, cells: &[String], widths: &[usize]) -> Result<()> {
+    let line: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{cell:<width$}"))
+        .collect();
+    writeln!(out, "{}", line.join("  ").trim_end())?;
+    Ok(())
+}
+
+/// Cut a cell down to `MAX_CELL_WIDTH` characters, marking where it was cut
+fn truncate(cell: &str) -> String {
+    if cell.chars().count() <= MAX_CELL_WIDTH {
+        return cell.to_string();
+    }
+    let mut cut: String = cell.chars().take(MAX_CELL_WIDTH - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Write `items` in `format`, using `table` to build the table form
+pub fn write_records<T: Serialize>(
+    out: &mut impl Write,
+    items: &[T],
+    format: OutputFormat,
+    table: impl FnOnce(&[T]) -> Table,
+) -> Result<()> {
+    match format {
+        OutputFormat::Jsonl => {
+            for item in items {
+                write_line(out, item)?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, items)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Table => table(items).write(out)?,
+    }
+    Ok(())
+}
+
+/// Write one JSONL record
+pub fn write_line(out: &mut impl Write, item: &impl Serialize) -> Result<()> {
+    serde_json::to_writer(&mut *out, item)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Time relative to the start of the capture, as shown in the viewer
+pub fn relative_time(timestamp: f64, start: f64) -> String {
+    format!("{:.3}", timestamp - start)
+}
@@ -0,0 +1,444 @@
+//! Interactive terminal UI for browsing a capture
+//!
+//! A list of messages or packets on the left and the selected item as an expandable tree
+//! on the right, for inspecting captures over SSH where the egui app can't run.
+
+use crate::Capture;
+use crate::output::relative_time;
+use anyhow::Result;
+use common::packet_flags::format_packet_flags;
+use common::query::Query;
+use common::tree::{TreeNode, collect_all_expandable_paths};
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table, TableState, Tabs};
+use std::collections::HashSet;
+
+/// Rows moved by PageUp/PageDown
+const PAGE_SIZE: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Messages,
+    Packets,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    List,
+    Detail,
+}
+
+/// Tree of the selected item and which of its nodes are expanded
+struct Detail {
+    view: View,
+    index: usize,
+    tree: TreeNode,
+    expanded: HashSet<String>,
+    state: ListState,
+}
+
+struct TuiApp<'a> {
+    capture: &'a Capture,
+    view: View,
+    focus: Focus,
+    /// Indices into the messages or packets that match the search
+    filtered: Vec<usize>,
+    list_state: TableState,
+    detail: Option<Detail>,
+    search: String,
+    /// Search text being typed, while the search prompt is open
+    search_input: Option<String>,
+    quit: bool,
+}
+
+/// Run the terminal UI until the user quits
+pub fn run(capture: &Capture) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = TuiApp::new(capture).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl<'a> TuiApp<'a> {
+    fn new(capture: &'a Capture) -> Self {
+        let mut app = Self {
+            capture,
+            view: View::Messages,
+            focus: Focus::List,
+            filtered: Vec::new(),
+            list_state: TableState::default(),
+            detail: None,
+            search: String::new(),
+            search_input: None,
+            quit: false,
+        };
+        app.apply_filter();
+        app
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            self.update_detail();
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key);
+            }
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        match self.view {
+            View::Messages => self.capture.messages.len(),
+            View::Packets => self.capture.packets.len(),
+        }
+    }
+
+    /// Rebuild the filtered list for the current view and search text
+    fn apply_filter(&mut self) {
+        let search = self.search.to_lowercase();
+        let start = self.capture.start_time();
+        self.filtered = match self.view {
+            View::Messages => {
+                let (query, _) = Query::parse_or_text(&self.search);
+                self.capture
+                    .messages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| query.matches(m, start))
+                    .map(|(i, _)| i)
+                    .collect()
+            }
+            View::Packets => self
+                .capture
+                .packets
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| {
+                    search.is_empty()
+                        || format_packet_flags(p.header.flags)
+                            .to_lowercase()
+                            .contains(&search)
+                })
+                .map(|(i, _)| i)
+                .collect(),
+        };
+        self.list_state.select(if self.filtered.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        self.list_state
+            .selected()
+            .and_then(|row| self.filtered.get(row).copied())
+    }
+
+    /// Build the tree for the selected item if the selection changed
+    fn update_detail(&mut self) {
+        let Some(index) = self.selected_index() else {
+            self.detail = None;
+            return;
+        };
+        if self
+            .detail
+            .as_ref()
+            .is_some_and(|d| d.view == self.view && d.index == index)
+        {
+            return;
+        }
+
+        let (key, value) = match self.view {
+            View::Messages => (
+                "Message",
+                serde_json::to_value(&self.capture.messages[index]),
+            ),
+            View::Packets => ("Packet", serde_json::to_value(&self.capture.packets[index])),
+        };
+        let tree = TreeNode::from_json(key, &value.unwrap_or_default());
+        // Start with the top level open so there's something to read
+        let expanded = HashSet::from([tree.key.clone()]);
+        self.detail = Some(Detail {
+            view: self.view,
+            index,
+            tree,
+            expanded,
+            state: ListState::default().with_selected(Some(0)),
+        });
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if let Some(input) = &mut self.search_input {
+            match key.code {
+                KeyCode::Enter => {
+                    self.search = self.search_input.take().unwrap_or_default();
+                    self.apply_filter();
+                }
+                KeyCode::Esc => self.search_input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Char('1') => self.switch_view(View::Messages),
+            KeyCode::Char('2') => self.switch_view(View::Packets),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::List => Focus::Detail,
+                    Focus::Detail => Focus::List,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::List,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Focus::Detail,
+            KeyCode::Char('/') => self.search_input = Some(self.search.clone()),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(PAGE_SIZE as isize),
+            KeyCode::PageUp => self.move_selection(-(PAGE_SIZE as isize)),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            KeyCode::Enter | KeyCode::Char(' ') => self.toggle_selected_node(),
+            KeyCode::Char('e') => {
+                if let Some(detail) = &mut self.detail {
+                    collect_all_expandable_paths(&detail.tree, String::new(), &mut detail.expanded);
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(detail) = &mut self.detail {
+                    detail.expanded = HashSet::from([detail.tree.key.clone()]);
+                    detail.state.select(Some(0));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn switch_view(&mut self, view: View) {
+        if self.view != view {
+            self.view = view;
+            self.focus = Focus::List;
+            self.apply_filter();
+        }
+    }
+
+    /// Move the selection in the focused pane by `delta` rows, clamped to its length
+    fn move_selection(&mut self, delta: isize) {
+        let (len, selected) = match self.focus {
+            Focus::List => (self.filtered.len(), self.list_state.selected()),
+            Focus::Detail => match &self.detail {
+                Some(d) => (display_lines(d).len(), d.state.selected()),
+                None => return,
+            },
+        };
+        if len == 0 {
+            return;
+        }
+        let current = selected.unwrap_or(0) as isize;
+        let next = current.saturating_add(delta).clamp(0, len as isize - 1) as usize;
+        match self.focus {
+            Focus::List => self.list_state.select(Some(next)),
+            Focus::Detail => {
+                if let Some(d) = &mut self.detail {
+                    d.state.select(Some(next));
+                }
+            }
+        }
+    }
+
+    /// Expand or collapse the node under the cursor in the detail pane
+    fn toggle_selected_node(&mut self) {
+        if self.focus == Focus::List {
+            self.focus = Focus::Detail;
+            return;
+        }
+        let Some(detail) = &mut self.detail else {
+            return;
+        };
+        let lines = display_lines(detail);
+        if let Some((_, path)) = detail.state.selected().and_then(|i| lines.get(i))
+            && !detail.expanded.remove(path)
+        {
+            detail.expanded.insert(path.clone());
+        }
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        let [tabs_area, main_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main_area);
+
+        let tabs = Tabs::new(vec![
+            format!("1 Messages ({})", self.capture.messages.len()),
+            format!("2 Packets ({})", self.capture.packets.len()),
+        ])
+        .select(match self.view {
+            View::Messages => 0,
+            View::Packets => 1,
+        })
+        .highlight_style(Style::new().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+        frame.render_widget(tabs, tabs_area);
+
+        self.draw_list(frame, list_area);
+        self.draw_detail(frame, detail_area);
+        self.draw_status(frame, status_area);
+    }
+
+    fn draw_list(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let start = self.capture.start_time();
+        let (header, widths, rows): (Row, Vec<Constraint>, Vec<Row>) = match self.view {
+            View::Messages => (
+                Row::new(["Id", "Type", "Dir", "OpCode", "Time"]),
+                vec![
+                    Constraint::Length(6),
+                    Constraint::Min(20),
+                    Constraint::Length(4),
+                    Constraint::Length(6),
+                    Constraint::Length(9),
+                ],
+                self.filtered
+                    .iter()
+                    .map(|&i| {
+                        let m = &self.capture.messages[i];
+                        Row::new([
+                            m.id.to_string(),
+                            m.message_type.clone(),
+                            m.direction.clone(),
+                            m.opcode.clone(),
+                            relative_time(m.timestamp, start),
+                        ])
+                        .style(direction_style(&m.direction))
+                    })
+                    .collect(),
+            ),
+            View::Packets => (
+                Row::new(["Id", "Dir", "Seq", "Flags", "Size", "Time"]),
+                vec![
+                    Constraint::Length(6),
+                    Constraint::Length(4),
+                    Constraint::Length(8),
+                    Constraint::Min(20),
+                    Constraint::Length(5),
+                    Constraint::Length(9),
+                ],
+                self.filtered
+                    .iter()
+                    .map(|&i| {
+                        let p = &self.capture.packets[i];
+                        Row::new([
+                            p.id.to_string(),
+                            p.direction.clone(),
+                            p.header.sequence.to_string(),
+                            format_packet_flags(p.header.flags),
+                            p.header.size.to_string(),
+                            relative_time(p.timestamp, start),
+                        ])
+                        .style(direction_style(&p.direction))
+                    })
+                    .collect(),
+            ),
+        };
+
+        let title = format!(" {}/{} ", self.filtered.len(), self.len());
+        let table = Table::new(rows, widths)
+            .header(header.style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(pane_block(title, self.focus == Focus::List))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.list_state);
+    }
+
+    fn draw_detail(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let focused = self.focus == Focus::Detail;
+        let Some(detail) = &mut self.detail else {
+            frame.render_widget(
+                Paragraph::new("Nothing selected").block(pane_block(" Detail ".into(), focused)),
+                area,
+            );
+            return;
+        };
+
+        let items: Vec<ListItem> = display_lines(detail)
+            .into_iter()
+            .map(|(text, _)| ListItem::new(text))
+            .collect();
+        let list = List::new(items)
+            .block(pane_block(" Detail ".into(), focused))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut detail.state);
+    }
+
+    fn draw_status(&self, frame: &mut ratatui::Frame, area: Rect) {
+        let line = if let Some(input) = &self.search_input {
+            Line::from(vec![
+                Span::styled("/", Style::new().fg(Color::Yellow)),
+                Span::raw(input.as_str()),
+                Span::styled("█", Style::new().fg(Color::Gray)),
+            ])
+        } else {
+            let mut spans = vec![Span::styled(
+                "q quit  1/2 view  tab focus  j/k move  enter expand  e/c expand/collapse all  / search",
+                Style::new().fg(Color::DarkGray),
+            )];
+            if !self.search.is_empty() {
+                spans.push(Span::styled(
+                    format!("  [search: {}]", self.search),
+                    Style::new().fg(Color::Yellow),
+                ));
+                if self.view == View::Messages
+                    && let Err(e) = Query::parse(&self.search)
+                {
+                    spans.push(Span::styled(
+                        format!("  {e}; searching as text"),
+                        Style::new().fg(Color::Red),
+                    ));
+                }
+            }
+            Line::from(spans)
+        };
+        frame.render_widget(Paragraph::new(line), area);
+    }
+}
+
+fn display_lines(detail: &Detail) -> Vec<(String, String)> {
+    detail
+        .tree
+        .get_display_lines(0, &detail.expanded, String::new())
+}
+
+fn pane_block(title: String, focused: bool) -> Block<'static> {
+    let style = if focused {
+        Style::new().fg(Color::Cyan)
+    } else {
+        Style::new().fg(Color::DarkGray)
+    };
+    Block::bordered().title(title).border_style(style)
+}
+
+/// Same colors as the GUI: blue for sent, green for received
+fn direction_style(direction: &str) -> Style {
+    if direction == "Send" {
+        Style::new().fg(Color::LightBlue)
+    } else {
+        Style::new().fg(Color::LightGreen)
+    }
+}
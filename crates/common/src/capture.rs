@@ -0,0 +1,341 @@
+//! Incremental reading of packet records from capture files, and writing them back out
+//!
+//! acprotocol's `PcapIterator` works on a byte slice, so the whole capture has to be in
+//! memory first. `PcapReader` pulls one record at a time from any `Read`, and
+//! `CaptureReader` does the same for either classic pcap or pcapng files. `PcapWriter`
+//! writes records to a new classic pcap file, and `trim` picks records out of a capture's
+//! bytes to write, e.g. to cut a capture down to the packets around a bug.
+
+use crate::pcapng::{self, PcapngReader};
+use crate::transport;
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::io::{ErrorKind, Read, Write};
+
+/// Records larger than this are treated as corruption rather than allocated
+pub(crate) const MAX_RECORD_SIZE: usize = 256 * 1024;
+
+const MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+
+/// Link type of Ethernet frames
+pub const LINKTYPE_ETHERNET: u32 = 1;
+/// BSD loopback: a 4-byte address family in the capturing host's byte order
+pub const LINKTYPE_NULL: u32 = 0;
+/// Raw IPv4 or IPv6 packets with no link-layer header
+pub const LINKTYPE_RAW: u32 = 101;
+/// Linux "cooked" capture, as written by `tcpdump -i any`
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+/// Raw IPv4 packets with no link-layer header
+pub const LINKTYPE_IPV4: u32 = 228;
+/// Linux "cooked" capture, version 2
+pub const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+/// A captured frame and when it was captured
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptureRecord {
+    pub ts_sec: u32,
+    pub ts_usec: u32,
+    /// Kind of link-layer header the frame starts with, e.g. `LINKTYPE_ETHERNET`
+    pub link_type: u32,
+    /// Length of the frame on the wire, more than `data.len()` if the capture cut it short
+    pub orig_len: u32,
+    /// Where the frame starts in the file it was read from
+    pub offset: u64,
+    /// The captured frame, starting with its link-layer header
+    pub data: Vec<u8>,
+}
+
+impl CaptureRecord {
+    /// Seconds since epoch with microsecond precision
+    pub fn timestamp(&self) -> f64 {
+        self.ts_sec as f64 + (self.ts_usec as f64 / 1_000_000.0)
+    }
+}
+
+/// Reads records from a classic libpcap file
+pub struct PcapReader<R> {
+    reader: R,
+    /// File was written on a host with the other byte order
+    swapped: bool,
+    /// Timestamps are in nanoseconds rather than microseconds
+    nanos: bool,
+    link_type: u32,
+    bytes_read: u64,
+}
+
+/// Reads records from a capture file in either format, told apart by its first bytes
+pub enum CaptureReader<R> {
+    Pcap(PcapReader<R>),
+    Pcapng(PcapngReader<R>),
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read the file header and prepare to read records
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .context("Capture is too short for a pcap file header")?;
+        if magic == pcapng::SECTION_HEADER_MAGIC {
+            PcapngReader::with_magic(magic, reader).map(CaptureReader::Pcapng)
+        } else {
+            PcapReader::with_magic(magic, reader).map(CaptureReader::Pcap)
+        }
+    }
+
+    /// Number of bytes consumed from the underlying reader so far
+    pub fn bytes_read(&self) -> u64 {
+        match self {
+            CaptureReader::Pcap(reader) => reader.bytes_read(),
+            CaptureReader::Pcapng(reader) => reader.bytes_read(),
+        }
+    }
+
+    /// Read the next record, or `None` at the end of the file
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>> {
+        match self {
+            CaptureReader::Pcap(reader) => reader.next_record(),
+            CaptureReader::Pcapng(reader) => reader.next_record(),
+        }
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the file header and prepare to read records
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .context("Capture is too short for a pcap file header")?;
+        Self::with_magic(magic, reader)
+    }
+
+    /// Read the rest of the file header, after its first four bytes
+    pub(crate) fn with_magic(magic: [u8; 4], mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 24];
+        header[..4].copy_from_slice(&magic);
+        reader
+            .read_exact(&mut header[4..])
+            .context("Capture is too short for a pcap file header")?;
+
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let (swapped, nanos) = match magic {
+            MAGIC_MICROS => (false, false),
+            MAGIC_NANOS => (false, true),
+            m if m.swap_bytes() == MAGIC_MICROS => (true, false),
+            m if m.swap_bytes() == MAGIC_NANOS => (true, true),
+            m => bail!("Not a pcap file (magic 0x{m:08X})"),
+        };
+        let link_type = [header[20], header[21], header[22], header[23]];
+        let link_type = if swapped {
+            u32::from_be_bytes(link_type)
+        } else {
+            u32::from_le_bytes(link_type)
+        };
+
+        Ok(Self {
+            reader,
+            swapped,
+            nanos,
+            link_type,
+            bytes_read: header.len() as u64,
+        })
+    }
+
+    /// Link type of every frame in the file
+    pub fn link_type(&self) -> u32 {
+        self.link_type
+    }
+
+    /// Number of bytes consumed from the underlying reader so far
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Read the next record, or `None` at the end of the file
+    ///
+    /// A file that ends part way through a record is an error.
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>> {
+        let mut header = [0u8; 16];
+        match self.fill(&mut header)? {
+            0 => return Ok(None),
+            16 => {}
+            n => bail!("Capture ends {n} bytes into a record header"),
+        }
+
+        let field = |i: usize| {
+            let b = [header[i], header[i + 1], header[i + 2], header[i + 3]];
+            if self.swapped {
+                u32::from_be_bytes(b)
+            } else {
+                u32::from_le_bytes(b)
+            }
+        };
+        let ts_sec = field(0);
+        let ts_frac = field(4);
+        let incl_len = field(8) as usize;
+        let orig_len = field(12);
+
+        if incl_len > MAX_RECORD_SIZE {
+            bail!("Record of {incl_len} bytes is larger than any valid frame");
+        }
+
+        let offset = self.bytes_read;
+        let mut data = vec![0u8; incl_len];
+        let n = self.fill(&mut data)?;
+        if n < incl_len {
+            bail!("Capture ends {n} bytes into a {incl_len} byte record");
+        }
+
+        Ok(Some(CaptureRecord {
+            ts_sec,
+            ts_usec: if self.nanos { ts_frac / 1000 } else { ts_frac },
+            link_type: self.link_type,
+            orig_len,
+            offset,
+            data,
+        }))
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let filled = fill(&mut self.reader, buf)?;
+        self.bytes_read += filled as u64;
+        Ok(filled)
+    }
+}
+
+/// Read until `buf` is full or the reader is exhausted, returning the bytes read
+pub(crate) fn fill(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e).context("Failed to read capture"),
+        }
+    }
+    Ok(filled)
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Writes records to a classic libpcap file with microsecond timestamps
+///
+/// A pcap file has one link type, so every record written must have the same one.
+pub struct PcapWriter<W> {
+    writer: W,
+    link_type: u32,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Write the file header for frames of `link_type`
+    pub fn new(mut writer: W, link_type: u32) -> Result<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&MAGIC_MICROS.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes()); // version major
+        header.extend_from_slice(&4u16.to_le_bytes()); // version minor
+        header.extend_from_slice(&[0u8; 8]); // thiszone, sigfigs
+        header.extend_from_slice(&(MAX_RECORD_SIZE as u32).to_le_bytes()); // snaplen
+        header.extend_from_slice(&link_type.to_le_bytes());
+        writer
+            .write_all(&header)
+            .context("Failed to write pcap header")?;
+        Ok(Self { writer, link_type })
+    }
+
+    /// Append `record`, keeping its timestamp and frame as captured
+    pub fn write_record(&mut self, record: &CaptureRecord) -> Result<()> {
+        if record.link_type != self.link_type {
+            bail!(
+                "Can't write a frame of link type {} to a pcap of link type {}",
+                record.link_type,
+                self.link_type
+            );
+        }
+        let len = record.data.len() as u32;
+        let mut header = [0u8; 16];
+        header[0..4].copy_from_slice(&record.ts_sec.to_le_bytes());
+        header[4..8].copy_from_slice(&record.ts_usec.to_le_bytes());
+        header[8..12].copy_from_slice(&len.to_le_bytes()); // captured length
+        header[12..16].copy_from_slice(&record.orig_len.max(len).to_le_bytes()); // original length
+        self.writer
+            .write_all(&header)
+            .and_then(|()| self.writer.write_all(&record.data))
+            .context("Failed to write pcap record")
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush().context("Failed to write pcap")?;
+        Ok(self.writer)
+    }
+}
+
+/// A pcap file of `records`, in the order given
+///
+/// The file takes the link type the records share, or Ethernet if there are none. A pcap
+/// file has only one link type, so records of mixed link types are all written as
+/// Ethernet frames, with their link-layer headers rewritten by `transport::to_ethernet`.
+pub fn write_pcap<'a>(records: impl IntoIterator<Item = &'a CaptureRecord>) -> Result<Vec<u8>> {
+    let records: Vec<&CaptureRecord> = records.into_iter().collect();
+    let link_type = match records.first() {
+        Some(first) if records.iter().all(|r| r.link_type == first.link_type) => first.link_type,
+        _ => LINKTYPE_ETHERNET,
+    };
+    let mut writer = PcapWriter::new(Vec::new(), link_type)?;
+    for record in records {
+        if record.link_type == link_type {
+            writer.write_record(record)?;
+            continue;
+        }
+        let Some(frame) = transport::to_ethernet(record.link_type, &record.data) else {
+            bail!(
+                "Can't write a frame of link type {} as an Ethernet frame",
+                record.link_type
+            );
+        };
+        // Whatever the capture cut off the end is still missing from the new frame
+        let cut_off = record.orig_len.saturating_sub(record.data.len() as u32);
+        let orig_len = frame.len() as u32 + cut_off;
+        writer.write_record(&CaptureRecord {
+            ts_sec: record.ts_sec,
+            ts_usec: record.ts_usec,
+            link_type: LINKTYPE_ETHERNET,
+            orig_len,
+            offset: record.offset,
+            data: frame.into_owned(),
+        })?;
+    }
+    writer.into_inner()
+}
+
+/// A pcap file of the records of the capture `data` at `record_indices`, counting every
+/// record in the file, in capture order
+pub fn trim(data: &[u8], record_indices: impl IntoIterator<Item = usize>) -> Result<Vec<u8>> {
+    let wanted: HashSet<usize> = record_indices.into_iter().collect();
+    let mut records = Vec::with_capacity(wanted.len());
+    for (index, record) in CaptureReader::new(data)?.enumerate() {
+        if wanted.contains(&index) {
+            records.push(record?);
+        } else {
+            record?;
+        }
+    }
+    write_pcap(&records)
+}
@@ -0,0 +1,105 @@
+//! Problems found while parsing a capture
+//!
+//! The parser keeps going when a packet or message can't be decoded; each problem is
+//! recorded as a `ParseDiagnostic` so wrong protocol definitions and truncated captures
+//! don't go unnoticed.
+
+use serde::Serialize;
+
+/// What kind of problem a diagnostic describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DiagnosticKind {
+    /// The capture ended part way through a record
+    TruncatedCapture,
+    /// Frames of this link type can't be decoded and were skipped
+    UnsupportedLinkType,
+    /// An AC datagram was too short for its transport header
+    InvalidHeader,
+    /// The optional headers announced by the packet flags couldn't be read
+    InvalidOptionalHeaders,
+    /// A blob fragment ran past the end of its packet
+    InvalidFragment,
+    /// A message was decoded as an opcode with no protocol definition
+    UnknownOpcode,
+    /// A message's fields couldn't be decoded
+    DecodeFailed,
+    /// Not every fragment of a message was captured
+    IncompleteMessage,
+}
+
+impl DiagnosticKind {
+    /// Human-readable name for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiagnosticKind::TruncatedCapture => "Truncated capture",
+            DiagnosticKind::UnsupportedLinkType => "Unsupported link type",
+            DiagnosticKind::InvalidHeader => "Invalid header",
+            DiagnosticKind::InvalidOptionalHeaders => "Invalid optional headers",
+            DiagnosticKind::InvalidFragment => "Invalid fragment",
+            DiagnosticKind::UnknownOpcode => "Unknown opcode",
+            DiagnosticKind::DecodeFailed => "Decode failed",
+            DiagnosticKind::IncompleteMessage => "Incomplete message",
+        }
+    }
+}
+
+/// A packet or message the parser couldn't fully decode
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseDiagnostic {
+    /// Index of the capture record the problem was found in, counting every record
+    /// whether or not it became a packet
+    #[serde(rename = "RecordIndex")]
+    pub record_index: Option<usize>,
+    /// Index of the packet the problem was found in, if the record became one
+    #[serde(rename = "PacketIndex")]
+    pub packet_index: Option<usize>,
+    #[serde(rename = "Timestamp")]
+    pub timestamp: f64,
+    /// Offset within the packet payload where the problem was found
+    #[serde(rename = "Offset")]
+    pub offset: Option<usize>,
+    #[serde(rename = "OpCode")]
+    pub opcode: Option<u32>,
+    #[serde(rename = "Kind")]
+    pub kind: DiagnosticKind,
+    #[serde(rename = "Message")]
+    pub message: String,
+}
+
+impl ParseDiagnostic {
+    pub fn new(kind: DiagnosticKind, message: impl Into<String>) -> Self {
+        Self {
+            record_index: None,
+            packet_index: None,
+            timestamp: 0.0,
+            offset: None,
+            opcode: None,
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Attach the capture record the problem was found in
+    pub fn at_record(mut self, record_index: usize, timestamp: f64) -> Self {
+        self.record_index = Some(record_index);
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Attach the packet the problem was found in
+    pub fn at_packet(mut self, packet_index: usize, timestamp: f64) -> Self {
+        self.packet_index = Some(packet_index);
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn with_opcode(mut self, opcode: u32) -> Self {
+        self.opcode = Some(opcode);
+        self
+    }
+}
@@ -0,0 +1,256 @@
+//! Comparing two captures message by message
+//!
+//! Messages are grouped by direction and type, and each group is aligned with the same
+//! group in the other capture using a Myers diff of the message data, so a message
+//! missing from the middle of one capture doesn't shift every later message onto the
+//! wrong partner. A message with no partner is missing from the right capture or extra in
+//! it; paired messages whose data differs are compared field by field.
+
+use crate::messages::ParsedMessage;
+use serde::Serialize;
+use serde_json::Value;
+use similar::{Algorithm, capture_diff_slices};
+use std::collections::HashMap;
+
+/// What to leave out of a comparison
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Field names skipped at any depth, e.g. `Sequence` or `ObjectId`, which differ
+    /// between captures of the same action
+    pub ignore_fields: Vec<String>,
+}
+
+/// How a message differs between the captures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DiffKind {
+    /// Only in the left capture
+    Missing,
+    /// Only in the right capture
+    Extra,
+    /// In both, with different data
+    Changed,
+}
+
+impl DiffKind {
+    /// Human-readable name for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiffKind::Missing => "Missing",
+            DiffKind::Extra => "Extra",
+            DiffKind::Changed => "Changed",
+        }
+    }
+}
+
+/// A field whose value differs, with `None` on the side it's absent from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FieldDiff {
+    /// Path of the field within the message data, e.g. `S2C.ItemCreateObject.Name`
+    pub path: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+/// A message that differs between the captures
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MessageDiff {
+    pub kind: DiffKind,
+    pub message_type: String,
+    pub direction: String,
+    /// How many messages of this type and direction came before it
+    pub occurrence: usize,
+    /// ID of the message in the left capture
+    pub left_id: Option<usize>,
+    /// ID of the message in the right capture
+    pub right_id: Option<usize>,
+    /// Seconds from the start of the capture the message is from, the left one if both
+    pub time: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldDiff>,
+}
+
+/// Every difference between two captures, in capture order
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CaptureDiff {
+    /// Messages paired with identical data
+    pub same: usize,
+    pub entries: Vec<MessageDiff>,
+}
+
+impl CaptureDiff {
+    /// Number of entries of `kind`
+    pub fn count(&self, kind: DiffKind) -> usize {
+        self.entries.iter().filter(|e| e.kind == kind).count()
+    }
+
+    /// Whether the captures had the same messages with the same data
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Compare the messages of two captures
+pub fn diff_captures(
+    left: &[ParsedMessage],
+    right: &[ParsedMessage],
+    options: &DiffOptions,
+) -> CaptureDiff {
+    let left_start = left.first().map_or(0.0, |m| m.timestamp);
+    let right_start = right.first().map_or(0.0, |m| m.timestamp);
+
+    // Messages of each (direction, type) from each capture, in capture order
+    type Group<'a> = (Vec<&'a ParsedMessage>, Vec<&'a ParsedMessage>);
+    let mut groups: HashMap<(&str, &str), Group> = HashMap::new();
+    for message in left {
+        groups
+            .entry((&message.direction, &message.message_type))
+            .or_default()
+            .0
+            .push(message);
+    }
+    for message in right {
+        groups
+            .entry((&message.direction, &message.message_type))
+            .or_default()
+            .1
+            .push(message);
+    }
+
+    let mut diff = CaptureDiff::default();
+    for (lefts, rights) in groups.values() {
+        let keys = |messages: &[&ParsedMessage]| -> Vec<String> {
+            messages
+                .iter()
+                .map(|m| comparable(&m.data, options).to_string())
+                .collect()
+        };
+        let ops = capture_diff_slices(Algorithm::Myers, &keys(lefts), &keys(rights));
+
+        // Within each run of changes, pair messages in order; what's left over on either
+        // side has no partner
+        for op in ops {
+            let (_, old, new) = op.as_tag_tuple();
+            for offset in 0..old.len().max(new.len()) {
+                let l = (old.start + offset < old.end).then(|| old.start + offset);
+                let r = (new.start + offset < new.end).then(|| new.start + offset);
+                let entry = |kind, message: &ParsedMessage, occurrence, start: f64| MessageDiff {
+                    kind,
+                    message_type: message.message_type.clone(),
+                    direction: message.direction.clone(),
+                    occurrence,
+                    left_id: l.map(|l| lefts[l].id),
+                    right_id: r.map(|r| rights[r].id),
+                    time: message.timestamp - start,
+                    fields: Vec::new(),
+                };
+                match (l, r) {
+                    (Some(l), Some(r)) => {
+                        let fields = diff_values(&lefts[l].data, &rights[r].data, options);
+                        if fields.is_empty() {
+                            diff.same += 1;
+                        } else {
+                            diff.entries.push(MessageDiff {
+                                fields,
+                                ..entry(DiffKind::Changed, lefts[l], l, left_start)
+                            });
+                        }
+                    }
+                    (Some(l), None) => {
+                        let missing = entry(DiffKind::Missing, lefts[l], l, left_start);
+                        diff.entries.push(missing);
+                    }
+                    (None, Some(r)) => {
+                        let extra = entry(DiffKind::Extra, rights[r], r, right_start);
+                        diff.entries.push(extra);
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+
+    diff.entries.sort_by(|a, b| {
+        a.time
+            .total_cmp(&b.time)
+            .then(a.left_id.cmp(&b.left_id))
+            .then(a.right_id.cmp(&b.right_id))
+    });
+    diff
+}
+
+/// The fields that differ between two values, in the order they appear
+pub fn diff_values(left: &Value, right: &Value, options: &DiffOptions) -> Vec<FieldDiff> {
+    let mut fields = Vec::new();
+    diff_at("", left, right, options, &mut fields);
+    fields
+}
+
+/// `value` without the ignored fields, for telling whether two messages are the same
+fn comparable(value: &Value, options: &DiffOptions) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| !options.ignore_fields.iter().any(|f| f == *key))
+                .map(|(key, value)| (key.clone(), comparable(value, options)))
+                .collect(),
+        ),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| comparable(item, options)).collect())
+        }
+        _ => value.clone(),
+    }
+}
+
+fn diff_at(
+    path: &str,
+    left: &Value,
+    right: &Value,
+    options: &DiffOptions,
+    fields: &mut Vec<FieldDiff>,
+) {
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            let keys = l.keys().chain(r.keys().filter(|k| !l.contains_key(*k)));
+            for key in keys {
+                if options.ignore_fields.iter().any(|f| f == key) {
+                    continue;
+                }
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                match (l.get(key), r.get(key)) {
+                    (Some(l), Some(r)) => diff_at(&path, l, r, options, fields),
+                    (l, r) => fields.push(FieldDiff {
+                        path,
+                        left: l.cloned(),
+                        right: r.cloned(),
+                    }),
+                }
+            }
+        }
+        (Value::Array(l), Value::Array(r)) => {
+            for idx in 0..l.len().max(r.len()) {
+                let path = format!("{path}[{idx}]");
+                match (l.get(idx), r.get(idx)) {
+                    (Some(l), Some(r)) => diff_at(&path, l, r, options, fields),
+                    (l, r) => fields.push(FieldDiff {
+                        path,
+                        left: l.cloned(),
+                        right: r.cloned(),
+                    }),
+                }
+            }
+        }
+        (l, r) if l != r => fields.push(FieldDiff {
+            path: path.to_string(),
+            left: Some(l.clone()),
+            right: Some(r.clone()),
+        }),
+        _ => {}
+    }
+}
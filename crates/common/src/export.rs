@@ -0,0 +1,172 @@
+//! Writing messages and weenies out as JSON, JSONL or CSV
+//!
+//! JSON and JSONL use the same serde names as everywhere else. CSV flattens each record
+//! into one row, with a column per leaf field named by its path, e.g.
+//! `Data.S2C.ItemCreateObject.Name` or `IntProperties.StackSize`.
+
+use crate::messages::ParsedMessage;
+use crate::weenie::Weenie;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// File format to export to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A single pretty-printed JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    /// One row per record, with flattened columns
+    Csv,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Json, ExportFormat::Jsonl, ExportFormat::Csv];
+
+    /// Human-readable name for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Jsonl => "JSONL",
+            ExportFormat::Csv => "CSV",
+        }
+    }
+
+    /// File extension, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Message fields that lead each CSV row, ahead of the flattened `Data` columns
+const MESSAGE_COLUMNS: [&str; 5] = ["Id", "Type", "Direction", "OpCode", "Timestamp"];
+
+/// Weenie fields that lead each CSV row, ahead of the flattened property columns
+const WEENIE_COLUMNS: [&str; 5] = [
+    "ObjectId",
+    "Name",
+    "FirstSeen",
+    "LastUpdated",
+    "MessageCount",
+];
+
+/// Weenie fields left out of CSV, which would make a column per message or change
+const WEENIE_SKIPPED: [&str; 3] = ["MessageIds", "History", "Lifecycle"];
+
+/// `messages` in `format`
+pub fn export_messages(messages: &[&ParsedMessage], format: ExportFormat) -> Result<Vec<u8>> {
+    export(messages, format, &MESSAGE_COLUMNS, &[])
+}
+
+/// `weenies` in `format`
+pub fn export_weenies(weenies: &[&Weenie], format: ExportFormat) -> Result<Vec<u8>> {
+    export(weenies, format, &WEENIE_COLUMNS, &WEENIE_SKIPPED)
+}
+
+fn export<T: Serialize>(
+    records: &[T],
+    format: ExportFormat,
+    leading: &[&str],
+    skipped: &[&str],
+) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, records)?;
+            out.push(b'\n');
+        }
+        ExportFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut out, record)?;
+                out.push(b'\n');
+            }
+        }
+        ExportFormat::Csv => {
+            let rows = records
+                .iter()
+                .map(|r| -> Result<Row> {
+                    let mut row = Vec::new();
+                    if let Value::Object(fields) = serde_json::to_value(r)? {
+                        for (key, value) in &fields {
+                            if !skipped.contains(&key.as_str()) {
+                                flatten(key, value, &mut row);
+                            }
+                        }
+                    }
+                    Ok(row)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            out = to_csv(&rows, leading).into_bytes();
+        }
+    }
+    Ok(out)
+}
+
+/// A record's leaf fields as (path, value), in the order they appear
+type Row = Vec<(String, String)>;
+
+/// Add each leaf under `value` to `row`, keyed by its path from `path`
+fn flatten(path: &str, value: &Value, row: &mut Row) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(&format!("{path}.{key}"), value, row);
+            }
+        }
+        Value::Array(items) => {
+            for (idx, value) in items.iter().enumerate() {
+                flatten(&format!("{path}[{idx}]"), value, row);
+            }
+        }
+        Value::Null => row.push((path.to_string(), String::new())),
+        Value::String(s) => row.push((path.to_string(), s.clone())),
+        other => row.push((path.to_string(), other.to_string())),
+    }
+}
+
+/// CSV of `rows`, with the `leading` columns first and the rest in the order first seen
+fn to_csv(rows: &[Row], leading: &[&str]) -> String {
+    // Keeping the order columns first appear in groups the columns of similar rows
+    let mut columns: Vec<&str> = leading.to_vec();
+    let mut seen: HashSet<&str> = leading.iter().copied().collect();
+    for row in rows {
+        for (path, _) in row {
+            if seen.insert(path.as_str()) {
+                columns.push(path.as_str());
+            }
+        }
+    }
+
+    let mut csv = csv_line(columns.iter().copied());
+    for row in rows {
+        let cells: HashMap<&str, &str> = row
+            .iter()
+            .map(|(path, value)| (path.as_str(), value.as_str()))
+            .collect();
+        csv.push_str(&csv_line(
+            columns.iter().map(|c| cells.get(c).copied().unwrap_or("")),
+        ));
+    }
+    csv
+}
+
+fn csv_line<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+    let mut line = cells.map(csv_escape).collect::<Vec<_>>().join(",");
+    line.push('\n');
+    line
+}
+
+/// Quote a cell if it holds a separator, quote or line break
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
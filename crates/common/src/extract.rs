@@ -0,0 +1,283 @@
+//! Pulling fields out of decoded messages with path expressions
+//!
+//! Paths follow JSONPath and jq closely enough that either habit works:
+//!
+//! - `Enchantment.Id.SpellId` or `$.Enchantment.Id.SpellId` or `.Enchantment.Id.SpellId`
+//! - `Items[0].Name`, `Items[*].Name` and `['Key with spaces']`
+//! - `*` for every field of an object
+//! - `..SpellId` for a field at any depth
+//!
+//! A named field applied to an array applies to each element, so `Items.Name` gives the
+//! name of every item.
+
+use crate::messages::ParsedMessage;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+/// A path that couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{message} (at character {position})")]
+pub struct PathError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl PathError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+/// One step of a path
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// A field by name, matched case-insensitively if there's no exact match
+    Key(String),
+    Index(usize),
+    /// Every field of an object or element of an array
+    Wildcard,
+    /// A field by name at any depth
+    Descendant(String),
+}
+
+/// A parsed path expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPath {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+impl FieldPath {
+    pub fn parse(text: &str) -> Result<Self, PathError> {
+        let chars: Vec<char> = text.trim().chars().collect();
+        let mut segments = Vec::new();
+        let mut i = 0;
+
+        if chars.first() == Some(&'$') {
+            i += 1;
+        }
+
+        while i < chars.len() {
+            match chars[i] {
+                '.' if chars.get(i + 1) == Some(&'.') => {
+                    i += 2;
+                    let (name, next) = read_name(&chars, i);
+                    if name.is_empty() {
+                        return Err(PathError::new("Expected a field name after `..`", i));
+                    }
+                    segments.push(Segment::Descendant(name));
+                    i = next;
+                }
+                '.' => {
+                    i += 1;
+                    // `.` on its own is the whole value, as in jq
+                    if i == chars.len() && segments.is_empty() {
+                        break;
+                    }
+                    i = read_key(&chars, i, &mut segments)?;
+                }
+                '[' => i = read_bracket(&chars, i, &mut segments)?,
+                _ if i == 0 || (i == 1 && chars[0] == '$') => {
+                    i = read_key(&chars, i, &mut segments)?
+                }
+                c => return Err(PathError::new(format!("Unexpected `{c}`"), i)),
+            }
+        }
+
+        Ok(Self {
+            text: text.trim().to_string(),
+            segments,
+        })
+    }
+
+    /// The path as it was written
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Whether the path selects the whole value
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Every value in `value` the path leads to
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut out = Vec::new();
+        select(value, &self.segments, &mut out);
+        out
+    }
+}
+
+impl std::fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Read a field name up to the next `.` or `[`
+fn read_name(chars: &[char], start: usize) -> (String, usize) {
+    let mut end = start;
+    while end < chars.len() && !matches!(chars[end], '.' | '[') {
+        end += 1;
+    }
+    (chars[start..end].iter().collect(), end)
+}
+
+fn read_key(chars: &[char], start: usize, segments: &mut Vec<Segment>) -> Result<usize, PathError> {
+    let (name, next) = read_name(chars, start);
+    if let Some(offset) = name.find(|c: char| c.is_whitespace() || matches!(c, ']' | '\'' | '"')) {
+        let c = name[offset..].chars().next().unwrap_or_default();
+        return Err(PathError::new(
+            format!("Unexpected `{c}`; quote names like ['this']"),
+            start + name[..offset].chars().count(),
+        ));
+    }
+    match name.as_str() {
+        "" => Err(PathError::new("Expected a field name", start)),
+        "*" => {
+            segments.push(Segment::Wildcard);
+            Ok(next)
+        }
+        _ => {
+            segments.push(Segment::Key(name));
+            Ok(next)
+        }
+    }
+}
+
+/// Read `[0]`, `[*]` or `['name']` starting at the `[`
+fn read_bracket(
+    chars: &[char],
+    start: usize,
+    segments: &mut Vec<Segment>,
+) -> Result<usize, PathError> {
+    let Some(close) = chars[start..].iter().position(|&c| c == ']') else {
+        return Err(PathError::new("Missing `]`", start));
+    };
+    let inner: String = chars[start + 1..start + close].iter().collect();
+    let inner = inner.trim();
+
+    let segment = if inner == "*" {
+        Segment::Wildcard
+    } else if let Ok(index) = inner.parse::<usize>() {
+        Segment::Index(index)
+    } else if inner.len() >= 2
+        && ((inner.starts_with('\'') && inner.ends_with('\''))
+            || (inner.starts_with('"') && inner.ends_with('"')))
+    {
+        Segment::Key(inner[1..inner.len() - 1].to_string())
+    } else {
+        return Err(PathError::new(
+            format!("Expected an index, `*` or a quoted name in `[{inner}]`"),
+            start,
+        ));
+    };
+    segments.push(segment);
+    Ok(start + close + 1)
+}
+
+fn select<'a>(value: &'a Value, segments: &[Segment], out: &mut Vec<&'a Value>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        out.push(value);
+        return;
+    };
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(obj)) => {
+            let child = obj.get(key).or_else(|| {
+                obj.iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                    .map(|(_, v)| v)
+            });
+            if let Some(child) = child {
+                select(child, rest, out);
+            }
+        }
+        // A name applied to an array applies to each element
+        (Segment::Key(_), Value::Array(arr)) => {
+            for child in arr {
+                select(child, segments, out);
+            }
+        }
+        (Segment::Index(index), Value::Array(arr)) => {
+            if let Some(child) = arr.get(*index) {
+                select(child, rest, out);
+            }
+        }
+        // Numeric keys, for maps keyed by id
+        (Segment::Index(index), Value::Object(obj)) => {
+            if let Some(child) = obj.get(&index.to_string()) {
+                select(child, rest, out);
+            }
+        }
+        (Segment::Wildcard, Value::Object(obj)) => {
+            for child in obj.values() {
+                select(child, rest, out);
+            }
+        }
+        (Segment::Wildcard, Value::Array(arr)) => {
+            for child in arr {
+                select(child, rest, out);
+            }
+        }
+        (Segment::Descendant(key), Value::Object(obj)) => {
+            for (k, child) in obj {
+                if k.eq_ignore_ascii_case(key) {
+                    select(child, rest, out);
+                }
+                select(child, segments, out);
+            }
+        }
+        (Segment::Descendant(_), Value::Array(arr)) => {
+            for child in arr {
+                select(child, segments, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The values a path extracted from one message
+#[derive(Debug, Clone, Serialize)]
+pub struct Extraction {
+    #[serde(rename = "Id")]
+    pub message_id: usize,
+    #[serde(rename = "Timestamp")]
+    pub timestamp: f64,
+    #[serde(rename = "Values")]
+    pub values: Vec<Value>,
+}
+
+/// Run `path` over every message, keeping those it found something in
+pub fn extract(messages: &[ParsedMessage], path: &FieldPath) -> Vec<Extraction> {
+    messages
+        .iter()
+        .filter_map(|m| {
+            let values = path.select(&m.data);
+            (!values.is_empty()).then(|| Extraction {
+                message_id: m.id,
+                timestamp: m.timestamp,
+                values: values.into_iter().cloned().collect(),
+            })
+        })
+        .collect()
+}
+
+/// Show extracted values compactly: strings unquoted, several separated by commas
+pub fn format_values<'a>(values: impl IntoIterator<Item = &'a Value>) -> String {
+    values
+        .into_iter()
+        .map(|v| match v {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,406 @@
+//! Rules describing which messages carry weenie data and where to find it
+//!
+//! Each rule names the message types it covers, a path to the object ID and the
+//! properties to read, so coverage for new message types can be added with a TOML or
+//! JSON file rather than code. The built-in rules are in `weenie_rules.toml`:
+//!
+//! ```toml
+//! [[rule]]
+//! message_type = "Item_ServerSaysContainId"
+//! root = "S2C.OrderedGameEvent.event.ItemServerSaysContainId"
+//! object_id = "ObjectId"
+//! property = [{ kind = "InstanceId", name = "Container", value = "ContainerId" }]
+//! ```
+//!
+//! Paths are written as in [`crate::extract`] and, when the rule has a `root`, are
+//! relative to it. A rule only applies when its root and object ID are present. A rule
+//! without `object_id` applies to the logged-in character.
+//!
+//! Each property reads its value from `value` and takes its name either from `name` or
+//! from the path in `key`. A property with `table` instead reads every field of the
+//! object there as a property of its kind.
+
+use crate::extract::FieldPath;
+use crate::messages::ParsedMessage;
+use crate::weenie::{LifecycleKind, PropertyKind, WeenieUpdate};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+
+/// Rules compiled into the app
+const BUILTIN_RULES: &str = include_str!("weenie_rules.toml");
+
+static BUILTIN: LazyLock<Arc<ExtractorRegistry>> = LazyLock::new(|| {
+    Arc::new(ExtractorRegistry::from_toml(BUILTIN_RULES).expect("built-in rules are valid"))
+});
+
+/// A rule as written in a rules file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtractorRule {
+    /// Message type, e.g. `Item_CreateObject`, or a list of them
+    pub message_type: OneOrMany,
+    /// Path the rule's other paths are relative to
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Path to the object ID; without one the rule applies to the logged-in character
+    #[serde(default)]
+    pub object_id: Option<String>,
+    /// Paths to the object's name, the first one present winning
+    #[serde(default)]
+    pub name: OneOrMany,
+    /// Whether the message creates or deletes the object
+    #[serde(default)]
+    pub lifecycle: Option<LifecycleKind>,
+    #[serde(default, rename = "property")]
+    pub properties: Vec<PropertyRule>,
+}
+
+/// Where one or more properties come from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PropertyRule {
+    pub kind: PropertyKind,
+    /// Name of the property
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Path to the name of the property, for messages that carry it
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Path to the value
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Path to an object whose every field is a property
+    #[serde(default)]
+    pub table: Option<String>,
+    #[serde(default)]
+    pub format: Option<ValueFormat>,
+}
+
+/// Values that are turned into text rather than stored as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ValueFormat {
+    /// A `Position` shown as its cell and coordinates
+    Position,
+}
+
+/// A single string or a list of them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    #[default]
+    None,
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::None => Vec::new(),
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<ExtractorRule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    root: Option<FieldPath>,
+    object_id: Option<FieldPath>,
+    name: Vec<FieldPath>,
+    lifecycle: Option<LifecycleKind>,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    kind: PropertyKind,
+    source: Source,
+    format: Option<ValueFormat>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Named(String, FieldPath),
+    Keyed(FieldPath, FieldPath),
+    Table(FieldPath),
+}
+
+/// Extractor rules by message type
+#[derive(Debug, Clone, Default)]
+pub struct ExtractorRegistry {
+    rules: HashMap<String, Vec<Rule>>,
+}
+
+impl ExtractorRegistry {
+    /// The rules compiled into the app
+    pub fn builtin() -> Arc<ExtractorRegistry> {
+        BUILTIN.clone()
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: RulesFile = toml::from_str(text).context("Invalid TOML rules")?;
+        Self::from_rules(file.rule)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let file: RulesFile = serde_json::from_str(text).context("Invalid JSON rules")?;
+        Self::from_rules(file.rule)
+    }
+
+    /// Read rules from a `.toml` or `.json` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let rules = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::from_json(&text),
+            _ => Self::from_toml(&text),
+        };
+        rules.with_context(|| format!("Failed to load rules from {}", path.display()))
+    }
+
+    /// The built-in rules, with those read from `path` replacing them for the message
+    /// types it covers
+    pub fn load_over_builtin(path: &Path) -> Result<Self> {
+        let mut rules = Self::builtin().as_ref().clone();
+        rules.merge(Self::load(path)?);
+        Ok(rules)
+    }
+
+    pub fn from_rules(rules: Vec<ExtractorRule>) -> Result<Self> {
+        let mut registry = Self::default();
+        for rule in rules {
+            let message_types = rule.message_type.clone().into_vec();
+            let label = message_types.join(", ");
+            if message_types.is_empty() {
+                bail!("A rule has no message_type");
+            }
+            let compiled = compile(rule).with_context(|| format!("In the rule for {label}"))?;
+            for message_type in message_types {
+                registry
+                    .rules
+                    .entry(message_type)
+                    .or_default()
+                    .push(compiled.clone());
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Add `other`'s rules, replacing ours for the message types it covers
+    pub fn merge(&mut self, other: ExtractorRegistry) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Number of message types covered
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether any rule covers `message_type`
+    pub fn covers(&self, message_type: &str) -> bool {
+        self.rules.contains_key(message_type)
+    }
+
+    /// Apply the rules for the message's type, one update per object
+    pub fn extract(&self, message: &ParsedMessage, player_id: Option<u32>) -> Vec<WeenieUpdate> {
+        let Some(rules) = self.rules.get(&message.message_type) else {
+            return Vec::new();
+        };
+
+        let mut updates: Vec<WeenieUpdate> = Vec::new();
+        for rule in rules {
+            let Some(update) = apply(rule, message, player_id) else {
+                continue;
+            };
+            // Several rules for one object make a single update
+            match updates.iter_mut().find(|u| u.object_id == update.object_id) {
+                Some(existing) => merge_update(existing, update),
+                None => updates.push(update),
+            }
+        }
+        updates
+    }
+}
+
+fn parse_path(path: &str) -> Result<FieldPath> {
+    FieldPath::parse(path).map_err(|e| anyhow!("Invalid path `{path}`: {e}"))
+}
+
+fn compile(rule: ExtractorRule) -> Result<Rule> {
+    let properties = rule
+        .properties
+        .into_iter()
+        .map(|p| {
+            let source = match (p.name, p.key, p.value, p.table) {
+                (Some(name), None, Some(value), None) => Source::Named(name, parse_path(&value)?),
+                (None, Some(key), Some(value), None) => {
+                    Source::Keyed(parse_path(&key)?, parse_path(&value)?)
+                }
+                (None, None, None, Some(table)) => Source::Table(parse_path(&table)?),
+                _ => bail!("A property needs `name` and `value`, `key` and `value`, or `table`"),
+            };
+            Ok(Property {
+                kind: p.kind,
+                source,
+                format: p.format,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Rule {
+        root: rule.root.as_deref().map(parse_path).transpose()?,
+        object_id: rule.object_id.as_deref().map(parse_path).transpose()?,
+        name: rule
+            .name
+            .into_vec()
+            .iter()
+            .map(|p| parse_path(p))
+            .collect::<Result<_>>()?,
+        lifecycle: rule.lifecycle,
+        properties,
+    })
+}
+
+fn first<'a>(path: &FieldPath, data: &'a Value) -> Option<&'a Value> {
+    path.select(data).into_iter().next()
+}
+
+fn apply(rule: &Rule, message: &ParsedMessage, player_id: Option<u32>) -> Option<WeenieUpdate> {
+    let data = match &rule.root {
+        Some(root) => first(root, &message.data)?,
+        None => &message.data,
+    };
+    let object_id = match &rule.object_id {
+        Some(path) => first(path, data)?.as_u64()? as u32,
+        None => player_id?,
+    };
+
+    let mut update = WeenieUpdate::new(object_id, message.timestamp, message.id);
+    update.lifecycle = rule.lifecycle;
+    update.name = rule
+        .name
+        .iter()
+        .find_map(|path| first(path, data)?.as_str())
+        .map(str::to_string);
+
+    for property in &rule.properties {
+        match &property.source {
+            Source::Named(name, value) => {
+                if let Some(value) = first(value, data) {
+                    insert_property(&mut update, property, name, value);
+                }
+            }
+            Source::Keyed(key, value) => {
+                if let (Some(key), Some(value)) = (
+                    first(key, data).and_then(|k| k.as_str()),
+                    first(value, data),
+                ) {
+                    insert_property(&mut update, property, key, value);
+                }
+            }
+            Source::Table(table) => {
+                if let Some(table) = first(table, data).and_then(|t| t.as_object()) {
+                    for (key, value) in table {
+                        insert_property(&mut update, property, key, value);
+                    }
+                }
+            }
+        }
+    }
+
+    Some(update)
+}
+
+/// Store `value` in the property map for the rule's kind
+///
+/// Enum fields that decode to their names rather than numbers are kept as strings.
+fn insert_property(update: &mut WeenieUpdate, property: &Property, key: &str, value: &Value) {
+    let key = key.to_string();
+    if property.format == Some(ValueFormat::Position) {
+        if let Some(position) = format_position(value) {
+            update.string_properties.insert(key, position);
+        }
+        return;
+    }
+
+    match (property.kind, value) {
+        (PropertyKind::Int, Value::Number(n)) if n.is_i64() => {
+            update
+                .int_properties
+                .insert(key, n.as_i64().unwrap_or_default() as i32);
+        }
+        (PropertyKind::Int64, Value::Number(n)) if n.is_i64() => {
+            update
+                .int64_properties
+                .insert(key, n.as_i64().unwrap_or_default());
+        }
+        (PropertyKind::Bool, Value::Bool(b)) => {
+            update.bool_properties.insert(key, *b);
+        }
+        (PropertyKind::Float, Value::Number(n)) => {
+            update
+                .float_properties
+                .insert(key, n.as_f64().unwrap_or_default());
+        }
+        (PropertyKind::DataId, Value::Number(n)) if n.is_u64() => {
+            update
+                .data_id_properties
+                .insert(key, n.as_u64().unwrap_or_default() as u32);
+        }
+        (PropertyKind::InstanceId, Value::Number(n)) if n.is_u64() => {
+            update
+                .instance_id_properties
+                .insert(key, n.as_u64().unwrap_or_default() as u32);
+        }
+        (PropertyKind::Name, Value::String(s)) => update.name = Some(s.clone()),
+        (_, Value::String(s)) => {
+            update.string_properties.insert(key, s.clone());
+        }
+        _ => {}
+    }
+}
+
+/// Show a `Position` as its cell and coordinates, e.g. `0xA9B4001F [84.00 12.50 94.25]`
+fn format_position(position: &Value) -> Option<String> {
+    let cell = ["ObjCellId", "LandblockId", "CellId"]
+        .iter()
+        .find_map(|key| position.get(*key))?
+        .as_u64()?;
+    let origin = position.get("Frame")?.get("Origin")?;
+    let coordinate = |axis: &str| origin.get(axis).and_then(|v| v.as_f64());
+    Some(format!(
+        "0x{cell:08X} [{:.2} {:.2} {:.2}]",
+        coordinate("X")?,
+        coordinate("Y")?,
+        coordinate("Z")?
+    ))
+}
+
+fn merge_update(into: &mut WeenieUpdate, from: WeenieUpdate) {
+    into.name = from.name.or(into.name.take());
+    into.lifecycle = from.lifecycle.or(into.lifecycle);
+    into.int_properties.extend(from.int_properties);
+    into.int64_properties.extend(from.int64_properties);
+    into.bool_properties.extend(from.bool_properties);
+    into.float_properties.extend(from.float_properties);
+    into.string_properties.extend(from.string_properties);
+    into.data_id_properties.extend(from.data_id_properties);
+    into.instance_id_properties
+        .extend(from.instance_id_properties);
+}
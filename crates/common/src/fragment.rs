@@ -0,0 +1,155 @@
+//! Blob fragment decoding and message reassembly
+//!
+//! Game messages travel as one or more blob fragments after the optional headers of a
+//! packet. acprotocol's `FragmentAssembler` decodes the messages but does not expose the
+//! bytes it reassembled, so we track fragments here as well to keep the raw message data.
+
+use crate::transport::ByteReader;
+use anyhow::{Result, bail};
+use std::collections::{HashMap, HashSet};
+
+/// Size of the header that precedes each blob fragment
+pub const FRAGMENT_HEADER_SIZE: usize = 16;
+
+/// A single blob fragment carried by a packet
+#[derive(Debug, Clone)]
+pub struct BlobFragment {
+    /// Sequence of the message this fragment belongs to
+    pub sequence: u32,
+    pub id: u32,
+    /// Total number of fragments in the message
+    pub count: u16,
+    /// Size of the fragment including its header
+    pub size: u16,
+    /// Position of this fragment within the message
+    pub index: u16,
+    pub group: u16,
+    /// Offset of the fragment header within the packet payload
+    pub offset: usize,
+    pub data: Vec<u8>,
+}
+
+/// Read every blob fragment from the packet body that follows the optional headers
+///
+/// `base_offset` is the position of `body` within the packet payload and is only used to
+/// record where each fragment starts.
+pub fn read_fragments(body: &[u8], base_offset: usize) -> Result<Vec<BlobFragment>> {
+    let mut reader = ByteReader::new(body);
+    let mut fragments = Vec::new();
+
+    while reader.remaining() >= FRAGMENT_HEADER_SIZE {
+        let offset = base_offset + reader.position();
+        let sequence = reader.u32()?;
+        let id = reader.u32()?;
+        let count = reader.u16()?;
+        let size = reader.u16()?;
+        let index = reader.u16()?;
+        let group = reader.u16()?;
+
+        if (size as usize) < FRAGMENT_HEADER_SIZE {
+            bail!("Fragment at offset {offset} has invalid size {size}");
+        }
+        let data = reader.bytes(size as usize - FRAGMENT_HEADER_SIZE)?.to_vec();
+
+        fragments.push(BlobFragment {
+            sequence,
+            id,
+            count,
+            size,
+            index,
+            group,
+            offset,
+            data,
+        });
+    }
+
+    Ok(fragments)
+}
+
+/// Key identifying a message being reassembled: direction plus fragment sequence and id
+pub type MessageKey = (String, u32, u32);
+
+/// A message whose fragments have all been received
+#[derive(Debug, Clone)]
+pub struct CompletedMessage {
+    pub sequence: u32,
+    pub id: u32,
+    pub data: Vec<u8>,
+}
+
+impl CompletedMessage {
+    /// The opcode at the start of the message, if there are enough bytes
+    pub fn opcode(&self) -> Option<u32> {
+        let b = self.data.get(..4)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// Collects fragments until each message is complete
+#[derive(Debug, Default)]
+pub struct FragmentReassembler {
+    partial: HashMap<MessageKey, HashMap<u16, Vec<u8>>>,
+    completed: HashSet<MessageKey>,
+}
+
+impl FragmentReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of distinct fragments received so far for the message `fragment` belongs to
+    pub fn received(&self, direction: &str, fragment: &BlobFragment) -> usize {
+        let key = (direction.to_string(), fragment.sequence, fragment.id);
+        if self.completed.contains(&key) {
+            return fragment.count.max(1) as usize;
+        }
+        self.partial.get(&key).map_or(0, |parts| parts.len())
+    }
+
+    /// Add a fragment, returning the full message if this fragment completed it
+    ///
+    /// Fragments of messages that were already completed (e.g. retransmissions) are ignored.
+    pub fn add(&mut self, direction: &str, fragment: &BlobFragment) -> Option<CompletedMessage> {
+        let key = (direction.to_string(), fragment.sequence, fragment.id);
+        if self.completed.contains(&key) {
+            return None;
+        }
+
+        let parts = self.partial.entry(key.clone()).or_default();
+        parts.insert(fragment.index, fragment.data.clone());
+
+        if parts.len() < fragment.count.max(1) as usize {
+            return None;
+        }
+
+        let mut parts = self.partial.remove(&key)?;
+        let mut data = Vec::new();
+        for index in 0..fragment.count.max(1) {
+            data.extend(parts.remove(&index)?);
+        }
+        self.completed.insert(key);
+
+        Some(CompletedMessage {
+            sequence: fragment.sequence,
+            id: fragment.id,
+            data,
+        })
+    }
+
+    /// Drop everything kept for the message `key`, complete or not
+    ///
+    /// Fragments of it that arrive afterwards start the message over.
+    pub fn forget(&mut self, key: &MessageKey) {
+        self.partial.remove(key);
+        self.completed.remove(key);
+    }
+
+    /// Number of messages with fragments kept, partial or completed
+    pub fn len(&self) -> usize {
+        self.partial.len() + self.completed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
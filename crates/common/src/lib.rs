@@ -4,26 +4,40 @@
 //! Asheron's Call network traffic.
 
 pub use acprotocol::enums::PacketHeaderFlags;
-use acprotocol::network::packet::PacketHeader;
-use acprotocol::network::packet_parser::FragmentAssembler;
-use acprotocol::network::pcap::PcapIterator;
 use anyhow::{Context, Result};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::io::Read;
-
+use std::sync::Arc;
+
+pub mod capture;
+pub mod diagnostics;
+pub mod diff;
+pub mod export;
+pub mod extract;
+pub mod extractor_rules;
+pub mod filter;
+pub mod fragment;
 pub mod messages;
 pub mod packet_flags;
+pub mod pcapng;
+pub mod query;
 pub mod serialization;
+pub mod session;
+pub mod stream;
+pub mod transport;
 pub mod tree;
+pub mod value_names;
 pub mod weenie;
 pub mod weenie_extractor;
 
 /// UI tab selection
-#[derive(Default, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum Tab {
     #[default]
     Messages,
+    Fragments,
     Weenies,
+    Diagnostics,
 }
 
 /// UI view mode
@@ -45,66 +59,152 @@ pub enum SortField {
     OpCode,
 }
 
-/// Fragment info as stored in packets
+/// A blob fragment carried by a packet and the state of the message it belongs to
 #[derive(Debug, Clone, Serialize)]
 pub struct FragmentInfo {
     #[serde(rename = "Data")]
     pub data: String, // Base64 encoded
     #[serde(rename = "Count")]
     pub count: u16,
+    /// Distinct fragments of the message received up to and including this one
     #[serde(rename = "Received")]
     pub received: usize,
     #[serde(rename = "Length")]
     pub length: usize,
     #[serde(rename = "Sequence")]
     pub sequence: u32,
+    #[serde(rename = "Id")]
+    pub id: u32,
+    #[serde(rename = "Index")]
+    pub index: u16,
+    #[serde(rename = "Group")]
+    pub group: u16,
+    /// Offset of the fragment header within the packet payload
+    #[serde(rename = "Offset")]
+    pub offset: usize,
+    /// Whether every fragment of the message was eventually received
+    #[serde(rename = "Complete")]
+    pub complete: bool,
+    /// Id of the decoded message this fragment contributed to
+    #[serde(rename = "MessageId")]
+    pub message_id: Option<usize>,
+}
+
+impl FragmentInfo {
+    fn new(fragment: &fragment::BlobFragment, received: usize) -> Self {
+        use base64::Engine;
+
+        Self {
+            data: base64::engine::general_purpose::STANDARD.encode(&fragment.data),
+            count: fragment.count,
+            received,
+            length: fragment.data.len(),
+            sequence: fragment.sequence,
+            id: fragment.id,
+            index: fragment.index,
+            group: fragment.group,
+            offset: fragment.offset,
+            complete: false,
+            message_id: None,
+        }
+    }
 }
 
 /// A parsed packet with all its data
 #[derive(Debug, Clone, Serialize)]
 pub struct ParsedPacket {
     #[serde(rename = "Header")]
-    pub header: PacketHeader,
+    pub header: transport::PacketHeader,
+    #[serde(rename = "Optional")]
+    pub optional: transport::OptionalHeaders,
     #[serde(rename = "Direction")]
     pub direction: String,
     #[serde(rename = "Messages")]
     pub messages: Vec<serde_json::Value>,
-    #[serde(rename = "Fragment")]
-    pub fragment: Option<FragmentInfo>,
+    #[serde(rename = "Fragments")]
+    pub fragments: Vec<FragmentInfo>,
     #[serde(rename = "Id")]
     pub id: usize,
     #[serde(rename = "Timestamp")]
     pub timestamp: f64, // Seconds since epoch (with microsecond precision)
+    /// Index of the capture record the packet was read from, counting every record, for
+    /// writing it back out with `capture::trim`
+    #[serde(skip)]
+    pub record_index: usize,
+    /// Byte range of the AC datagram within the capture file
     #[serde(skip)]
-    pub raw_payload: Vec<u8>,
+    pub payload_range: std::ops::Range<usize>,
+}
+
+impl ParsedPacket {
+    /// The AC datagram, from its packet header to the end of the UDP payload, read from
+    /// `capture`, the bytes of the file the packet was parsed from
+    pub fn raw_payload<'a>(&self, capture: &'a [u8]) -> &'a [u8] {
+        capture.get(self.payload_range.clone()).unwrap_or_default()
+    }
 }
 
 /// Main parser for PCAP files
-pub struct PacketParser {}
+pub struct PacketParser {
+    diagnostics: Vec<diagnostics::ParseDiagnostic>,
+    rules: Arc<extractor_rules::ExtractorRegistry>,
+}
 
 impl PacketParser {
     pub fn new() -> Self {
-        Self {}
+        Self::with_rules(extractor_rules::ExtractorRegistry::builtin())
+    }
+
+    /// A parser that extracts weenies with `rules` rather than the built-in rules
+    pub fn with_rules(rules: Arc<extractor_rules::ExtractorRegistry>) -> Self {
+        Self {
+            diagnostics: Vec::new(),
+            rules,
+        }
+    }
+
+    /// Problems found by the last parse, in capture order
+    pub fn diagnostics(&self) -> &[diagnostics::ParseDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Take the problems found by the last parse, leaving none behind
+    pub fn take_diagnostics(&mut self) -> Vec<diagnostics::ParseDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Stream the packets, messages and weenie updates of a pcap or pcapng file as they are decoded
+    ///
+    /// Only the current record, and fragments of messages from the last
+    /// `stream::FRAGMENT_TIMEOUT` seconds, are held in memory. Problems are yielded as
+    /// `ParseEvent::Diagnostic` rather than collected in `diagnostics`.
+    pub fn stream<R: Read>(&self, reader: R) -> Result<stream::PacketStream<R>> {
+        let records =
+            capture::CaptureReader::new(reader).context("Failed to read capture header")?;
+        Ok(stream::PacketStream::new(
+            records,
+            weenie_extractor::WeenieExtractor::with_rules(self.rules.clone()),
+        ))
     }
 
     /// Parse a PCAP file from a reader
     pub fn parse_pcap<R: Read>(
         &mut self,
-        mut reader: R,
+        reader: R,
     ) -> Result<(
         Vec<ParsedPacket>,
         Vec<messages::ParsedMessage>,
         weenie::WeenieDatabase,
     )> {
-        let mut buffer = Vec::new();
-        reader
-            .read_to_end(&mut buffer)
-            .context("Failed to read pcap data")?;
-
-        self.parse_pcap_bytes(&buffer)
+        let events = self.stream(reader)?;
+        Ok(self.collect(events))
     }
 
     /// Parse PCAP data from bytes
+    ///
+    /// Packets and messages that can't be decoded are skipped and recorded in
+    /// `diagnostics`. A capture that ends part way through a record keeps everything
+    /// read up to that point.
     pub fn parse_pcap_bytes(
         &mut self,
         buffer: &[u8],
@@ -113,122 +213,33 @@ impl PacketParser {
         Vec<messages::ParsedMessage>,
         weenie::WeenieDatabase,
     )> {
-        let mut packets = Vec::new();
-        let mut all_messages = Vec::new();
-        let mut weenie_db = weenie::WeenieDatabase::new();
-        let mut packet_id = 0;
-        let mut message_id = 0;
-
-        // Create iterator and assembler
-        let iter = PcapIterator::<std::io::Cursor<&[u8]>>::from_bytes(buffer)
-            .context("Failed to create pcap iterator")?;
-        let mut assembler = FragmentAssembler::new();
-
-        for result in iter {
-            let packet = result.context("Failed to read packet")?;
-
-            // Extract timestamp (seconds + microseconds)
-            let timestamp = packet.ts_sec as f64 + (packet.ts_usec as f64 / 1_000_000.0);
-
-            // Use FragmentAssembler to parse the packet payload
-            // This handles header stripping, fragment assembly, and message parsing
-            match assembler.parse_packet_payload(&packet.data) {
-                Ok(messages) => {
-                    if !messages.is_empty() {
-                        // Create a ParsedPacket for this packet
-                        // Note: We don't have direct access to the packet header anymore,
-                        // so we'll create a minimal packet entry
-                        let mut parsed_messages_json = Vec::new();
-
-                        for msg in messages {
-                            // Convert acprotocol message to our ParsedMessage format
-                            let message_type = msg.message_type.clone();
-                            let opcode_str = format!("{:04X}", msg.opcode);
-
-                            // Serialize the message to JSON
-                            let data = serde_json::to_value(&msg)
-                                .unwrap_or_else(|_| serde_json::json!({}));
-
-                            parsed_messages_json.push(data.clone());
-
-                            // The direction is already a string in the message
-                            let direction_str = msg.direction.clone();
-
-                            // Create ParsedMessage
-                            let parsed_msg = messages::ParsedMessage {
-                                id: message_id,
-                                message_type,
-                                data,
-                                direction: direction_str,
-                                opcode: opcode_str,
-                                timestamp,
-                                raw_bytes: Vec::new(), // We don't have access to raw bytes anymore
-                            };
-
-                            all_messages.push(parsed_msg);
-                            message_id += 1;
-                        }
-
-                        // Create a minimal ParsedPacket
-                        // Since we don't parse packet headers directly anymore, we create a stub
-                        let parsed_packet = ParsedPacket {
-                            header: PacketHeader::with_flags(PacketHeaderFlags::empty()),
-                            direction: if !all_messages.is_empty() {
-                                all_messages.last().unwrap().direction.clone()
-                            } else {
-                                "Unknown".to_string()
-                            },
-                            messages: parsed_messages_json,
-                            fragment: None, // Fragment info not available with FragmentAssembler
-                            id: packet_id,
-                            timestamp,
-                            raw_payload: Vec::new(),
-                        };
-
-                        packets.push(parsed_packet);
-                        packet_id += 1;
-                    }
-                }
-                Err(_e) => {
-                    // Skip failed packets silently
-                }
-            }
-        }
+        self.parse_pcap(buffer)
+    }
 
-        // Extract weenie updates from all messages
-        let mut type_counts: std::collections::HashMap<String, (usize, usize)> =
-            std::collections::HashMap::new();
-        for msg in &all_messages {
-            let updates = weenie_extractor::extract_weenie_updates(msg);
-            let entry = type_counts
-                .entry(msg.message_type.clone())
-                .or_insert((0, 0));
-            entry.0 += 1; // total messages
-            entry.1 += updates.len(); // successful extractions
-            for update in updates {
-                weenie_db.add_or_update(update);
-            }
+    /// Gather a whole stream into collections, recording its diagnostics
+    fn collect<R: Read>(
+        &mut self,
+        events: stream::PacketStream<R>,
+    ) -> (
+        Vec<ParsedPacket>,
+        Vec<messages::ParsedMessage>,
+        weenie::WeenieDatabase,
+    ) {
+        let mut capture = stream::ParsedCapture::new();
+        for event in events {
+            capture.push(event);
         }
 
-        eprintln!("\n=== Extraction Summary ===");
-        eprintln!("Total messages processed: {}", all_messages.len());
-        let mut types: Vec<_> = type_counts.iter().collect();
-        types.sort_by_key(|(_, (_, extracted))| std::cmp::Reverse(*extracted));
-        for (msg_type, (total, extracted)) in types.iter().take(20) {
-            if *extracted > 0 {
-                eprintln!(
-                    "{}: {} extracted from {} messages",
-                    msg_type, extracted, total
-                );
-            }
-        }
-        eprintln!(
-            "Total message types with 0 extractions: {}",
-            types.iter().filter(|(_, (_, e))| *e == 0).count()
-        );
-        eprintln!("Final weenie count: {}\n", weenie_db.count());
+        let stream::ParsedCapture {
+            packets,
+            messages,
+            weenie_db,
+            mut diagnostics,
+        } = capture;
+        diagnostics.sort_by_key(|d| d.record_index);
+        self.diagnostics = diagnostics;
 
-        Ok((packets, all_messages, weenie_db))
+        (packets, messages, weenie_db)
     }
 }
 
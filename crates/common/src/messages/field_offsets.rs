@@ -0,0 +1,359 @@
+//! Map decoded message fields back to the bytes they came from
+//!
+//! acprotocol's readers don't report positions, so offsets are recovered by locating the
+//! encoding of every leaf value within the message bytes. Values with more significant
+//! bytes (strings, large ids, floats) are placed first so that common small values like
+//! `0` or `1` can't claim bytes that belong to a more distinctive field. Containers span
+//! the bytes of all of their located children.
+//!
+//! The offsets are inferred, not read: a value found at more than one free position is
+//! left out rather than guessed, and even a unique match can be a coincidence.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Byte range `start..end` within the message bytes inferred to have decoded the field
+/// at `path`
+///
+/// Paths use the same form as the tree view: `Key.Nested`, `List[0].Field`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpan {
+    pub path: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl FieldSpan {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+/// The spans of a message's fields, looked up by path or by byte
+#[derive(Debug, Clone, Default)]
+pub struct FieldOffsets {
+    spans: Vec<FieldSpan>,
+    /// Index into `spans` of each path
+    by_path: HashMap<String, usize>,
+}
+
+impl FieldOffsets {
+    /// Locate the fields of `data` in `bytes`, as `map_field_offsets` does
+    pub fn new(data: &Value, bytes: &[u8]) -> Self {
+        let spans = map_field_offsets(data, bytes);
+        let by_path = spans
+            .iter()
+            .enumerate()
+            .map(|(idx, span)| (span.path.clone(), idx))
+            .collect();
+        Self { spans, by_path }
+    }
+
+    /// Every span, in tree order
+    pub fn spans(&self) -> &[FieldSpan] {
+        &self.spans
+    }
+
+    /// The span of the field at `path`
+    pub fn get(&self, path: &str) -> Option<&FieldSpan> {
+        self.by_path.get(path).map(|&idx| &self.spans[idx])
+    }
+
+    /// The innermost field that decoded the byte at `offset`
+    pub fn at(&self, offset: usize) -> Option<&FieldSpan> {
+        field_at(&self.spans, offset)
+    }
+}
+
+/// One way a value may have been written on the wire
+enum Encoding {
+    Exact(Vec<u8>),
+    F32(f64),
+    F64(f64),
+}
+
+impl Encoding {
+    fn len(&self) -> usize {
+        match self {
+            Encoding::Exact(bytes) => bytes.len(),
+            Encoding::F32(_) => 4,
+            Encoding::F64(_) => 8,
+        }
+    }
+
+    fn matches(&self, window: &[u8]) -> bool {
+        match self {
+            Encoding::Exact(bytes) => window == bytes.as_slice(),
+            Encoding::F32(value) => {
+                let f = f32::from_le_bytes([window[0], window[1], window[2], window[3]]);
+                rounds_to(f as f64, *value)
+            }
+            Encoding::F64(value) => {
+                let mut b = [0u8; 8];
+                b.copy_from_slice(window);
+                rounds_to(f64::from_le_bytes(b), *value)
+            }
+        }
+    }
+}
+
+/// Floats are serialized rounded to two decimals (see `serialization::serialize_f32`)
+fn rounds_to(decoded: f64, value: f64) -> bool {
+    decoded.is_finite() && ((decoded * 100.0).round() / 100.0 - value).abs() < 1e-9
+}
+
+/// A leaf value waiting to be placed
+struct Leaf {
+    encodings: Vec<Encoding>,
+    /// How distinctive the value is; higher values are placed first
+    weight: usize,
+}
+
+/// Compute the byte span of every field in `data` that can be located in `bytes`
+///
+/// Spans are returned in tree order, parents before their children. Fields whose
+/// encoding can't be found (enum names, values computed by the decoder) or is found in
+/// more than one place are omitted.
+pub fn map_field_offsets(data: &Value, bytes: &[u8]) -> Vec<FieldSpan> {
+    let mut leaves = Vec::new();
+    collect_leaves(data, &mut leaves);
+
+    // Place the most distinctive values first; the sort is stable so ties keep tree order
+    let mut order: Vec<usize> = (0..leaves.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(leaves[i].weight));
+
+    let mut claimed = vec![false; bytes.len()];
+    let mut located: Vec<Option<(usize, usize)>> = vec![None; leaves.len()];
+    for i in order {
+        for encoding in &leaves[i].encodings {
+            let found = {
+                let mut starts = find_unclaimed(bytes, &claimed, encoding);
+                (starts.next(), starts.next())
+            };
+            match found {
+                (None, _) => continue,
+                (Some(start), None) => {
+                    let end = start + encoding.len();
+                    claimed[start..end].iter_mut().for_each(|c| *c = true);
+                    located[i] = Some((start, end));
+                }
+                // Found in more than one place, so where it came from is anyone's guess
+                (Some(_), Some(_)) => {}
+            }
+            break;
+        }
+    }
+
+    let mut spans = Vec::new();
+    let mut next_leaf = 0;
+    collect_spans(data, "", &located, &mut next_leaf, &mut spans);
+    spans
+}
+
+/// Find the innermost span containing `offset`
+pub fn field_at(spans: &[FieldSpan], offset: usize) -> Option<&FieldSpan> {
+    // Children follow their parents, so search backwards to prefer a leaf over a
+    // container of the same size
+    spans
+        .iter()
+        .rev()
+        .filter(|span| span.contains(offset))
+        .min_by_key(|span| span.len())
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// Gather leaf values in tree order
+fn collect_leaves(value: &Value, leaves: &mut Vec<Leaf>) {
+    match value {
+        Value::Object(map) => map.values().for_each(|val| collect_leaves(val, leaves)),
+        Value::Array(arr) => arr.iter().for_each(|val| collect_leaves(val, leaves)),
+        _ => {
+            let (encodings, weight) = encodings_for(value);
+            leaves.push(Leaf { encodings, weight });
+        }
+    }
+}
+
+/// Walk the tree in the same order as `collect_leaves`, emitting spans for located
+/// leaves and for every container with at least one located descendant
+fn collect_spans(
+    value: &Value,
+    path: &str,
+    located: &[Option<(usize, usize)>],
+    next_leaf: &mut usize,
+    spans: &mut Vec<FieldSpan>,
+) -> Option<(usize, usize)> {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(k, v)| (child_path(path, k), v)).collect(),
+        Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("{path}[{i}]"), v))
+            .collect(),
+        _ => {
+            let range = located[*next_leaf];
+            *next_leaf += 1;
+            if let Some((start, end)) = range {
+                spans.push(FieldSpan {
+                    path: path.to_string(),
+                    start,
+                    end,
+                });
+            }
+            return range;
+        }
+    };
+
+    // Reserve the container's slot so it precedes its children
+    let slot = spans.len();
+    let mut range: Option<(usize, usize)> = None;
+    for (child, val) in children {
+        if let Some((start, end)) = collect_spans(val, &child, located, next_leaf, spans) {
+            range = Some(match range {
+                Some((s, e)) => (s.min(start), e.max(end)),
+                None => (start, end),
+            });
+        }
+    }
+
+    if let Some((start, end)) = range
+        && !path.is_empty()
+    {
+        spans.insert(
+            slot,
+            FieldSpan {
+                path: path.to_string(),
+                start,
+                end,
+            },
+        );
+    }
+    range
+}
+
+/// Every start of `encoding` in `bytes` that doesn't overlap a claimed byte
+fn find_unclaimed<'a>(
+    bytes: &'a [u8],
+    claimed: &'a [bool],
+    encoding: &'a Encoding,
+) -> impl Iterator<Item = usize> + 'a {
+    let len = encoding.len();
+    let last = if len == 0 {
+        None
+    } else {
+        bytes.len().checked_sub(len)
+    };
+    last.into_iter()
+        .flat_map(|last| 0..=last)
+        .filter(move |&start| {
+            !claimed[start..start + len].iter().any(|c| *c)
+                && encoding.matches(&bytes[start..start + len])
+        })
+}
+
+/// Candidate encodings for a leaf, most likely first, and the leaf's placement weight
+fn encodings_for(value: &Value) -> (Vec<Encoding>, usize) {
+    match value {
+        Value::Bool(b) => (
+            vec![
+                Encoding::Exact((*b as u32).to_le_bytes().to_vec()),
+                Encoding::Exact(vec![*b as u8]),
+            ],
+            0,
+        ),
+        Value::Number(num) => {
+            if let Some(u) = num.as_u64() {
+                let mut encodings = unsigned_encodings(u);
+                // Whole floats serialize as integers
+                encodings.push(Encoding::F32(u as f64));
+                encodings.push(Encoding::F64(u as f64));
+                (encodings, significant_bytes(u))
+            } else if let Some(i) = num.as_i64() {
+                let mut encodings = Vec::new();
+                if let Ok(v) = i32::try_from(i) {
+                    encodings.push(Encoding::Exact(v.to_le_bytes().to_vec()));
+                }
+                encodings.push(Encoding::Exact(i.to_le_bytes().to_vec()));
+                encodings.push(Encoding::F32(i as f64));
+                encodings.push(Encoding::F64(i as f64));
+                (encodings, 4)
+            } else if let Some(f) = num.as_f64() {
+                (vec![Encoding::F32(f), Encoding::F64(f)], 4)
+            } else {
+                (Vec::new(), 0)
+            }
+        }
+        Value::String(s) => {
+            let mut encodings = Vec::new();
+
+            // Hex strings such as opcodes ("F7B0") are numbers on the wire
+            if matches!(s.len(), 4 | 8)
+                && let Ok(u) = u32::from_str_radix(s, 16)
+            {
+                encodings.push(Encoding::Exact(u.to_le_bytes().to_vec()));
+            }
+
+            // String16L: u16 length followed by the characters
+            if let Ok(len) = u16::try_from(s.len()) {
+                let mut prefixed = len.to_le_bytes().to_vec();
+                prefixed.extend_from_slice(s.as_bytes());
+                encodings.push(Encoding::Exact(prefixed));
+            }
+
+            // WString: UTF-16 characters, with and without a length prefix
+            let wide: Vec<u8> = s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+            if let Ok(len) = u16::try_from(s.encode_utf16().count()) {
+                let mut prefixed = len.to_le_bytes().to_vec();
+                prefixed.extend_from_slice(&wide);
+                encodings.push(Encoding::Exact(prefixed));
+            }
+
+            if !s.is_empty() {
+                encodings.push(Encoding::Exact(s.as_bytes().to_vec()));
+            }
+
+            (encodings, s.len() + 2)
+        }
+        _ => (Vec::new(), 0),
+    }
+}
+
+/// u32 first since most AC fields are dwords, then the narrower widths the value fits in
+fn unsigned_encodings(u: u64) -> Vec<Encoding> {
+    let Ok(v) = u32::try_from(u) else {
+        return vec![Encoding::Exact(u.to_le_bytes().to_vec())];
+    };
+    let mut encodings = vec![Encoding::Exact(v.to_le_bytes().to_vec())];
+    if let Ok(v) = u16::try_from(v) {
+        encodings.push(Encoding::Exact(v.to_le_bytes().to_vec()));
+    }
+    if let Ok(v) = u8::try_from(v) {
+        encodings.push(Encoding::Exact(vec![v]));
+    }
+    encodings.push(Encoding::Exact(u.to_le_bytes().to_vec()));
+    encodings
+}
+
+/// Number of bytes needed to hold `u`, with 0 and 1 counting as nothing
+fn significant_bytes(u: u64) -> usize {
+    if u <= 1 {
+        0
+    } else {
+        (64 - u.leading_zeros() as usize).div_ceil(8)
+    }
+}
@@ -5,6 +5,12 @@ use std::io::Cursor;
 use acprotocol::message::{Direction, MessageKind};
 use acprotocol::readers::ACReader;
 
+use crate::diagnostics::{DiagnosticKind, ParseDiagnostic};
+
+pub mod field_offsets;
+
+use field_offsets::{FieldOffsets, FieldSpan};
+
 /// Parsed AC message with all fields decoded
 #[derive(Debug, Clone, Serialize)]
 pub struct ParsedMessage {
@@ -22,10 +28,46 @@ pub struct ParsedMessage {
     pub timestamp: f64, // Seconds since epoch (with microsecond precision)
     #[serde(skip)]
     pub raw_bytes: Vec<u8>,
+    /// Inferred byte range of each decoded field within `raw_bytes`
+    ///
+    /// Filled by `parse_message`; messages from a capture leave it as `None` and compute
+    /// it on demand with `ensure_field_offsets`, since only the inspected ones need it.
+    #[serde(skip)]
+    pub field_offsets: Option<FieldOffsets>,
+}
+
+impl ParsedMessage {
+    /// Compute the field offsets if they haven't been yet, keeping them on the message
+    pub fn ensure_field_offsets(&mut self) -> &FieldOffsets {
+        self.field_offsets
+            .get_or_insert_with(|| FieldOffsets::new(&self.data, &self.raw_bytes))
+    }
+
+    /// The byte range of the field at `path`
+    pub fn field_span(&self, path: &str) -> Option<&FieldSpan> {
+        self.field_offsets.as_ref()?.get(path)
+    }
+
+    /// The innermost field that decoded the byte at `offset`
+    pub fn field_at(&self, offset: usize) -> Option<&FieldSpan> {
+        self.field_offsets.as_ref()?.at(offset)
+    }
 }
 
 /// Parse a message from raw bytes using acprotocol
+///
+/// A message whose fields fail to decode is still returned, with the error and the
+/// undecoded bytes as its data; use `parse_message_with_diagnostics` to find out about it.
 pub fn parse_message(data: &[u8], id: usize) -> Result<ParsedMessage> {
+    parse_message_with_diagnostics(data, id, &mut Vec::new())
+}
+
+/// Parse a message from raw bytes, recording a diagnostic if its fields fail to decode
+pub fn parse_message_with_diagnostics(
+    data: &[u8],
+    id: usize,
+    diagnostics: &mut Vec<ParseDiagnostic>,
+) -> Result<ParsedMessage> {
     if data.len() < 4 {
         anyhow::bail!("Message data too short to contain opcode");
     }
@@ -50,6 +92,12 @@ pub fn parse_message(data: &[u8], id: usize) -> Result<ParsedMessage> {
             serde_json::to_value(&message)?
         }
         Err(e) => {
+            diagnostics.push(
+                ParseDiagnostic::new(DiagnosticKind::DecodeFailed, e.to_string())
+                    .with_offset(0)
+                    .with_opcode(opcode),
+            );
+
             // If parsing fails, return error info
             serde_json::json!({
                 "error": format!("{}", e),
@@ -61,6 +109,7 @@ pub fn parse_message(data: &[u8], id: usize) -> Result<ParsedMessage> {
 
     // Get message type name
     let message_type = get_message_type_name(opcode, data);
+    let field_offsets = FieldOffsets::new(&parsed_data, data);
 
     Ok(ParsedMessage {
         id,
@@ -70,9 +119,15 @@ pub fn parse_message(data: &[u8], id: usize) -> Result<ParsedMessage> {
         opcode: format!("{:04X}", opcode),
         timestamp: 0.0,
         raw_bytes: data.to_vec(),
+        field_offsets: Some(field_offsets),
     })
 }
 
+/// Whether the opcode is a client-to-server or server-to-client message acprotocol knows
+pub fn is_known_opcode(opcode: u32) -> bool {
+    determine_direction(opcode).is_ok()
+}
+
 /// Determine message direction based on opcode
 fn determine_direction(opcode: u32) -> Result<Direction> {
     use acprotocol::enums::{C2SMessage, S2CMessage};
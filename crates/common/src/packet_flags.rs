@@ -1,79 +1,38 @@
+use crate::value_names::flag_names_with;
 use acprotocol::enums::PacketHeaderFlags;
 
-/// Mapping of flag names to their short display forms for the TUI
-pub fn format_packet_flags(flags: PacketHeaderFlags) -> String {
-    let mut flag_names = Vec::new();
-
-    if flags.contains(PacketHeaderFlags::RETRANSMISSION) {
-        flag_names.push("Retrans");
-    }
-    if flags.contains(PacketHeaderFlags::ENCRYPTED_CHECKSUM) {
-        flag_names.push("EncCksum");
-    }
-    if flags.contains(PacketHeaderFlags::BLOB_FRAGMENTS) {
-        flag_names.push("BlobFrag");
-    }
-    if flags.contains(PacketHeaderFlags::SERVER_SWITCH) {
-        flag_names.push("SrvSwitch");
-    }
-    if flags.contains(PacketHeaderFlags::LOGON_SERVER_ADDR) {
-        flag_names.push("LogonAddr");
-    }
-    if flags.contains(PacketHeaderFlags::EMPTY_HEADER1) {
-        flag_names.push("EmptyHdr1");
-    }
-    if flags.contains(PacketHeaderFlags::REFERRAL) {
-        flag_names.push("Referral");
-    }
-    if flags.contains(PacketHeaderFlags::REQUEST_RETRANSMIT) {
-        flag_names.push("ReqRetrans");
-    }
-    if flags.contains(PacketHeaderFlags::REJECT_RETRANSMIT) {
-        flag_names.push("RejRetrans");
-    }
-    if flags.contains(PacketHeaderFlags::ACK_SEQUENCE) {
-        flag_names.push("Ack");
-    }
-    if flags.contains(PacketHeaderFlags::DISCONNECT) {
-        flag_names.push("Disc");
-    }
-    if flags.contains(PacketHeaderFlags::LOGIN_REQUEST) {
-        flag_names.push("Login");
-    }
-    if flags.contains(PacketHeaderFlags::WORLD_LOGIN_REQUEST) {
-        flag_names.push("WorldLogin");
-    }
-    if flags.contains(PacketHeaderFlags::CONNECT_REQUEST) {
-        flag_names.push("ConnectReq");
-    }
-    if flags.contains(PacketHeaderFlags::CONNECT_RESPONSE) {
-        flag_names.push("ConnectResp");
-    }
-    if flags.contains(PacketHeaderFlags::NET_ERROR) {
-        flag_names.push("NetErr");
-    }
-    if flags.contains(PacketHeaderFlags::NET_ERROR_DISCONNECT) {
-        flag_names.push("NetErrDisc");
-    }
-    if flags.contains(PacketHeaderFlags::CICMDCOMMAND) {
-        flag_names.push("CICMD");
-    }
-    if flags.contains(PacketHeaderFlags::TIME_SYNC) {
-        flag_names.push("TimeSync");
-    }
-    if flags.contains(PacketHeaderFlags::ECHO_REQUEST) {
-        flag_names.push("EchoReq");
-    }
-    if flags.contains(PacketHeaderFlags::ECHO_RESPONSE) {
-        flag_names.push("EchoResp");
-    }
-    if flags.contains(PacketHeaderFlags::FLOW) {
-        flag_names.push("Flow");
-    }
+/// Short display names for packet header flags, by their names in `PacketHeaderFlags`
+const SHORT_NAMES: &[(&str, &str)] = &[
+    ("RETRANSMISSION", "Retrans"),
+    ("ENCRYPTED_CHECKSUM", "EncCksum"),
+    ("BLOB_FRAGMENTS", "BlobFrag"),
+    ("SERVER_SWITCH", "SrvSwitch"),
+    ("LOGON_SERVER_ADDR", "LogonAddr"),
+    ("EMPTY_HEADER1", "EmptyHdr1"),
+    ("REFERRAL", "Referral"),
+    ("REQUEST_RETRANSMIT", "ReqRetrans"),
+    ("REJECT_RETRANSMIT", "RejRetrans"),
+    ("ACK_SEQUENCE", "Ack"),
+    ("DISCONNECT", "Disc"),
+    ("LOGIN_REQUEST", "Login"),
+    ("WORLD_LOGIN_REQUEST", "WorldLogin"),
+    ("CONNECT_REQUEST", "ConnectReq"),
+    ("CONNECT_RESPONSE", "ConnectResp"),
+    ("NET_ERROR", "NetErr"),
+    ("NET_ERROR_DISCONNECT", "NetErrDisc"),
+    ("CICMDCOMMAND", "CICMD"),
+    ("TIME_SYNC", "TimeSync"),
+    ("ECHO_REQUEST", "EchoReq"),
+    ("ECHO_RESPONSE", "EchoResp"),
+    ("FLOW", "Flow"),
+];
 
-    if flag_names.is_empty() {
-        "None".to_string()
-    } else {
-        flag_names.join("|")
-    }
+/// The flags set in `flags` by their short names, e.g. `Retrans|Ack`
+pub fn format_packet_flags(flags: PacketHeaderFlags) -> String {
+    flag_names_with::<PacketHeaderFlags>(flags.bits(), |name| {
+        SHORT_NAMES
+            .iter()
+            .find(|(flag, _)| *flag == name)
+            .map(|(_, short)| *short)
+    })
 }
@@ -0,0 +1,291 @@
+//! Reading packet records from pcapng files
+//!
+//! A pcapng file is a sequence of blocks. A section header starts each section and sets
+//! its byte order; interface descriptions then declare each capture interface's link type
+//! and timestamp resolution; enhanced packet blocks hold the frames, each naming the
+//! interface it was captured on. Other blocks (name resolution, statistics, custom) carry
+//! no frames and are skipped.
+
+use crate::capture::{self, CaptureRecord, MAX_RECORD_SIZE};
+use anyhow::{Context, Result, bail};
+use std::io::Read;
+
+/// Type of a section header block, which reads the same in either byte order
+pub const SECTION_HEADER_MAGIC: [u8; 4] = [0x0A, 0x0D, 0x0D, 0x0A];
+
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+const OPTION_END: u16 = 0;
+const OPTION_IF_TSRESOL: u16 = 9;
+const OPTION_IF_TSOFFSET: u16 = 14;
+
+/// Blocks larger than this are treated as corruption rather than allocated; room is left
+/// for the options after a frame of the largest size
+const MAX_BLOCK_SIZE: usize = MAX_RECORD_SIZE + 64 * 1024;
+
+/// A capture interface declared in the current section
+#[derive(Debug, Clone)]
+struct Interface {
+    link_type: u32,
+    /// Timestamp units per second, from `if_tsresol`
+    units_per_second: u64,
+    /// Seconds added to every timestamp, from `if_tsoffset`
+    offset: i64,
+}
+
+/// Reads records from a pcapng file
+pub struct PcapngReader<R> {
+    reader: R,
+    /// Current section was written on a host with the other byte order
+    swapped: bool,
+    /// Interfaces of the current section, in the order declared
+    interfaces: Vec<Interface>,
+    bytes_read: u64,
+}
+
+impl<R: Read> PcapngReader<R> {
+    /// Read the first section header and prepare to read records
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .context("Capture is too short for a pcapng section header")?;
+        Self::with_magic(magic, reader)
+    }
+
+    /// Read the rest of the first section header, after its block type
+    pub(crate) fn with_magic(magic: [u8; 4], reader: R) -> Result<Self> {
+        if magic != SECTION_HEADER_MAGIC {
+            bail!(
+                "Not a pcapng file (block type 0x{:08X})",
+                u32::from_le_bytes(magic)
+            );
+        }
+        let mut pcapng = Self {
+            reader,
+            swapped: false,
+            interfaces: Vec::new(),
+            bytes_read: magic.len() as u64,
+        };
+        let (_, body) = pcapng.read_block(magic)?;
+        pcapng.section_header(&body)?;
+        Ok(pcapng)
+    }
+
+    /// Number of bytes consumed from the underlying reader so far
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Read the next record, or `None` at the end of the file
+    ///
+    /// A file that ends part way through a block is an error.
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>> {
+        loop {
+            let start = self.bytes_read;
+            let mut block_type = [0u8; 4];
+            match self.fill(&mut block_type)? {
+                0 => return Ok(None),
+                4 => {}
+                n => bail!("Capture ends {n} bytes into a block header"),
+            }
+
+            let (block_type, body) = self.read_block(block_type)?;
+            match block_type {
+                BLOCK_SECTION_HEADER => self.section_header(&body)?,
+                BLOCK_INTERFACE_DESCRIPTION => self.interface_description(&body)?,
+                BLOCK_ENHANCED_PACKET => return self.enhanced_packet(start, &body).map(Some),
+                _ => {}
+            }
+        }
+    }
+
+    /// Read the rest of a block after its type, returning its type and body
+    ///
+    /// A section header switches to the byte order it was written in.
+    fn read_block(&mut self, block_type: [u8; 4]) -> Result<(u32, Vec<u8>)> {
+        let mut length = [0u8; 4];
+        if self.fill(&mut length)? < 4 {
+            bail!("Capture ends in a block header");
+        }
+
+        // The byte-order magic is the first field of a section header's body
+        let mut body = Vec::new();
+        if block_type == SECTION_HEADER_MAGIC {
+            let mut magic = [0u8; 4];
+            if self.fill(&mut magic)? < 4 {
+                bail!("Capture ends in a section header");
+            }
+            self.swapped = match u32::from_le_bytes(magic) {
+                BYTE_ORDER_MAGIC => false,
+                m if m.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                m => bail!("Not a pcapng section (byte-order magic 0x{m:08X})"),
+            };
+            body.extend_from_slice(&magic);
+        }
+
+        let block_type = self.u32(&block_type);
+        let length = self.u32(&length) as usize;
+        if length < 12 + body.len() || !length.is_multiple_of(4) || length > MAX_BLOCK_SIZE {
+            bail!("Block of {length} bytes is not a valid pcapng block");
+        }
+
+        // The rest of the body, then the block length repeated
+        let start = body.len();
+        body.resize(length - 8, 0);
+        let n = self.fill(&mut body[start..])?;
+        if n < length - 8 - start {
+            bail!("Capture ends {n} bytes into a {length} byte block");
+        }
+        body.truncate(length - 12);
+        Ok((block_type, body))
+    }
+
+    /// Start a new section, whose interfaces replace those of the last
+    fn section_header(&mut self, body: &[u8]) -> Result<()> {
+        if body.len() < 16 {
+            bail!("Section header of {} bytes is too short", body.len());
+        }
+        let major = self.u16(&body[4..6]);
+        if major != 1 {
+            bail!("Unsupported pcapng version {major}");
+        }
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    fn interface_description(&mut self, body: &[u8]) -> Result<()> {
+        if body.len() < 8 {
+            bail!("Interface description of {} bytes is too short", body.len());
+        }
+        let mut interface = Interface {
+            link_type: self.u16(&body[0..2]) as u32,
+            units_per_second: 1_000_000,
+            offset: 0,
+        };
+
+        for (code, value) in self.options(&body[8..]) {
+            match (code, value) {
+                (OPTION_IF_TSRESOL, [resolution, ..]) => {
+                    // High bit clear is a power of ten, set is a power of two
+                    let exponent = (resolution & 0x7F) as u32;
+                    let units = if resolution & 0x80 == 0 {
+                        10u64.checked_pow(exponent)
+                    } else {
+                        1u64.checked_shl(exponent)
+                    };
+                    interface.units_per_second = units.with_context(|| {
+                        format!("Unsupported timestamp resolution 0x{resolution:02X}")
+                    })?;
+                }
+                (OPTION_IF_TSOFFSET, value) if value.len() >= 8 => {
+                    interface.offset = self.u64(value) as i64;
+                }
+                _ => {}
+            }
+        }
+
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    /// The frame in the body of an enhanced packet block starting at `start` in the file
+    fn enhanced_packet(&self, start: u64, body: &[u8]) -> Result<CaptureRecord> {
+        if body.len() < 20 {
+            bail!("Enhanced packet block of {} bytes is too short", body.len());
+        }
+        let interface_id = self.u32(&body[0..4]) as usize;
+        let Some(interface) = self.interfaces.get(interface_id) else {
+            bail!("Packet captured on interface {interface_id}, which was never described");
+        };
+        let timestamp = ((self.u32(&body[4..8]) as u64) << 32) | self.u32(&body[8..12]) as u64;
+        let captured_len = self.u32(&body[12..16]) as usize;
+        let orig_len = self.u32(&body[16..20]);
+        if captured_len > MAX_RECORD_SIZE {
+            bail!("Record of {captured_len} bytes is larger than any valid frame");
+        }
+        let Some(data) = body.get(20..20 + captured_len) else {
+            bail!("Enhanced packet block is too short for its {captured_len} byte frame");
+        };
+
+        let units = interface.units_per_second;
+        let seconds = ((timestamp / units) as i64).saturating_add(interface.offset);
+        let micros = (timestamp % units) as u128 * 1_000_000 / units as u128;
+        Ok(CaptureRecord {
+            ts_sec: u32::try_from(seconds)
+                .with_context(|| format!("Timestamp of {seconds}s is out of range"))?,
+            ts_usec: micros as u32,
+            link_type: interface.link_type,
+            orig_len,
+            // After the block type and length, and the fields before the frame
+            offset: start + 8 + 20,
+            data: data.to_vec(),
+        })
+    }
+
+    /// Each option of an options list as (code, value), up to the end-of-options marker
+    fn options<'a>(&self, mut data: &'a [u8]) -> Vec<(u16, &'a [u8])> {
+        let mut options = Vec::new();
+        while data.len() >= 4 {
+            let code = self.u16(&data[0..2]);
+            let length = self.u16(&data[2..4]) as usize;
+            if code == OPTION_END {
+                break;
+            }
+            let Some(value) = data.get(4..4 + length) else {
+                break;
+            };
+            options.push((code, value));
+            // Values are padded to 32 bits
+            data = data
+                .get(4 + length.next_multiple_of(4)..)
+                .unwrap_or_default();
+        }
+        options
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.swapped {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.swapped {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    fn u64(&self, bytes: &[u8]) -> u64 {
+        let bytes: [u8; 8] = bytes[..8].try_into().unwrap_or_default();
+        if self.swapped {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        }
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let filled = capture::fill(&mut self.reader, buf)?;
+        self.bytes_read += filled as u64;
+        Ok(filled)
+    }
+}
+
+impl<R: Read> Iterator for PcapngReader<R> {
+    type Item = Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
@@ -0,0 +1,562 @@
+//! Structured queries over parsed messages
+//!
+//! A query combines field comparisons and free text with `AND`, `OR`, `NOT` and
+//! parentheses:
+//!
+//! ```text
+//! type:Magic_UpdateEnchantment AND dir:recv AND data.ObjectId==0x5000001A AND ts>120
+//! ```
+//!
+//! Terms next to each other are ANDed. Fields are `type`, `dir`, `id`, `opcode`, `ts`
+//! (seconds since the start of the capture) and `data.<path>` for any field of the
+//! decoded message, where the path is written as in [`crate::extract`]. Operators are `:`
+//! (contains), `==`, `!=`, `>`, `>=`, `<` and `<=`. Anything that isn't a field comparison
+//! is free text, matched the same way as the plain search box.
+
+use crate::extract::FieldPath;
+use crate::filter::{json_contains_string, matches_any_filter, parse_filter_string};
+use crate::messages::ParsedMessage;
+use serde_json::Value;
+use thiserror::Error;
+
+/// A query that couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{message} (at character {position})")]
+pub struct QueryError {
+    pub message: String,
+    /// Character offset into the query where the problem is
+    pub position: usize,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+/// A message field that can be compared
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Type,
+    Direction,
+    Id,
+    OpCode,
+    /// Seconds since the start of the capture
+    Time,
+    /// Path into the decoded message data; empty searches the whole of it
+    Data(FieldPath),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Result<Self, String> {
+        let field = match name.to_lowercase().as_str() {
+            "type" => Field::Type,
+            "dir" | "direction" => Field::Direction,
+            "id" => Field::Id,
+            "opcode" | "op" => Field::OpCode,
+            "ts" | "time" => Field::Time,
+            _ => {
+                let unknown = || format!("Unknown field `{name}`");
+                let path = name
+                    .get(..4)
+                    .filter(|prefix| prefix.eq_ignore_ascii_case("data"))
+                    .map(|_| &name[4..])
+                    .filter(|path| path.is_empty() || path.starts_with(['.', '[']))
+                    .ok_or_else(unknown)?;
+                let path = FieldPath::parse(path)
+                    .map_err(|e| format!("Invalid path `{name}`: {}", e.message))?;
+                Field::Data(path)
+            }
+        };
+        Ok(field)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// `:` — substring for text, equality for numbers
+    Contains,
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Contains => ":",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        matches!(self, Op::Gt | Op::Ge | Op::Lt | Op::Le)
+    }
+}
+
+/// The right-hand side of a comparison
+#[derive(Debug, Clone, PartialEq)]
+pub struct Literal {
+    pub text: String,
+    /// The value as a number, if it is one (`0x` prefixed values are hex)
+    pub number: Option<f64>,
+}
+
+impl Literal {
+    fn new(text: &str, field: &Field) -> Self {
+        let mut number = parse_number(text);
+        // Opcodes are written in hex with or without the prefix
+        if *field == Field::OpCode {
+            let hex = text
+                .strip_prefix("0x")
+                .or_else(|| text.strip_prefix("0X"))
+                .unwrap_or(text);
+            number = u32::from_str_radix(hex, 16).ok().map(f64::from).or(number);
+        }
+        Self {
+            text: text.to_string(),
+            number,
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).ok().map(|n| n as f64);
+    }
+    // Don't let `inf`, `nan` and the like through
+    if !text
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'))
+    {
+        return None;
+    }
+    text.parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub field: Field,
+    pub op: Op,
+    pub value: Literal,
+}
+
+/// A parsed query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Matches everything; the empty query
+    All,
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Comparison),
+    /// Free text matched against every part of the message
+    Text(String),
+}
+
+/// A query ready to be matched against messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    expr: Expr,
+}
+
+impl Query {
+    /// Parse a query, failing on malformed syntax or unknown fields
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: input.chars().count(),
+        };
+        let expr = if parser.tokens.is_empty() {
+            Expr::All
+        } else {
+            parser.or_expr()?
+        };
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(QueryError::new("Unexpected `)`", token.position));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Parse a query, treating the whole input as free text if it isn't valid
+    ///
+    /// Lets a search box keep working while a query is half typed; the error is returned
+    /// so it can be shown.
+    pub fn parse_or_text(input: &str) -> (Self, Option<QueryError>) {
+        match Self::parse(input) {
+            Ok(query) => (query, None),
+            Err(e) => (
+                Self {
+                    expr: Expr::Text(input.trim().to_string()),
+                },
+                Some(e),
+            ),
+        }
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Whether the query matches everything
+    pub fn is_empty(&self) -> bool {
+        self.expr == Expr::All
+    }
+
+    /// Whether `message` matches, with `ts` measured from `start_time`
+    pub fn matches(&self, message: &ParsedMessage, start_time: f64) -> bool {
+        eval(&self.expr, message, start_time)
+    }
+}
+
+fn eval(expr: &Expr, message: &ParsedMessage, start_time: f64) -> bool {
+    match expr {
+        Expr::All => true,
+        Expr::And(a, b) => eval(a, message, start_time) && eval(b, message, start_time),
+        Expr::Or(a, b) => eval(a, message, start_time) || eval(b, message, start_time),
+        Expr::Not(e) => !eval(e, message, start_time),
+        Expr::Compare(c) => compare(c, message, start_time),
+        Expr::Text(text) => matches_text(message, text),
+    }
+}
+
+/// The free-text search: id, opcode and direction as hex or decimal, type and data as text
+pub fn matches_text(message: &ParsedMessage, text: &str) -> bool {
+    let filters = parse_filter_string(text);
+    if filters.is_empty() {
+        return true;
+    }
+    if matches_any_filter(&filters, &message.id.to_string())
+        || matches_any_filter(&filters, &message.opcode)
+        || matches_any_filter(&filters, &message.direction)
+    {
+        return true;
+    }
+    let data_str = serde_json::to_string(&message.data).unwrap_or_default();
+    if matches_any_filter(&filters, &data_str) {
+        return true;
+    }
+    let text = text.to_lowercase();
+    message.message_type.to_lowercase().contains(&text)
+        || json_contains_string(&message.data, &text)
+}
+
+/// A field's value in the form comparisons work on
+struct Operand {
+    text: String,
+    number: Option<f64>,
+}
+
+impl Operand {
+    fn text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            number: None,
+        }
+    }
+
+    fn number(n: f64) -> Self {
+        Self {
+            text: n.to_string(),
+            number: Some(n),
+        }
+    }
+
+    fn from_json(value: &Value) -> Self {
+        match value {
+            Value::Number(n) => Self {
+                text: n.to_string(),
+                number: n.as_f64(),
+            },
+            // Ids are often serialized as hex strings
+            Value::String(s) => Self {
+                text: s.clone(),
+                number: parse_number(s),
+            },
+            Value::Bool(b) => Self::text(if *b { "true" } else { "false" }),
+            Value::Null => Self::text("null"),
+            Value::Array(_) | Value::Object(_) => Self::text(&value.to_string()),
+        }
+    }
+
+    fn matches(&self, op: Op, literal: &Literal) -> bool {
+        if let (Some(a), Some(b)) = (self.number, literal.number) {
+            return match op {
+                Op::Contains | Op::Eq => a == b,
+                Op::Ne => a != b,
+                Op::Gt => a > b,
+                Op::Ge => a >= b,
+                Op::Lt => a < b,
+                Op::Le => a <= b,
+            };
+        }
+        let text = self.text.to_lowercase();
+        let other = literal.text.to_lowercase();
+        match op {
+            Op::Contains => text.contains(&other),
+            Op::Eq => text == other,
+            Op::Ne => text != other,
+            // Ordering only makes sense for numbers
+            Op::Gt | Op::Ge | Op::Lt | Op::Le => false,
+        }
+    }
+}
+
+fn compare(c: &Comparison, message: &ParsedMessage, start_time: f64) -> bool {
+    let operand = match &c.field {
+        Field::Type => Operand::text(&message.message_type),
+        Field::Direction => Operand::text(&message.direction),
+        Field::Id => Operand::number(message.id as f64),
+        Field::OpCode => Operand {
+            text: message.opcode.clone(),
+            number: u32::from_str_radix(&message.opcode, 16).ok().map(f64::from),
+        },
+        Field::Time => Operand::number(message.timestamp - start_time),
+        Field::Data(path) if path.is_empty() => {
+            return json_contains_string(&message.data, &c.value.text);
+        }
+        Field::Data(path) => {
+            let operands = path
+                .select(&message.data)
+                .into_iter()
+                .map(Operand::from_json);
+            // `!=` holds when no value is equal, everything else when any value matches
+            return if c.op == Op::Ne {
+                operands.into_iter().all(|o| o.matches(Op::Ne, &c.value))
+            } else {
+                operands.into_iter().any(|o| o.matches(c.op, &c.value))
+            };
+        }
+    };
+    operand.matches(c.op, &c.value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    /// A term, and whether it was entirely quoted
+    Atom(String, bool),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let kind = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => {
+                i += 1;
+                TokenKind::LParen
+            }
+            ')' => {
+                i += 1;
+                TokenKind::RParen
+            }
+            '&' if chars.get(i + 1) == Some(&'&') => {
+                i += 2;
+                TokenKind::And
+            }
+            '|' if chars.get(i + 1) == Some(&'|') => {
+                i += 2;
+                TokenKind::Or
+            }
+            '!' if chars.get(i + 1) != Some(&'=') => {
+                i += 1;
+                TokenKind::Not
+            }
+            _ => {
+                let mut text = String::new();
+                let mut quoted_whole = c == '"';
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')')
+                {
+                    if chars[i] == '"' {
+                        let open = i;
+                        i += 1;
+                        while i < chars.len() && chars[i] != '"' {
+                            text.push(chars[i]);
+                            i += 1;
+                        }
+                        if i == chars.len() {
+                            return Err(QueryError::new("Unclosed quote", open));
+                        }
+                        i += 1;
+                    } else {
+                        quoted_whole = false;
+                        text.push(chars[i]);
+                        i += 1;
+                    }
+                }
+                match text.as_str() {
+                    "AND" if !quoted_whole => TokenKind::And,
+                    "OR" if !quoted_whole => TokenKind::Or,
+                    "NOT" if !quoted_whole => TokenKind::Not,
+                    _ => TokenKind::Atom(text, quoted_whole),
+                }
+            }
+        };
+        tokens.push(Token {
+            kind,
+            position: start,
+        });
+    }
+    Ok(tokens)
+}
+
+/// Turn an atom into a field comparison, or free text if it doesn't name a field
+fn parse_term(text: &str, quoted: bool, position: usize) -> Result<Expr, QueryError> {
+    if quoted {
+        return Ok(Expr::Text(text.to_string()));
+    }
+    let Some(op_start) = text.find([':', '=', '!', '<', '>']) else {
+        return Ok(Expr::Text(text.to_string()));
+    };
+    let name = &text[..op_start];
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']' | '*'));
+    if !is_identifier {
+        return Ok(Expr::Text(text.to_string()));
+    }
+    let field = Field::from_name(name).map_err(|e| QueryError::new(e, position))?;
+
+    let rest = &text[op_start..];
+    let (op, len) = [
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        (":", Op::Contains),
+        ("=", Op::Eq),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+    ]
+    .into_iter()
+    .find(|(symbol, _)| rest.starts_with(symbol))
+    .map(|(symbol, op)| (op, symbol.len()))
+    .ok_or_else(|| QueryError::new("Expected an operator", position + op_start))?;
+
+    let value = &rest[len..];
+    let value_position = position + op_start + len;
+    if value.is_empty() {
+        return Err(QueryError::new(
+            format!("Missing value after `{}`", op.symbol()),
+            value_position,
+        ));
+    }
+    if matches!(&field, Field::Data(path) if path.is_empty()) && op != Op::Contains {
+        return Err(QueryError::new(
+            format!("`data` needs a field path to use `{}`", op.symbol()),
+            position,
+        ));
+    }
+    let value = Literal::new(value, &field);
+    if op.is_ordering() && value.number.is_none() {
+        return Err(QueryError::new(
+            format!("`{}` needs a number", op.symbol()),
+            value_position,
+        ));
+    }
+    Ok(Expr::Compare(Comparison { field, op, value }))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Length of the input, for errors at the end
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and_expr()?;
+        while self.peek() == Some(&TokenKind::Or) {
+            self.pos += 1;
+            let rhs = self.and_expr()?;
+            expr = Expr::Or(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(TokenKind::And) => self.pos += 1,
+                // Adjacent terms are ANDed
+                Some(TokenKind::Atom(..) | TokenKind::Not | TokenKind::LParen) => {}
+                _ => break,
+            }
+            let rhs = self.unary()?;
+            expr = Expr::And(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if self.peek() == Some(&TokenKind::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(QueryError::new("Expected a term", self.end));
+        };
+        self.pos += 1;
+        match token.kind {
+            TokenKind::LParen => {
+                let expr = self.or_expr()?;
+                if self.peek() != Some(&TokenKind::RParen) {
+                    return Err(QueryError::new("Missing `)`", token.position));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            TokenKind::Atom(text, quoted) => parse_term(&text, quoted, token.position),
+            TokenKind::RParen => Err(QueryError::new("Unexpected `)`", token.position)),
+            TokenKind::And | TokenKind::Or | TokenKind::Not => {
+                Err(QueryError::new("Expected a term", token.position))
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use acprotocol::enums::PacketHeaderFlags;
 use serde::Serializer;
 
+use crate::packet_flags::format_packet_flags;
+
 /// Serialize f32 with proper rounding (2 decimal places for small numbers)
 /// Outputs integer format for whole numbers (1 instead of 1.0)
 pub fn serialize_f32<S>(value: &f32, serializer: S) -> Result<S::Ok, S::Error>
@@ -37,3 +40,14 @@ where
         serializer.serialize_f64(rounded)
     }
 }
+
+/// Serialize packet header flags using their short display names (e.g. "BlobFrag|Ack")
+pub fn serialize_packet_flags<S>(
+    flags: &PacketHeaderFlags,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&format_packet_flags(*flags))
+}
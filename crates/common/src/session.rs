@@ -0,0 +1,202 @@
+//! Saved analyses of a capture
+//!
+//! A session records what someone found in a capture: the messages and packets they
+//! marked, bookmarks with notes on messages and objects, and the filter they were looking
+//! through. It is written as JSON to an `.acsession` file that names the capture by the
+//! SHA-256 hash of its bytes instead of its path, so it can be shared and reopened against
+//! anyone's copy of the same file.
+
+use crate::Tab;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+/// File extension of session files
+pub const SESSION_EXTENSION: &str = "acsession";
+
+/// Version written to new session files; older files are upgraded and newer ones refused
+///
+/// Version 1 bookmarked a message with just a name, and kept notes on messages apart
+/// from the bookmarks.
+pub const SESSION_VERSION: u32 = 2;
+
+/// Hex SHA-256 hash of a capture file's bytes
+pub fn capture_hash(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Hashes a capture a piece at a time, giving the same hash as `capture_hash`
+#[derive(Default)]
+pub struct CaptureHasher(Sha256);
+
+impl CaptureHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub fn finish(self) -> String {
+        hex::encode(self.0.finalize())
+    }
+}
+
+/// An analysis of one capture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Session {
+    pub version: u32,
+    /// Hash of the capture the session is about, from `capture_hash`
+    pub capture_hash: String,
+    /// Name of the capture file when the session was saved, to help find it again
+    #[serde(default)]
+    pub capture_name: String,
+    /// IDs of marked messages
+    #[serde(default)]
+    pub marked_messages: Vec<usize>,
+    /// IDs of marked packets
+    #[serde(default)]
+    pub marked_packets: Vec<usize>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub filter: FilterState,
+}
+
+/// Color of new bookmarks, a warm yellow
+pub const DEFAULT_BOOKMARK_COLOR: [u8; 3] = [230, 180, 60];
+
+/// A labeled, colored note on a message or an object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Bookmark {
+    pub label: String,
+    pub target: BookmarkTarget,
+    /// sRGB color it's drawn in
+    #[serde(default = "default_color")]
+    pub color: [u8; 3],
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+/// What a bookmark is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BookmarkTarget {
+    /// The message with this ID
+    Message(usize),
+    /// The object with this ID
+    Object(u32),
+}
+
+fn default_color() -> [u8; 3] {
+    DEFAULT_BOOKMARK_COLOR
+}
+
+impl Bookmark {
+    /// A bookmark on `target` in the default color, without a note
+    pub fn new(target: BookmarkTarget, label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            target,
+            color: DEFAULT_BOOKMARK_COLOR,
+            note: String::new(),
+        }
+    }
+}
+
+/// What the capture was being looked at through
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct FilterState {
+    pub tab: Tab,
+    pub search_query: String,
+    /// Start and end of the time range selected on the messages scrubber, if narrowed
+    pub time_range: Option<(f64, f64)>,
+    /// ID of the selected message
+    pub selected_message: Option<usize>,
+}
+
+impl Session {
+    /// An empty session for the capture with `capture_hash`
+    pub fn new(capture_hash: impl Into<String>, capture_name: impl Into<String>) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            capture_hash: capture_hash.into(),
+            capture_name: capture_name.into(),
+            marked_messages: Vec::new(),
+            marked_packets: Vec::new(),
+            bookmarks: Vec::new(),
+            filter: FilterState::default(),
+        }
+    }
+
+    /// Read a session from the JSON of an `.acsession` file
+    pub fn from_json(text: &str) -> Result<Self> {
+        let mut session: Value = serde_json::from_str(text).context("Invalid session file")?;
+        let version = session
+            .get("Version")
+            .and_then(Value::as_u64)
+            .context("Invalid session file: missing `Version`")?;
+        if version > SESSION_VERSION as u64 {
+            bail!(
+                "Session file version {version} is newer than this version supports ({SESSION_VERSION})"
+            );
+        }
+        if version < 2 {
+            upgrade_v1(&mut session);
+        }
+        serde_json::from_value(session).context("Invalid session file")
+    }
+
+    /// The session as the JSON of an `.acsession` file
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to write session")
+    }
+
+    /// Whether the session is about the capture with `capture_hash`
+    pub fn is_for(&self, capture_hash: &str) -> bool {
+        self.capture_hash.eq_ignore_ascii_case(capture_hash)
+    }
+}
+
+/// Rewrite a version 1 session in the current format
+///
+/// Bookmarks were `{ Name, MessageId }`, and `Notes` mapped message IDs to notes. Each
+/// note moves onto the bookmark of its message, which is made if there wasn't one.
+fn upgrade_v1(session: &mut Value) {
+    let Some(fields) = session.as_object_mut() else {
+        return;
+    };
+
+    let mut bookmarks: Vec<Value> = match fields.remove("Bookmarks") {
+        Some(Value::Array(bookmarks)) => bookmarks
+            .iter()
+            .filter_map(|b| {
+                let name = b.get("Name")?.as_str()?;
+                let message_id = b.get("MessageId")?.as_u64()?;
+                Some(json!({ "Label": name, "Target": { "Message": message_id } }))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    if let Some(Value::Object(notes)) = fields.remove("Notes") {
+        for (message_id, note) in notes {
+            let (Ok(message_id), Some(note)) = (message_id.parse::<u64>(), note.as_str()) else {
+                continue;
+            };
+            match bookmarks
+                .iter_mut()
+                .find(|b| b["Target"]["Message"] == message_id)
+            {
+                Some(bookmark) => bookmark["Note"] = note.into(),
+                None => bookmarks.push(json!({
+                    "Label": format!("Message #{message_id}"),
+                    "Target": { "Message": message_id },
+                    "Note": note,
+                })),
+            }
+        }
+    }
+
+    fields.insert("Bookmarks".to_string(), bookmarks.into());
+    fields.insert("Version".to_string(), SESSION_VERSION.into());
+}
@@ -0,0 +1,517 @@
+//! Incremental parsing of a capture into packets, messages and weenie updates
+//!
+//! `PacketStream` reads one capture record at a time and yields what was decoded from it
+//! as `ParseEvent`s, so captures of any size can be processed without holding the file,
+//! or everything decoded from it, in memory. Fragments are only kept for messages seen in
+//! the last `FRAGMENT_TIMEOUT` seconds of the capture.
+
+use crate::capture::{CaptureReader, CaptureRecord};
+use crate::diagnostics::{DiagnosticKind, ParseDiagnostic};
+use crate::fragment::{self, FragmentReassembler, MessageKey};
+use crate::weenie::{WeenieDatabase, WeenieUpdate};
+use crate::weenie_extractor::WeenieExtractor;
+use crate::{FragmentInfo, PacketHeaderFlags, ParsedPacket, messages, transport};
+use acprotocol::network::packet_parser::FragmentAssembler;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Read;
+
+/// Seconds of capture time a message is tracked for after its last fragment
+///
+/// A message still missing fragments by then is reported incomplete, and a completed
+/// one stops being recognised in retransmissions.
+pub const FRAGMENT_TIMEOUT: f64 = 60.0;
+
+/// Seconds of capture time between sweeps for messages past `FRAGMENT_TIMEOUT`
+const EXPIRE_INTERVAL: f64 = 10.0;
+
+/// Something decoded from the capture
+#[derive(Debug, Clone)]
+pub enum ParseEvent {
+    /// A datagram, with the messages it completed
+    Packet(ParsedPacket),
+    /// A decoded game message; it follows the `Packet` that completed it
+    Message(messages::ParsedMessage),
+    /// A weenie update extracted from the preceding `Message`
+    WeenieUpdate(WeenieUpdate),
+    /// Fragments carried by earlier packets whose message has now completed
+    FragmentsCompleted(FragmentCompletion),
+    /// A packet or message that couldn't be decoded
+    Diagnostic(ParseDiagnostic),
+}
+
+/// Fragments of a message that completed after the packets carrying them were yielded
+#[derive(Debug, Clone)]
+pub struct FragmentCompletion {
+    /// Id of the decoded message, if acprotocol decoded it
+    pub message_id: Option<usize>,
+    /// Each fragment as (packet index, index within `ParsedPacket::fragments`)
+    pub fragments: Vec<(usize, usize)>,
+}
+
+impl FragmentCompletion {
+    /// Mark the fragments complete in packets collected from the stream
+    pub fn apply(&self, packets: &mut [ParsedPacket]) {
+        self.apply_from(0, packets);
+    }
+
+    /// Mark the fragments complete in packets collected from the stream from packet
+    /// index `first` on
+    pub fn apply_from(&self, first: usize, packets: &mut [ParsedPacket]) {
+        for &(packet_idx, index) in &self.fragments {
+            if let Some(info) = packet_idx
+                .checked_sub(first)
+                .and_then(|i| packets.get_mut(i))
+                .and_then(|p| p.fragments.get_mut(index))
+            {
+                info.complete = true;
+                info.message_id = self.message_id;
+            }
+        }
+    }
+}
+
+/// Everything decoded from a capture, built up from a stream's events
+#[derive(Debug, Clone, Default)]
+pub struct ParsedCapture {
+    pub packets: Vec<ParsedPacket>,
+    pub messages: Vec<messages::ParsedMessage>,
+    pub weenie_db: WeenieDatabase,
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+impl ParsedCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event from the stream
+    pub fn push(&mut self, event: ParseEvent) {
+        match event {
+            ParseEvent::Packet(packet) => self.packets.push(packet),
+            ParseEvent::Message(message) => self.messages.push(message),
+            ParseEvent::WeenieUpdate(update) => self.weenie_db.add_or_update(update),
+            ParseEvent::FragmentsCompleted(completion) => completion.apply(&mut self.packets),
+            ParseEvent::Diagnostic(diagnostic) => self.diagnostics.push(diagnostic),
+        }
+    }
+}
+
+/// A fragment of a message that hasn't completed yet
+struct PendingFragment {
+    record_index: usize,
+    packet_index: usize,
+    fragment_index: usize,
+    timestamp: f64,
+    index: u16,
+    count: u16,
+    offset: usize,
+}
+
+/// Iterator of `ParseEvent`s decoded from a capture
+pub struct PacketStream<R> {
+    records: CaptureReader<R>,
+    assembler: FragmentAssembler,
+    reassembler: FragmentReassembler,
+    /// Fragments of each pending message, so they can be marked once it completes
+    pending: HashMap<MessageKey, Vec<PendingFragment>>,
+    /// Decoded message id of each recently completed message, for late retransmissions,
+    /// and when it completed
+    completed_messages: HashMap<MessageKey, (Option<usize>, f64)>,
+    /// Link types already reported as unsupported, so each is reported once
+    unsupported_link_types: HashSet<u32>,
+    /// Port pairs that have carried AC packets, whose bad headers are worth reporting
+    ac_flows: HashSet<(u16, u16)>,
+    /// Remembers the logged-in character for updates that don't name their object
+    weenie_extractor: WeenieExtractor,
+    events: VecDeque<ParseEvent>,
+    /// Records read so far, including those that didn't become packets
+    record_index: usize,
+    packet_id: usize,
+    message_id: usize,
+    last_timestamp: f64,
+    /// Capture time of the next sweep for expired messages
+    next_expiry: f64,
+    finished: bool,
+}
+
+impl<R: Read> PacketStream<R> {
+    pub(crate) fn new(records: CaptureReader<R>, weenie_extractor: WeenieExtractor) -> Self {
+        Self {
+            records,
+            assembler: FragmentAssembler::new(),
+            reassembler: FragmentReassembler::new(),
+            pending: HashMap::new(),
+            completed_messages: HashMap::new(),
+            unsupported_link_types: HashSet::new(),
+            ac_flows: HashSet::new(),
+            weenie_extractor,
+            events: VecDeque::new(),
+            record_index: 0,
+            packet_id: 0,
+            message_id: 0,
+            last_timestamp: 0.0,
+            next_expiry: f64::NEG_INFINITY,
+            finished: false,
+        }
+    }
+
+    /// Number of bytes of the capture read so far
+    pub fn bytes_read(&self) -> u64 {
+        self.records.bytes_read()
+    }
+
+    /// Number of messages whose fragments are being kept, pending or recently completed
+    pub fn tracked_messages(&self) -> usize {
+        self.pending.len() + self.completed_messages.len() + self.reassembler.len()
+    }
+
+    /// Index of the earliest packet carrying fragments of a message that hasn't completed
+    ///
+    /// Once a `Packet` event has been taken, no later `FragmentsCompleted` event changes
+    /// a packet before this one, or any packet if there is none.
+    pub fn oldest_pending_packet(&self) -> Option<usize> {
+        self.pending
+            .values()
+            .flatten()
+            .map(|f| f.packet_index)
+            .min()
+    }
+
+    fn diagnostic(&mut self, diagnostic: ParseDiagnostic) {
+        self.events.push_back(ParseEvent::Diagnostic(diagnostic));
+    }
+
+    /// Stop tracking messages whose last fragment is more than `FRAGMENT_TIMEOUT` before
+    /// `now`, reporting those that never completed
+    fn expire(&mut self, now: f64) {
+        if now < self.next_expiry {
+            return;
+        }
+        self.next_expiry = now + EXPIRE_INTERVAL;
+        let cutoff = now - FRAGMENT_TIMEOUT;
+
+        let expired: Vec<MessageKey> = self
+            .pending
+            .iter()
+            .filter(|(_, fragments)| fragments.iter().all(|f| f.timestamp < cutoff))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut incomplete = Vec::with_capacity(expired.len());
+        for key in expired {
+            self.reassembler.forget(&key);
+            if let Some(fragments) = self.pending.remove(&key) {
+                incomplete.push((key, fragments));
+            }
+        }
+        self.report_incomplete(incomplete);
+
+        let reassembler = &mut self.reassembler;
+        self.completed_messages.retain(|key, (_, completed_at)| {
+            let keep = *completed_at >= cutoff;
+            if !keep {
+                reassembler.forget(key);
+            }
+            keep
+        });
+    }
+
+    /// Decode one capture record, queueing the events it produces
+    fn process_record(&mut self, record: CaptureRecord) {
+        let timestamp = record.timestamp();
+        self.last_timestamp = timestamp;
+        self.expire(timestamp);
+        let record_index = self.record_index;
+        self.record_index += 1;
+        let packet_id = self.packet_id;
+        // Where a problem in a record that becomes a packet was found
+        let in_packet = |diagnostic: ParseDiagnostic| {
+            diagnostic
+                .at_record(record_index, timestamp)
+                .at_packet(packet_id, timestamp)
+        };
+
+        let Some(frame) = transport::to_ethernet(record.link_type, &record.data) else {
+            if self.unsupported_link_types.insert(record.link_type) {
+                self.diagnostic(
+                    ParseDiagnostic::new(
+                        DiagnosticKind::UnsupportedLinkType,
+                        format!(
+                            "Frames with link type {} can't be decoded and were skipped",
+                            record.link_type
+                        ),
+                    )
+                    .at_record(record_index, timestamp),
+                );
+            }
+            return;
+        };
+
+        // Decode the AC transport header ourselves; anything that isn't an AC
+        // datagram (non-UDP traffic, other UDP traffic like DNS) is skipped
+        let Some(datagram) = transport::extract_udp(&frame) else {
+            return;
+        };
+        let header = match transport::PacketHeader::read_datagram(datagram.payload) {
+            Ok(header) => header,
+            Err(e) => {
+                // Only a flow already carrying AC packets is known to be AC traffic. The
+                // record is dropped, so there's no packet to point at
+                if self.ac_flows.contains(&datagram.flow()) {
+                    self.diagnostic(
+                        ParseDiagnostic::new(DiagnosticKind::InvalidHeader, e.to_string())
+                            .at_record(record_index, timestamp)
+                            .with_offset(0),
+                    );
+                }
+                return;
+            }
+        };
+        self.ac_flows.insert(datagram.flow());
+        let optional = transport::OptionalHeaders::read(
+            header.flags,
+            &datagram.payload[transport::PACKET_HEADER_SIZE..],
+        )
+        .unwrap_or_else(|e| {
+            self.diagnostic(
+                in_packet(ParseDiagnostic::new(
+                    DiagnosticKind::InvalidOptionalHeaders,
+                    e.to_string(),
+                ))
+                .with_offset(transport::PACKET_HEADER_SIZE),
+            );
+            Default::default()
+        });
+        let direction = datagram.direction();
+        // The datagram's place in the file; a rewritten frame only differs from the record
+        // in its link-layer header, so it lines up with the end of the record
+        let payload_start =
+            record.offset as usize + datagram.payload_offset + record.data.len() - frame.len();
+        let payload_range = payload_start..payload_start + datagram.payload.len();
+
+        // Reassemble the blob fragments ourselves to keep the raw message bytes
+        let body_start = transport::PACKET_HEADER_SIZE + optional.body_offset;
+        let fragments = if header.flags.contains(PacketHeaderFlags::BLOB_FRAGMENTS) {
+            fragment::read_fragments(&datagram.payload[body_start..], body_start).unwrap_or_else(
+                |e| {
+                    self.diagnostic(
+                        in_packet(ParseDiagnostic::new(
+                            DiagnosticKind::InvalidFragment,
+                            e.to_string(),
+                        ))
+                        .with_offset(body_start),
+                    );
+                    Vec::new()
+                },
+            )
+        } else {
+            Vec::new()
+        };
+        let mut fragment_infos = Vec::with_capacity(fragments.len());
+        let mut completed = Vec::new();
+        for (index, f) in fragments.iter().enumerate() {
+            let key = (direction.to_string(), f.sequence, f.id);
+            let done = self.reassembler.add(direction, f);
+            let mut info = FragmentInfo::new(f, self.reassembler.received(direction, f));
+            if let Some((message_id, _)) = self.completed_messages.get(&key) {
+                info.complete = true;
+                info.message_id = *message_id;
+            } else {
+                self.pending.entry(key).or_default().push(PendingFragment {
+                    record_index,
+                    packet_index: packet_id,
+                    fragment_index: index,
+                    timestamp,
+                    index: f.index,
+                    count: f.count,
+                    offset: f.offset,
+                });
+            }
+            fragment_infos.push(info);
+            completed.extend(done);
+        }
+        // Messages completed by this packet, and the id of the decoded message for each
+        let mut completed_ids: Vec<(MessageKey, Option<usize>)> = completed
+            .iter()
+            .map(|c| ((direction.to_string(), c.sequence, c.id), None))
+            .collect();
+        let mut completed: Vec<(usize, fragment::CompletedMessage)> =
+            completed.into_iter().enumerate().collect();
+
+        // Use FragmentAssembler to reassemble and decode the game messages
+        let mut parsed_messages = Vec::new();
+        let mut decode_error = None;
+        match self.assembler.parse_packet_payload(&frame) {
+            Ok(messages) => {
+                for msg in messages {
+                    // Serialize the message to JSON
+                    let data = serde_json::to_value(&msg).unwrap_or_else(|_| serde_json::json!({}));
+
+                    // Pair the decoded message with the bytes we reassembled for it
+                    let raw_bytes = completed
+                        .iter()
+                        .position(|(_, c)| c.opcode() == Some(msg.opcode))
+                        .map(|i| {
+                            let (slot, c) = completed.remove(i);
+                            completed_ids[slot].1 = Some(self.message_id);
+                            c.data
+                        })
+                        .unwrap_or_default();
+
+                    parsed_messages.push(messages::ParsedMessage {
+                        id: self.message_id,
+                        message_type: msg.message_type.clone(),
+                        data,
+                        // The direction is already a string in the message
+                        direction: msg.direction.clone(),
+                        opcode: format!("{:04X}", msg.opcode),
+                        timestamp,
+                        raw_bytes,
+                        field_offsets: None,
+                    });
+                    self.message_id += 1;
+                }
+            }
+            Err(e) => decode_error = Some(e.to_string()),
+        }
+
+        // Anything we reassembled that acprotocol didn't decode
+        for (_, message) in &completed {
+            let opcode = message.opcode().unwrap_or_default();
+            let kind = if messages::is_known_opcode(opcode) {
+                DiagnosticKind::DecodeFailed
+            } else {
+                DiagnosticKind::UnknownOpcode
+            };
+            let text = decode_error.clone().unwrap_or_else(|| {
+                format!(
+                    "{} byte message was reassembled but not decoded",
+                    message.data.len()
+                )
+            });
+            self.diagnostic(
+                in_packet(ParseDiagnostic::new(kind, text))
+                    .with_offset(body_start)
+                    .with_opcode(opcode),
+            );
+        }
+        if completed.is_empty()
+            && let Some(text) = decode_error
+        {
+            self.diagnostic(
+                in_packet(ParseDiagnostic::new(DiagnosticKind::DecodeFailed, text))
+                    .with_offset(body_start),
+            );
+        }
+
+        // Mark every fragment of the messages this packet completed; those in this
+        // packet directly, those in packets already yielded with a completion event
+        for (key, message_id) in completed_ids {
+            let mut earlier = Vec::new();
+            for pending in self.pending.remove(&key).unwrap_or_default() {
+                if pending.packet_index == packet_id {
+                    let info = &mut fragment_infos[pending.fragment_index];
+                    info.complete = true;
+                    info.message_id = message_id;
+                } else {
+                    earlier.push((pending.packet_index, pending.fragment_index));
+                }
+            }
+            if !earlier.is_empty() {
+                self.events
+                    .push_back(ParseEvent::FragmentsCompleted(FragmentCompletion {
+                        message_id,
+                        fragments: earlier,
+                    }));
+            }
+            self.completed_messages.insert(key, (message_id, timestamp));
+        }
+
+        // Every datagram gets a ParsedPacket, including acks, time syncs and echoes
+        // that carry no game messages
+        self.events.push_back(ParseEvent::Packet(ParsedPacket {
+            header,
+            optional,
+            direction: direction.to_string(),
+            messages: parsed_messages.iter().map(|m| m.data.clone()).collect(),
+            fragments: fragment_infos,
+            id: packet_id,
+            timestamp,
+            record_index,
+            payload_range,
+        }));
+        self.packet_id += 1;
+
+        for message in parsed_messages {
+            let updates = self.weenie_extractor.extract(&message);
+            self.events.push_back(ParseEvent::Message(message));
+            self.events
+                .extend(updates.into_iter().map(ParseEvent::WeenieUpdate));
+        }
+    }
+
+    /// Report messages still waiting for fragments when the capture ended
+    fn finish(&mut self) {
+        let incomplete = self.pending.drain().collect();
+        self.report_incomplete(incomplete);
+    }
+
+    /// Report messages that stopped waiting for fragments, in capture order
+    fn report_incomplete(&mut self, mut incomplete: Vec<(MessageKey, Vec<PendingFragment>)>) {
+        incomplete.sort_by_key(|(_, fragments)| {
+            fragments
+                .first()
+                .map(|f| (f.packet_index, f.fragment_index))
+        });
+
+        for ((_, sequence, id), fragments) in incomplete {
+            let Some(first) = fragments.first() else {
+                continue;
+            };
+            let received = fragments
+                .iter()
+                .map(|f| f.index)
+                .collect::<HashSet<_>>()
+                .len();
+            let diagnostic = ParseDiagnostic::new(
+                DiagnosticKind::IncompleteMessage,
+                format!(
+                    "Received {received}/{} fragments of message {sequence} (id 0x{id:08X})",
+                    first.count
+                ),
+            )
+            .at_record(first.record_index, first.timestamp)
+            .at_packet(first.packet_index, first.timestamp)
+            .with_offset(first.offset);
+            self.diagnostic(diagnostic);
+        }
+    }
+}
+
+impl<R: Read> Iterator for PacketStream<R> {
+    type Item = ParseEvent;
+
+    fn next(&mut self) -> Option<ParseEvent> {
+        while self.events.is_empty() && !self.finished {
+            match self.records.next_record() {
+                Ok(Some(record)) => self.process_record(record),
+                Ok(None) => {
+                    self.finished = true;
+                    self.finish();
+                }
+                Err(e) => {
+                    // Keep everything read so far from a truncated capture; the
+                    // record that couldn't be read never becomes a packet
+                    self.finished = true;
+                    let diagnostic = ParseDiagnostic::new(
+                        DiagnosticKind::TruncatedCapture,
+                        format!("Failed to read packet: {e:#}"),
+                    )
+                    .at_record(self.record_index, self.last_timestamp);
+                    self.diagnostic(diagnostic);
+                    self.finish();
+                }
+            }
+        }
+        self.events.pop_front()
+    }
+}
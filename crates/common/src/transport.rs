@@ -0,0 +1,347 @@
+//! AC transport layer decoding
+//!
+//! Every AC UDP datagram starts with a fixed 20-byte packet header, followed by a set of
+//! optional headers selected by the header flags, followed by any blob fragments. This
+//! module strips the link/IP/UDP layers off a captured frame and decodes those headers so
+//! the viewer can show the real wire traffic, including packets that carry no messages.
+
+use crate::capture::{
+    LINKTYPE_ETHERNET, LINKTYPE_IPV4, LINKTYPE_LINUX_SLL, LINKTYPE_LINUX_SLL2, LINKTYPE_NULL,
+    LINKTYPE_RAW,
+};
+use acprotocol::enums::PacketHeaderFlags;
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// Size of the fixed AC packet header in bytes
+pub const PACKET_HEADER_SIZE: usize = 20;
+
+/// UDP ports used by retail AC world and login servers
+const SERVER_PORTS: std::ops::RangeInclusive<u16> = 9000..=9013;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+/// BSD `AF_INET`, the same on every platform
+const AF_INET: u32 = 2;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// A UDP datagram extracted from a captured Ethernet frame
+#[derive(Debug, Clone, Copy)]
+pub struct UdpDatagram<'a> {
+    pub src_port: u16,
+    pub dst_port: u16,
+    /// Offset of the payload within the frame
+    pub payload_offset: usize,
+    pub payload: &'a [u8],
+}
+
+impl UdpDatagram<'_> {
+    /// Direction of the datagram ("Send" for client-to-server, "Recv" otherwise),
+    /// using the same strings as `ParsedMessage::direction`
+    ///
+    /// The server end is a retail server port if either end is one, and otherwise the
+    /// lower port, since clients send from ephemeral ports.
+    pub fn direction(&self) -> &'static str {
+        let to_server = match (
+            SERVER_PORTS.contains(&self.src_port),
+            SERVER_PORTS.contains(&self.dst_port),
+        ) {
+            (false, true) => true,
+            (true, _) => false,
+            (false, false) => self.dst_port < self.src_port,
+        };
+        if to_server { "Send" } else { "Recv" }
+    }
+
+    /// The pair of ports the datagram travels between, the same in either direction
+    pub fn flow(&self) -> (u16, u16) {
+        (
+            self.src_port.min(self.dst_port),
+            self.src_port.max(self.dst_port),
+        )
+    }
+}
+
+/// Rewrite a frame's link-layer header as an Ethernet header
+///
+/// Both `extract_udp` and acprotocol only read Ethernet frames, so frames from Linux
+/// cooked, raw IP and loopback captures are given an Ethernet header with the same
+/// ethertype. Returns `None` for link types that can't be rewritten.
+pub fn to_ethernet(link_type: u32, frame: &[u8]) -> Option<Cow<'_, [u8]>> {
+    let be16 = |bytes: &[u8]| u16::from_be_bytes([bytes[0], bytes[1]]);
+    let (ethertype, payload) = match link_type {
+        LINKTYPE_ETHERNET => return Some(Cow::Borrowed(frame)),
+        LINKTYPE_LINUX_SLL => (be16(frame.get(14..16)?), frame.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => (be16(frame.get(0..2)?), frame.get(20..)?),
+        LINKTYPE_RAW | LINKTYPE_IPV4 => match frame.first()? >> 4 {
+            4 => (ETHERTYPE_IPV4, frame),
+            6 => (ETHERTYPE_IPV6, frame),
+            _ => return None,
+        },
+        LINKTYPE_NULL => {
+            let family: [u8; 4] = frame.get(0..4)?.try_into().ok()?;
+            if u32::from_le_bytes(family) != AF_INET && u32::from_be_bytes(family) != AF_INET {
+                return None;
+            }
+            (ETHERTYPE_IPV4, frame.get(4..)?)
+        }
+        _ => return None,
+    };
+
+    let mut ethernet = Vec::with_capacity(14 + payload.len());
+    ethernet.extend_from_slice(&[0u8; 12]);
+    ethernet.extend_from_slice(&ethertype.to_be_bytes());
+    ethernet.extend_from_slice(payload);
+    Some(Cow::Owned(ethernet))
+}
+
+/// Extract the UDP datagram from an Ethernet/IPv4 frame, or `None` for anything else
+pub fn extract_udp(frame: &[u8]) -> Option<UdpDatagram<'_>> {
+    let mut offset = 12;
+    let mut ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+    offset += 2;
+
+    // Skip any 802.1Q VLAN tags
+    while ethertype == ETHERTYPE_VLAN {
+        offset += 2;
+        ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+        offset += 2;
+    }
+
+    if ethertype != ETHERTYPE_IPV4 {
+        return None;
+    }
+
+    let ip = frame.get(offset..)?;
+    let ihl = (*ip.first()? & 0x0F) as usize * 4;
+    if ihl < 20 || *ip.get(9)? != IP_PROTOCOL_UDP {
+        return None;
+    }
+
+    let udp_offset = offset + ihl;
+    let udp = ip.get(ihl..)?;
+    if udp.len() < 8 {
+        return None;
+    }
+    let src_port = u16::from_be_bytes([udp[0], udp[1]]);
+    let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+    let udp_len = u16::from_be_bytes([udp[4], udp[5]]) as usize;
+
+    // Trust the UDP length field, but never read past the captured bytes
+    let end = udp_len.clamp(8, udp.len());
+    Some(UdpDatagram {
+        src_port,
+        dst_port,
+        payload_offset: udp_offset + 8,
+        payload: &udp[8..end],
+    })
+}
+
+/// The fixed AC packet header at the start of every datagram
+#[derive(Debug, Clone, Serialize)]
+pub struct PacketHeader {
+    #[serde(rename = "Sequence")]
+    pub sequence: u32,
+    #[serde(
+        rename = "Flags",
+        serialize_with = "crate::serialization::serialize_packet_flags"
+    )]
+    pub flags: PacketHeaderFlags,
+    #[serde(rename = "Checksum")]
+    pub checksum: u32,
+    #[serde(rename = "RecipientId")]
+    pub recipient_id: u16,
+    #[serde(rename = "Time")]
+    pub time: u16,
+    /// Size of the data following this header
+    #[serde(rename = "Size")]
+    pub size: u16,
+    #[serde(rename = "Iteration")]
+    pub iteration: u16,
+}
+
+impl PacketHeader {
+    /// Decode the packet header from the start of a UDP payload
+    pub fn read(payload: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(payload);
+        Ok(Self {
+            sequence: reader.u32()?,
+            flags: PacketHeaderFlags::from_bits_retain(reader.u32()?),
+            checksum: reader.u32()?,
+            recipient_id: reader.u16()?,
+            time: reader.u16()?,
+            size: reader.u16()?,
+            iteration: reader.u16()?,
+        })
+    }
+
+    /// Decode the packet header of a whole datagram, checking it describes the datagram
+    ///
+    /// The header's size is that of the data after it, which other UDP traffic that
+    /// happens to be long enough almost never matches.
+    pub fn read_datagram(payload: &[u8]) -> Result<Self> {
+        let header = Self::read(payload)?;
+        let data_len = payload.len() - PACKET_HEADER_SIZE;
+        if header.size as usize != data_len {
+            bail!(
+                "Header gives {} bytes of data, but {data_len} follow it",
+                header.size
+            );
+        }
+        Ok(header)
+    }
+}
+
+/// Values carried by the optional headers that follow the packet header
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OptionalHeaders {
+    #[serde(rename = "AckSequence", skip_serializing_if = "Option::is_none")]
+    pub ack_sequence: Option<u32>,
+    #[serde(rename = "RequestRetransmit", skip_serializing_if = "Vec::is_empty")]
+    pub request_retransmit: Vec<u32>,
+    #[serde(rename = "RejectRetransmit", skip_serializing_if = "Vec::is_empty")]
+    pub reject_retransmit: Vec<u32>,
+    #[serde(rename = "TimeSync", skip_serializing_if = "Option::is_none")]
+    pub time_sync: Option<f64>,
+    #[serde(rename = "EchoRequest", skip_serializing_if = "Option::is_none")]
+    pub echo_request: Option<f32>,
+    #[serde(rename = "EchoResponse", skip_serializing_if = "Option::is_none")]
+    pub echo_response: Option<(f32, f32)>,
+    #[serde(rename = "FlowBytes", skip_serializing_if = "Option::is_none")]
+    pub flow_bytes: Option<u32>,
+    #[serde(rename = "FlowInterval", skip_serializing_if = "Option::is_none")]
+    pub flow_interval: Option<u16>,
+    /// Offset of the first byte after the optional headers, relative to the end of the
+    /// packet header (i.e. where blob fragments begin)
+    #[serde(skip)]
+    pub body_offset: usize,
+}
+
+impl OptionalHeaders {
+    /// Decode the optional headers selected by `flags` from the data after the packet header
+    pub fn read(flags: PacketHeaderFlags, data: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(data);
+        let mut optional = OptionalHeaders::default();
+
+        if flags.contains(PacketHeaderFlags::SERVER_SWITCH) {
+            reader.skip(8)?;
+        }
+        if flags.contains(PacketHeaderFlags::REFERRAL) {
+            bail!("Referral headers are not supported");
+        }
+        if flags.contains(PacketHeaderFlags::REQUEST_RETRANSMIT) {
+            let count = reader.u32()?;
+            for _ in 0..count {
+                optional.request_retransmit.push(reader.u32()?);
+            }
+        }
+        if flags.contains(PacketHeaderFlags::REJECT_RETRANSMIT) {
+            let count = reader.u32()?;
+            for _ in 0..count {
+                optional.reject_retransmit.push(reader.u32()?);
+            }
+        }
+        if flags.contains(PacketHeaderFlags::ACK_SEQUENCE) {
+            optional.ack_sequence = Some(reader.u32()?);
+        }
+        if flags.contains(PacketHeaderFlags::LOGIN_REQUEST) {
+            // The login request consumes the remainder of the packet
+            reader.skip(reader.remaining())?;
+        }
+        if flags.contains(PacketHeaderFlags::WORLD_LOGIN_REQUEST) {
+            reader.skip(8)?;
+        }
+        if flags.contains(PacketHeaderFlags::CONNECT_REQUEST) {
+            reader.skip(32)?;
+        }
+        if flags.contains(PacketHeaderFlags::CONNECT_RESPONSE) {
+            reader.skip(8)?;
+        }
+        if flags.contains(PacketHeaderFlags::NET_ERROR) {
+            reader.skip(8)?;
+        }
+        if flags.contains(PacketHeaderFlags::NET_ERROR_DISCONNECT) {
+            reader.skip(8)?;
+        }
+        if flags.contains(PacketHeaderFlags::CICMDCOMMAND) {
+            reader.skip(8)?;
+        }
+        if flags.contains(PacketHeaderFlags::TIME_SYNC) {
+            optional.time_sync = Some(reader.f64()?);
+        }
+        if flags.contains(PacketHeaderFlags::ECHO_REQUEST) {
+            optional.echo_request = Some(reader.f32()?);
+        }
+        if flags.contains(PacketHeaderFlags::ECHO_RESPONSE) {
+            optional.echo_response = Some((reader.f32()?, reader.f32()?));
+        }
+        if flags.contains(PacketHeaderFlags::FLOW) {
+            optional.flow_bytes = Some(reader.u32()?);
+            optional.flow_interval = Some(reader.u16()?);
+        }
+
+        optional.body_offset = reader.position();
+        Ok(optional)
+    }
+}
+
+/// Minimal little-endian reader over a byte slice
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.remaining() < len {
+            bail!(
+                "Unexpected end of data at offset {} (needed {} bytes, {} remaining)",
+                self.pos,
+                len,
+                self.remaining()
+            );
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn skip(&mut self, len: usize) -> Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64> {
+        let b = self.bytes(8)?;
+        Ok(f64::from_le_bytes([
+            b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+        ]))
+    }
+}
@@ -0,0 +1,272 @@
+//! Names for enum, flag and spell values that messages carry as plain numbers
+//!
+//! Fields are recognized by name, and for property keys by the message or property table
+//! they're in, and decoded with acprotocol's enums: `ItemType: 0x00000002` reads as
+//! `Armor` and a `DamageType` of `0x00000003` as `Slash|Pierce`, and packet header
+//! `Flags` read with the short names the packet list uses, e.g. `Retrans|Ack`. Spell
+//! names come from a spell table, since the protocol doesn't define them.
+
+use crate::packet_flags::format_packet_flags;
+use acprotocol::enums::{
+    DamageType, ItemType, PacketHeaderFlags, PropertyBool, PropertyDataId, PropertyFloat,
+    PropertyInstanceId, PropertyInt, PropertyInt64, PropertyString,
+};
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+
+/// How a field's value is decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    ItemType,
+    DamageType,
+    /// Packet header flags
+    PacketFlags,
+    /// The key of a property of this kind
+    Property(crate::weenie::PropertyKind),
+    Spell,
+}
+
+impl ValueKind {
+    /// What the field `key` at `path` holds, if it's a value we can name
+    ///
+    /// `path` is the dotted path of the field within the message data, e.g.
+    /// `S2C.QualitiesUpdateInt.Key`.
+    pub fn of_field(path: &str, key: &str) -> Option<ValueKind> {
+        use crate::weenie::PropertyKind;
+
+        match key {
+            "ItemType" | "ItemTypes" | "TargetType" | "HookItemTypes" => Some(ValueKind::ItemType),
+            "DamageType" | "DamageTypes" => Some(ValueKind::DamageType),
+            "Flags" if path.ends_with("Header.Flags") => Some(ValueKind::PacketFlags),
+            "SpellId" | "spell_id" | "Spell" => Some(ValueKind::Spell),
+            "Key" | "key" => {
+                // Longer names first, so `UpdateInt64` isn't taken for `UpdateInt`
+                let kind = [
+                    ("UpdateInt64", PropertyKind::Int64),
+                    ("UpdateInstanceId", PropertyKind::InstanceId),
+                    ("UpdateInt", PropertyKind::Int),
+                    ("UpdateBool", PropertyKind::Bool),
+                    ("UpdateFloat", PropertyKind::Float),
+                    ("UpdateString", PropertyKind::String),
+                    ("UpdateDataId", PropertyKind::DataId),
+                ]
+                .into_iter()
+                .find(|(message, _)| path.contains(message))?
+                .1;
+                Some(ValueKind::Property(kind))
+            }
+            _ => None,
+        }
+    }
+
+    /// What the keys of the map at `path` hold, if they're values we can name
+    ///
+    /// Property tables such as `S2C.Item_SetAppraiseInfo.IntProperties.Table` are keyed
+    /// by properties of their kind.
+    pub fn of_map_key(path: &str) -> Option<ValueKind> {
+        use crate::weenie::PropertyKind;
+
+        let table = path.strip_suffix(".Table")?;
+        let name = table.rsplit('.').next().unwrap_or(table);
+        let kind = match name {
+            "IntProperties" => PropertyKind::Int,
+            "Int64Properties" => PropertyKind::Int64,
+            "BoolProperties" => PropertyKind::Bool,
+            "FloatProperties" => PropertyKind::Float,
+            "StringProperties" => PropertyKind::String,
+            "DataIdProperties" => PropertyKind::DataId,
+            "InstanceIdProperties" => PropertyKind::InstanceId,
+            _ => return None,
+        };
+        Some(ValueKind::Property(kind))
+    }
+}
+
+/// Decodes field values to names, with spell names from a spell table
+#[derive(Debug, Clone, Default)]
+pub struct ValueNames {
+    spells: HashMap<u32, String>,
+}
+
+impl ValueNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name spells with `spells`, replacing any table loaded before
+    pub fn with_spells(spells: HashMap<u32, String>) -> Self {
+        Self { spells }
+    }
+
+    /// Number of spells the spell table names
+    pub fn spell_count(&self) -> usize {
+        self.spells.len()
+    }
+
+    /// The name of the value of field `key` at `path`, if it's a value we can name
+    pub fn describe(&self, path: &str, key: &str, value: u64) -> Option<String> {
+        let value = u32::try_from(value).ok()?;
+        self.name(ValueKind::of_field(path, key)?, value)
+    }
+
+    /// The name of `key` in the map at `path`, if its keys are values we can name
+    ///
+    /// Keys written as names already, rather than numbers, get `None`.
+    pub fn describe_key(&self, path: &str, key: &str) -> Option<String> {
+        let kind = ValueKind::of_map_key(path)?;
+        self.name(kind, parse_id(key)?)
+    }
+
+    fn name(&self, kind: ValueKind, value: u32) -> Option<String> {
+        match kind {
+            ValueKind::ItemType => Some(flag_names::<ItemType>(value)),
+            ValueKind::DamageType => Some(flag_names::<DamageType>(value)),
+            ValueKind::PacketFlags => Some(format_packet_flags(
+                PacketHeaderFlags::from_bits_retain(value),
+            )),
+            ValueKind::Property(kind) => property_name(kind, value),
+            ValueKind::Spell => self.spells.get(&value).cloned(),
+        }
+    }
+
+    /// Name spells from the spell table at `path`: a `.json` object of spell ID to name,
+    /// or a `.csv` with an ID and a name on each line
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let spells = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => spells_from_json(&text),
+            _ => spells_from_csv(&text),
+        };
+        let spells =
+            spells.with_context(|| format!("Failed to load spells from {}", path.display()))?;
+        Ok(Self::with_spells(spells))
+    }
+}
+
+/// Spells from a CSV of `id,name` lines; a header line and `#` comments are skipped
+pub fn spells_from_csv(text: &str) -> Result<HashMap<u32, String>> {
+    let mut spells = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((id, name)) = line.split_once(',') else {
+            bail!("Line {} has no name", number + 1);
+        };
+        let Some(id) = parse_id(id.trim()) else {
+            if number == 0 {
+                continue;
+            }
+            bail!(
+                "Line {} has an invalid spell ID `{}`",
+                number + 1,
+                id.trim()
+            );
+        };
+        spells.insert(id, name.trim().trim_matches('"').to_string());
+    }
+    Ok(spells)
+}
+
+/// Spells from a JSON object of spell ID to name
+pub fn spells_from_json(text: &str) -> Result<HashMap<u32, String>> {
+    let table: HashMap<String, String> = serde_json::from_str(text).context("Invalid JSON")?;
+    table
+        .into_iter()
+        .map(|(id, name)| match parse_id(&id) {
+            Some(id) => Ok((id, name)),
+            None => bail!("Invalid spell ID `{id}`"),
+        })
+        .collect()
+}
+
+/// A decimal or `0x` hex ID
+fn parse_id(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn property_name(kind: crate::weenie::PropertyKind, value: u32) -> Option<String> {
+    use crate::weenie::PropertyKind;
+
+    match kind {
+        PropertyKind::Int => enum_name::<PropertyInt>(value),
+        PropertyKind::Int64 => enum_name::<PropertyInt64>(value),
+        PropertyKind::Bool => enum_name::<PropertyBool>(value),
+        PropertyKind::Float => enum_name::<PropertyFloat>(value),
+        PropertyKind::String => enum_name::<PropertyString>(value),
+        PropertyKind::DataId => enum_name::<PropertyDataId>(value),
+        PropertyKind::InstanceId => enum_name::<PropertyInstanceId>(value),
+        PropertyKind::Name => None,
+    }
+}
+
+/// The name of an enum value, as it's written in decoded messages
+fn enum_name<E>(value: u32) -> Option<String>
+where
+    E: TryFrom<u32> + Serialize + Debug,
+{
+    let value = E::try_from(value).ok()?;
+    // Serialize to get the serde-renamed version
+    Some(
+        serde_json::to_value(&value)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_else(|| format!("{value:?}")),
+    )
+}
+
+/// The names of the flags set in `bits`, e.g. `MeleeWeapon|Armor`
+///
+/// Bits without a name are shown in hex so nothing set goes unmentioned.
+pub fn flag_names<F>(bits: u32) -> String
+where
+    F: bitflags::Flags<Bits = u32>,
+{
+    flag_names_with::<F>(bits, |_| None)
+}
+
+/// Like `flag_names`, but naming a flag with `rename` where it gives a name for the
+/// flag's constant name, e.g. `ACK_SEQUENCE`
+pub fn flag_names_with<F>(bits: u32, rename: impl Fn(&str) -> Option<&'static str>) -> String
+where
+    F: bitflags::Flags<Bits = u32>,
+{
+    let flags = F::from_bits_retain(bits);
+    let mut names: Vec<String> = flags
+        .iter_names()
+        .map(|(name, _)| rename(name).map_or_else(|| pascal_case(name), str::to_string))
+        .collect();
+    let unknown = bits & !F::all().bits();
+    if unknown != 0 {
+        names.push(format!("0x{unknown:X}"));
+    }
+
+    if names.is_empty() {
+        "None".to_string()
+    } else {
+        names.join("|")
+    }
+}
+
+/// `MELEE_WEAPON` as `MeleeWeapon`
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => {
+                    first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+                }
+                None => String::new(),
+            }
+        })
+        .collect()
+}
@@ -5,8 +5,10 @@
 //! information about objects, we maintain a database that merges all information
 //! seen about each object throughout the PCAP.
 
-use serde::{Serialize, Serializer};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 /// A weenie (game object) with all its accumulated properties
 #[derive(Debug, Clone, Serialize)]
@@ -65,6 +67,18 @@ pub struct Weenie {
     /// Metadata: IDs of messages that referenced this object
     #[serde(rename = "MessageIds", skip_serializing_if = "Vec::is_empty")]
     pub message_ids: Vec<usize>,
+
+    /// Metadata: timestamp of each message in `message_ids`
+    #[serde(skip)]
+    pub message_timestamps: Vec<f64>,
+
+    /// Every time a property was set to a new value, oldest first
+    #[serde(rename = "History", skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PropertyChange>,
+
+    /// Each time the object was created or deleted, oldest first
+    #[serde(rename = "Lifecycle", skip_serializing_if = "Vec::is_empty")]
+    pub lifecycle: Vec<LifecycleEvent>,
 }
 
 impl Weenie {
@@ -84,45 +98,361 @@ impl Weenie {
             last_updated: timestamp,
             message_count: 1,
             message_ids: vec![message_id],
+            message_timestamps: vec![timestamp],
+            history: Vec::new(),
+            lifecycle: Vec::new(),
         }
     }
 
+    /// Create a weenie from the first message that referenced it
+    pub fn from_update(update: WeenieUpdate) -> Self {
+        let mut weenie = Self::new(update.object_id, update.timestamp, update.message_id);
+        weenie.apply(update);
+        weenie
+    }
+
     /// Update the weenie with new data from a message
     pub fn update(&mut self, update: WeenieUpdate) {
         self.last_updated = update.timestamp;
         self.message_count += 1;
         self.message_ids.push(update.message_id);
+        self.message_timestamps.push(update.timestamp);
+        self.apply(update);
+    }
+
+    /// Record the lifecycle event and property changes in `update`
+    fn apply(&mut self, update: WeenieUpdate) {
+        if let Some(kind) = update.lifecycle {
+            // An object created again starts over without its old properties
+            if kind == LifecycleKind::Created && self.deleted_at().is_some() {
+                self.clear_properties();
+            }
+            self.lifecycle.push(LifecycleEvent {
+                kind,
+                timestamp: update.timestamp,
+                message_id: update.message_id,
+            });
+        }
+
+        let mut log = ChangeLog {
+            history: &mut self.history,
+            timestamp: update.timestamp,
+            message_id: update.message_id,
+        };
 
         if let Some(name) = update.name {
+            if self.name.as_ref() != Some(&name) {
+                log.record(
+                    PropertyKind::Name,
+                    "Name",
+                    self.name.clone().map(PropertyValue::String),
+                    PropertyValue::String(name.clone()),
+                );
+            }
             self.name = Some(name);
         }
 
-        for (key, value) in update.int_properties {
-            self.int_properties.insert(key, value);
-        }
+        log.merge(
+            PropertyKind::Int,
+            &mut self.int_properties,
+            update.int_properties,
+            PropertyValue::Int,
+        );
+        log.merge(
+            PropertyKind::Int64,
+            &mut self.int64_properties,
+            update.int64_properties,
+            PropertyValue::Int64,
+        );
+        log.merge(
+            PropertyKind::Bool,
+            &mut self.bool_properties,
+            update.bool_properties,
+            PropertyValue::Bool,
+        );
+        log.merge(
+            PropertyKind::Float,
+            &mut self.float_properties,
+            update.float_properties,
+            PropertyValue::Float,
+        );
+        log.merge(
+            PropertyKind::String,
+            &mut self.string_properties,
+            update.string_properties,
+            PropertyValue::String,
+        );
+        log.merge(
+            PropertyKind::DataId,
+            &mut self.data_id_properties,
+            update.data_id_properties,
+            PropertyValue::DataId,
+        );
+        log.merge(
+            PropertyKind::InstanceId,
+            &mut self.instance_id_properties,
+            update.instance_id_properties,
+            PropertyValue::InstanceId,
+        );
+    }
 
-        for (key, value) in update.int64_properties {
-            self.int64_properties.insert(key, value);
+    /// What the weenie looked like at `timestamp`, or `None` if it hadn't been seen yet
+    /// or had been deleted and not created again
+    pub fn state_at(&self, timestamp: f64) -> Option<Weenie> {
+        if self.first_seen > timestamp {
+            return None;
         }
 
-        for (key, value) in update.bool_properties {
-            self.bool_properties.insert(key, value);
+        let seen = self
+            .message_timestamps
+            .iter()
+            .take_while(|&&t| t <= timestamp)
+            .count();
+        let mut weenie = Weenie::new(self.object_id, self.first_seen, self.message_ids[0]);
+        weenie.message_ids = self.message_ids[..seen].to_vec();
+        weenie.message_timestamps = self.message_timestamps[..seen].to_vec();
+        weenie.message_count = seen;
+        weenie.last_updated = weenie
+            .message_timestamps
+            .last()
+            .copied()
+            .unwrap_or(self.first_seen);
+
+        weenie.lifecycle = self
+            .lifecycle
+            .iter()
+            .take_while(|e| e.timestamp <= timestamp)
+            .cloned()
+            .collect();
+        if weenie.deleted_at().is_some() {
+            return None;
         }
-
-        for (key, value) in update.float_properties {
-            self.float_properties.insert(key, value);
+        // Only changes since the object was last created again describe it now
+        let recreated_in = weenie.recreated_in();
+        for change in self.history.iter().take_while(|c| c.timestamp <= timestamp) {
+            if recreated_in.is_none_or(|id| change.message_id >= id) {
+                weenie.set_property(change.kind, &change.property, change.new_value.clone());
+            }
+            weenie.history.push(change.clone());
         }
+        Some(weenie)
+    }
+
+    /// Message that last created the object again after it was deleted
+    fn recreated_in(&self) -> Option<usize> {
+        self.lifecycle
+            .windows(2)
+            .rev()
+            .find(|w| w[0].kind == LifecycleKind::Deleted && w[1].kind == LifecycleKind::Created)
+            .map(|w| w[1].message_id)
+    }
+
+    fn clear_properties(&mut self) {
+        self.name = None;
+        self.int_properties.clear();
+        self.int64_properties.clear();
+        self.bool_properties.clear();
+        self.float_properties.clear();
+        self.string_properties.clear();
+        self.data_id_properties.clear();
+        self.instance_id_properties.clear();
+    }
 
-        for (key, value) in update.string_properties {
-            self.string_properties.insert(key, value);
+    fn set_property(&mut self, kind: PropertyKind, property: &str, value: PropertyValue) {
+        let key = property.to_string();
+        match (kind, value) {
+            (PropertyKind::Name, PropertyValue::String(v)) => self.name = Some(v),
+            (PropertyKind::Int, PropertyValue::Int(v)) => {
+                self.int_properties.insert(key, v);
+            }
+            (PropertyKind::Int64, PropertyValue::Int64(v)) => {
+                self.int64_properties.insert(key, v);
+            }
+            (PropertyKind::Bool, PropertyValue::Bool(v)) => {
+                self.bool_properties.insert(key, v);
+            }
+            (PropertyKind::Float, PropertyValue::Float(v)) => {
+                self.float_properties.insert(key, v);
+            }
+            (PropertyKind::String, PropertyValue::String(v)) => {
+                self.string_properties.insert(key, v);
+            }
+            (PropertyKind::DataId, PropertyValue::DataId(v)) => {
+                self.data_id_properties.insert(key, v);
+            }
+            (PropertyKind::InstanceId, PropertyValue::InstanceId(v)) => {
+                self.instance_id_properties.insert(key, v);
+            }
+            _ => {}
         }
+    }
+
+    /// Each message that referenced the object with its timestamp, oldest first and
+    /// without repeats
+    pub fn messages(&self) -> Vec<(usize, f64)> {
+        let mut messages: Vec<(usize, f64)> = self
+            .message_ids
+            .iter()
+            .copied()
+            .zip(self.message_timestamps.iter().copied())
+            .collect();
+        messages.dedup_by_key(|(id, _)| *id);
+        messages
+    }
+
+    /// When the object was first created, if the capture saw it happen
+    pub fn created_at(&self) -> Option<f64> {
+        self.lifecycle
+            .iter()
+            .find(|e| e.kind == LifecycleKind::Created)
+            .map(|e| e.timestamp)
+    }
 
-        for (key, value) in update.data_id_properties {
-            self.data_id_properties.insert(key, value);
+    /// When the object was deleted, unless it was created again afterwards
+    pub fn deleted_at(&self) -> Option<f64> {
+        self.lifecycle
+            .last()
+            .filter(|e| e.kind == LifecycleKind::Deleted)
+            .map(|e| e.timestamp)
+    }
+
+    /// Changes to one property, oldest first
+    pub fn property_history<'a>(
+        &'a self,
+        kind: PropertyKind,
+        property: &'a str,
+    ) -> impl Iterator<Item = &'a PropertyChange> + 'a {
+        self.history
+            .iter()
+            .filter(move |c| c.kind == kind && c.property == property)
+    }
+}
+
+/// Which property map a property lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PropertyKind {
+    Name,
+    Int,
+    Int64,
+    Bool,
+    Float,
+    String,
+    DataId,
+    InstanceId,
+}
+
+/// A property value of any kind
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Int(i32),
+    Int64(i64),
+    Bool(bool),
+    Float(f64),
+    String(String),
+    DataId(u32),
+    InstanceId(u32),
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::Int(v) => write!(f, "{v}"),
+            PropertyValue::Int64(v) => write!(f, "{v}"),
+            PropertyValue::Bool(v) => write!(f, "{v}"),
+            PropertyValue::Float(v) => write!(f, "{v}"),
+            PropertyValue::String(v) => write!(f, "{v}"),
+            PropertyValue::DataId(v) | PropertyValue::InstanceId(v) => write!(f, "0x{v:08X}"),
         }
+    }
+}
+
+/// One property of a weenie being set to a new value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PropertyChange {
+    #[serde(rename = "Kind")]
+    pub kind: PropertyKind,
 
-        for (key, value) in update.instance_id_properties {
-            self.instance_id_properties.insert(key, value);
+    #[serde(rename = "Property")]
+    pub property: String,
+
+    /// `None` the first time the property is seen
+    #[serde(rename = "OldValue")]
+    pub old_value: Option<PropertyValue>,
+
+    #[serde(rename = "NewValue")]
+    pub new_value: PropertyValue,
+
+    #[serde(rename = "Timestamp")]
+    pub timestamp: f64,
+
+    /// The message that carried the new value
+    #[serde(rename = "MessageId")]
+    pub message_id: usize,
+}
+
+/// An object coming into or going out of existence for the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LifecycleKind {
+    Created,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LifecycleEvent {
+    #[serde(rename = "Kind")]
+    pub kind: LifecycleKind,
+
+    #[serde(rename = "Timestamp")]
+    pub timestamp: f64,
+
+    #[serde(rename = "MessageId")]
+    pub message_id: usize,
+}
+
+/// Records the changes one update makes to a weenie
+struct ChangeLog<'a> {
+    history: &'a mut Vec<PropertyChange>,
+    timestamp: f64,
+    message_id: usize,
+}
+
+impl ChangeLog<'_> {
+    fn record(
+        &mut self,
+        kind: PropertyKind,
+        property: &str,
+        old_value: Option<PropertyValue>,
+        new_value: PropertyValue,
+    ) {
+        self.history.push(PropertyChange {
+            kind,
+            property: property.to_string(),
+            old_value,
+            new_value,
+            timestamp: self.timestamp,
+            message_id: self.message_id,
+        });
+    }
+
+    /// Insert `updates` into `properties`, logging each value that differs from before
+    fn merge<T: Clone + PartialEq>(
+        &mut self,
+        kind: PropertyKind,
+        properties: &mut HashMap<String, T>,
+        updates: HashMap<String, T>,
+        wrap: fn(T) -> PropertyValue,
+    ) {
+        // Sorted so changes from one message are logged in a stable order
+        let mut updates: Vec<_> = updates.into_iter().collect();
+        updates.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (key, value) in updates {
+            let old = properties.get(&key);
+            if old != Some(&value) {
+                self.record(kind, &key, old.cloned().map(wrap), wrap(value.clone()));
+            }
+            properties.insert(key, value);
         }
     }
 }
@@ -141,6 +471,8 @@ pub struct WeenieUpdate {
     pub string_properties: HashMap<String, String>,
     pub data_id_properties: HashMap<String, u32>,
     pub instance_id_properties: HashMap<String, u32>,
+    /// Set when the message created or deleted the object
+    pub lifecycle: Option<LifecycleKind>,
 }
 
 impl WeenieUpdate {
@@ -159,6 +491,8 @@ impl WeenieUpdate {
 #[derive(Debug, Clone, Default)]
 pub struct WeenieDatabase {
     weenies: HashMap<u32, Weenie>,
+    /// Objects referenced by each message, in the order they were updated
+    by_message: HashMap<usize, Vec<u32>>,
 }
 
 impl WeenieDatabase {
@@ -166,23 +500,25 @@ impl WeenieDatabase {
     pub fn new() -> Self {
         Self {
             weenies: HashMap::new(),
+            by_message: HashMap::new(),
         }
     }
 
     /// Add or update a weenie with the given update
     pub fn add_or_update(&mut self, update: WeenieUpdate) {
         let object_id = update.object_id;
-        let timestamp = update.timestamp;
-        let message_id = update.message_id;
 
-        self.weenies
-            .entry(object_id)
-            .and_modify(|w| w.update(update.clone()))
-            .or_insert_with(|| {
-                let mut weenie = Weenie::new(object_id, timestamp, message_id);
-                weenie.update(update);
-                weenie
-            });
+        let objects = self.by_message.entry(update.message_id).or_default();
+        if !objects.contains(&object_id) {
+            objects.push(object_id);
+        }
+
+        match self.weenies.entry(object_id) {
+            Entry::Occupied(mut entry) => entry.get_mut().update(update),
+            Entry::Vacant(entry) => {
+                entry.insert(Weenie::from_update(update));
+            }
+        }
     }
 
     /// Get a weenie by object ID
@@ -190,6 +526,59 @@ impl WeenieDatabase {
         self.weenies.get(&object_id)
     }
 
+    /// Name of the object with `object_id`, if one has been seen
+    pub fn name_of(&self, object_id: u32) -> Option<&str> {
+        self.weenies.get(&object_id)?.name.as_deref()
+    }
+
+    /// Every property change recorded for an object, oldest first
+    pub fn history(&self, object_id: u32) -> &[PropertyChange] {
+        self.weenies
+            .get(&object_id)
+            .map_or(&[], |w| w.history.as_slice())
+    }
+
+    /// What every object looked like at `timestamp`, leaving out objects not yet seen
+    ///
+    /// Properties are replayed from each weenie's history, so later updates don't leak
+    /// into the result.
+    pub fn state_at(&self, timestamp: f64) -> WeenieDatabase {
+        let weenies: HashMap<u32, Weenie> = self
+            .weenies
+            .iter()
+            .filter_map(|(&id, w)| Some((id, w.state_at(timestamp)?)))
+            .collect();
+        // Messages each object had been referenced by at `timestamp`
+        let seen: HashMap<u32, HashSet<usize>> = weenies
+            .iter()
+            .map(|(&id, w)| (id, w.message_ids.iter().copied().collect()))
+            .collect();
+        let by_message = self
+            .by_message
+            .iter()
+            .filter_map(|(&message_id, objects)| {
+                let objects: Vec<u32> = objects
+                    .iter()
+                    .copied()
+                    .filter(|id| seen.get(id).is_some_and(|ids| ids.contains(&message_id)))
+                    .collect();
+                (!objects.is_empty()).then_some((message_id, objects))
+            })
+            .collect();
+        WeenieDatabase {
+            weenies,
+            by_message,
+        }
+    }
+
+    /// Objects the message with `message_id` referenced, in the order it updated them
+    pub fn objects_in_message(&self, message_id: usize) -> Vec<&Weenie> {
+        self.by_message
+            .get(&message_id)
+            .map(|ids| ids.iter().filter_map(|id| self.weenies.get(id)).collect())
+            .unwrap_or_default()
+    }
+
     /// Get all weenies
     pub fn weenies(&self) -> &HashMap<u32, Weenie> {
         &self.weenies
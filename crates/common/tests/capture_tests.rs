@@ -0,0 +1,268 @@
+mod support;
+
+use common::PacketParser;
+use common::capture::{CaptureRecord, LINKTYPE_ETHERNET, PcapReader, PcapWriter, trim, write_pcap};
+use common::diagnostics::DiagnosticKind;
+use common::stream::{ParseEvent, ParsedCapture};
+use support::{
+    ack_frame, ack_payload, build_pcap, encode_fragment, ethernet_frame, fragment_frame,
+};
+
+#[test]
+fn test_read_records() {
+    let file = build_pcap(&[(10, 500, vec![1, 2, 3]), (11, 0, vec![4])]);
+    let records: Vec<_> = PcapReader::new(file.as_slice())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].data, vec![1, 2, 3]);
+    assert_eq!(records[0].timestamp(), 10.0005);
+    assert_eq!(records[1].ts_sec, 11);
+}
+
+#[test]
+fn test_read_big_endian_nanosecond_records() {
+    let mut file = Vec::new();
+    file.extend_from_slice(&0xA1B2_3C4Du32.to_be_bytes());
+    file.extend_from_slice(&[0, 2, 0, 4]);
+    file.extend_from_slice(&[0u8; 8]);
+    file.extend_from_slice(&65535u32.to_be_bytes());
+    file.extend_from_slice(&1u32.to_be_bytes());
+    file.extend_from_slice(&7u32.to_be_bytes());
+    file.extend_from_slice(&250_000_000u32.to_be_bytes());
+    file.extend_from_slice(&2u32.to_be_bytes());
+    file.extend_from_slice(&2u32.to_be_bytes());
+    file.extend_from_slice(&[9, 9]);
+
+    let mut reader = PcapReader::new(file.as_slice()).unwrap();
+    let record = reader.next_record().unwrap().unwrap();
+    assert_eq!(record.ts_usec, 250_000);
+    assert_eq!(record.data, vec![9, 9]);
+    assert!(reader.next_record().unwrap().is_none());
+    assert_eq!(reader.bytes_read(), file.len() as u64);
+}
+
+#[test]
+fn test_rejects_non_pcap() {
+    assert!(PcapReader::new(&[0u8; 24][..]).is_err());
+    assert!(PcapReader::new(&[0u8; 3][..]).is_err());
+}
+
+#[test]
+fn test_truncated_record_is_an_error() {
+    let mut file = build_pcap(&[(1, 0, vec![1, 2, 3, 4])]);
+    file.truncate(file.len() - 2);
+
+    let mut reader = PcapReader::new(file.as_slice()).unwrap();
+    assert!(reader.next_record().is_err());
+}
+
+#[test]
+fn test_write_round_trip() {
+    let mut file = build_pcap(&[
+        (10, 500, vec![1, 2, 3]),
+        (11, 0, vec![4]),
+        (12, 999_999, vec![5]),
+    ]);
+    // The first frame was cut short by the snaplen; it was 1500 bytes on the wire
+    file[24 + 12..24 + 16].copy_from_slice(&1500u32.to_le_bytes());
+    let records: Vec<CaptureRecord> = PcapReader::new(file.as_slice())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(records[0].orig_len, 1500);
+    assert_eq!(records[1].orig_len, 1);
+    assert_eq!(records[0].offset, 24 + 16);
+    assert_eq!(records[1].offset, 24 + 16 + 3 + 16);
+
+    let trimmed = write_pcap([&records[0], &records[2]]).unwrap();
+    let mut reader = PcapReader::new(trimmed.as_slice()).unwrap();
+    assert_eq!(reader.link_type(), LINKTYPE_ETHERNET);
+    let reread: Vec<CaptureRecord> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+    // Everything but where each frame sits in the file
+    let moved = |r: &CaptureRecord, offset| CaptureRecord {
+        offset,
+        ..r.clone()
+    };
+    assert_eq!(
+        reread,
+        vec![
+            moved(&records[0], 24 + 16),
+            moved(&records[2], 24 + 16 + 3 + 16)
+        ]
+    );
+
+    // Trimming the file by record index writes the same
+    assert_eq!(trim(&file, [2, 0]).unwrap(), trimmed);
+}
+
+#[test]
+fn test_writer_keeps_one_link_type() {
+    let mut writer = PcapWriter::new(Vec::new(), LINKTYPE_ETHERNET).unwrap();
+    let record = CaptureRecord {
+        ts_sec: 1,
+        ts_usec: 0,
+        link_type: 101, // raw IP
+        orig_len: 1,
+        offset: 0,
+        data: vec![0x45],
+    };
+    assert!(writer.write_record(&record).is_err());
+
+    // An empty selection still makes a valid, empty capture
+    let empty = write_pcap([]).unwrap();
+    assert!(PcapReader::new(empty.as_slice()).unwrap().next().is_none());
+}
+
+#[test]
+fn test_stream_yields_packets() {
+    let file = build_pcap(&[(1, 0, ack_frame(5)), (2, 0, ack_frame(6))]);
+    let parser = PacketParser::new();
+    let packets: Vec<_> = parser
+        .stream(file.as_slice())
+        .unwrap()
+        .filter_map(|event| match event {
+            ParseEvent::Packet(packet) => Some(packet),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].id, 0);
+    assert_eq!(packets[0].header.sequence, 5);
+    assert_eq!(packets[0].optional.ack_sequence, Some(4));
+    assert_eq!(packets[1].timestamp, 2.0);
+    assert_eq!(packets[1].record_index, 1);
+    assert_eq!(packets[1].raw_payload(&file), ack_payload(6));
+}
+
+#[test]
+fn test_stream_skips_other_udp_traffic() {
+    // A DNS response long enough to pass for an AC header, with flag bits set
+    let dns = ethernet_frame(53, 50123, &[0xFF; 48]);
+    let file = build_pcap(&[(1, 0, dns), (2, 0, ack_frame(6))]);
+    let events: Vec<_> = PacketParser::new()
+        .stream(file.as_slice())
+        .unwrap()
+        .collect();
+
+    assert_eq!(events.len(), 1);
+    let ParseEvent::Packet(packet) = &events[0] else {
+        panic!("expected only the AC packet, got {events:?}");
+    };
+    assert_eq!(packet.id, 0);
+    assert_eq!(packet.header.sequence, 6);
+}
+
+#[test]
+fn test_stream_decodes_ac_traffic_on_any_port() {
+    // A private server on port 7777, with a runt datagram before it has been seen
+    let file = build_pcap(&[
+        (1, 0, ethernet_frame(7777, 60000, &[0u8; 10])),
+        (2, 0, ethernet_frame(7777, 60000, &ack_payload(5))),
+        (3, 0, ethernet_frame(60000, 7777, &ack_payload(6))),
+    ]);
+    let events: Vec<_> = PacketParser::new()
+        .stream(file.as_slice())
+        .unwrap()
+        .collect();
+
+    let directions: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            ParseEvent::Packet(packet) => (packet.header.sequence, packet.direction.as_str()),
+            other => panic!("expected only AC packets, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(directions, vec![(5, "Recv"), (6, "Send")]);
+}
+
+#[test]
+fn test_truncated_capture_keeps_earlier_packets() {
+    let mut file = build_pcap(&[(1, 0, ack_frame(5)), (2, 0, ack_frame(6))]);
+    file.truncate(file.len() - 10);
+
+    let mut parser = PacketParser::new();
+    let (packets, _, _) = parser.parse_pcap_bytes(&file).unwrap();
+    assert_eq!(packets.len(), 1);
+
+    let diagnostics = parser.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::TruncatedCapture);
+    assert_eq!(diagnostics[0].packet_index, None);
+    assert_eq!(diagnostics[0].record_index, Some(1));
+}
+
+#[test]
+fn test_parsed_capture_collects_events() {
+    let file = build_pcap(&[(1, 0, ack_frame(5)), (2, 0, ack_frame(6))]);
+    let parser = PacketParser::new();
+    let mut stream = parser.stream(file.as_slice()).unwrap();
+    let mut capture = ParsedCapture::new();
+    for event in stream.by_ref() {
+        capture.push(event);
+    }
+
+    assert_eq!(capture.packets.len(), 2);
+    assert!(capture.diagnostics.is_empty());
+    assert_eq!(stream.bytes_read(), file.len() as u64);
+}
+
+#[test]
+fn test_example_messages_keep_their_raw_bytes() {
+    let file = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../static/example.pcap"
+    ))
+    .unwrap();
+    let mut capture = ParsedCapture::new();
+    for event in PacketParser::new().stream(file.as_slice()).unwrap() {
+        capture.push(event);
+    }
+
+    assert!(!capture.messages.is_empty());
+    for message in &capture.messages {
+        let raw = &message.raw_bytes;
+        assert!(raw.len() >= 4, "message {} has no raw bytes", message.id);
+        let raw_opcode = u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]);
+        assert_eq!(
+            format!("{raw_opcode:04X}"),
+            message.opcode,
+            "message {} was paired with another message's bytes",
+            message.id
+        );
+    }
+}
+
+#[test]
+fn test_stream_forgets_old_messages() {
+    // A message a second for an hour, every tenth missing its second fragment
+    let records: Vec<_> = (1..=3600u32)
+        .map(|sequence| {
+            let count = if sequence % 10 == 0 { 2 } else { 1 };
+            let body = encode_fragment(sequence, count, 0, &[0xB0, 0xF7, 0, 0]);
+            (sequence, 0, fragment_frame(sequence, &body))
+        })
+        .collect();
+    let file = build_pcap(&records);
+
+    let mut stream = PacketParser::new().stream(file.as_slice()).unwrap();
+    let mut most_tracked = 0;
+    let mut incomplete = Vec::new();
+    while let Some(event) = stream.next() {
+        most_tracked = most_tracked.max(stream.tracked_messages());
+        if let ParseEvent::Diagnostic(d) = event
+            && d.kind == DiagnosticKind::IncompleteMessage
+        {
+            incomplete.push(d.packet_index.unwrap());
+        }
+    }
+
+    // Only the last FRAGMENT_TIMEOUT seconds or so are ever tracked
+    assert!(most_tracked < 3 * 80, "tracked {most_tracked} messages");
+    // Each incomplete message is still reported, once, in capture order
+    let expected: Vec<usize> = (1..=360).map(|n| n * 10 - 1).collect();
+    assert_eq!(incomplete, expected);
+}
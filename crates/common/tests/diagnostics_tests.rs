@@ -0,0 +1,126 @@
+mod support;
+
+use common::PacketParser;
+use common::diagnostics::{DiagnosticKind, ParseDiagnostic};
+use common::stream::ParseEvent;
+use support::{ack_frame, build_pcap, encode_fragment, ethernet_frame, fragment_frame};
+
+/// Stream a pcap file, returning the ids of the packets and the diagnostics it yields
+fn stream(file: &[u8]) -> (Vec<usize>, Vec<ParseDiagnostic>) {
+    let mut packets = Vec::new();
+    let mut diagnostics = Vec::new();
+    for event in PacketParser::new().stream(file).unwrap() {
+        match event {
+            ParseEvent::Packet(packet) => packets.push(packet.id),
+            ParseEvent::Diagnostic(diagnostic) => diagnostics.push(diagnostic),
+            _ => {}
+        }
+    }
+    (packets, diagnostics)
+}
+
+#[test]
+fn test_diagnostic_builder() {
+    let diagnostic = ParseDiagnostic::new(DiagnosticKind::UnknownOpcode, "not decoded")
+        .at_packet(12, 1700000000.5)
+        .with_offset(0x24)
+        .with_opcode(0xF7B0);
+
+    assert_eq!(diagnostic.packet_index, Some(12));
+    assert_eq!(diagnostic.timestamp, 1700000000.5);
+    assert_eq!(diagnostic.offset, Some(0x24));
+    assert_eq!(diagnostic.opcode, Some(0xF7B0));
+    assert_eq!(diagnostic.kind.label(), "Unknown opcode");
+}
+
+#[test]
+fn test_diagnostic_serialization() {
+    let diagnostic = ParseDiagnostic::new(DiagnosticKind::TruncatedCapture, "unexpected eof");
+    let json = serde_json::to_value(&diagnostic).unwrap();
+
+    assert_eq!(json["Kind"], "TruncatedCapture");
+    assert_eq!(json["Message"], "unexpected eof");
+    assert!(json["PacketIndex"].is_null());
+    assert!(json["RecordIndex"].is_null());
+    assert!(json["OpCode"].is_null());
+}
+
+#[test]
+fn test_invalid_header_points_at_no_packet() {
+    let short = ethernet_frame(9000, 50123, &[0u8; 10]);
+    let file = build_pcap(&[(1, 0, ack_frame(5)), (2, 0, short), (3, 0, ack_frame(6))]);
+    let (packets, diagnostics) = stream(&file);
+
+    // The short datagram is dropped, so the next packet is still packet 1
+    assert_eq!(packets, vec![0, 1]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::InvalidHeader);
+    assert_eq!(diagnostics[0].packet_index, None);
+    assert_eq!(diagnostics[0].record_index, Some(1));
+    assert_eq!(diagnostics[0].timestamp, 2.0);
+}
+
+#[test]
+fn test_invalid_fragment_points_at_its_packet() {
+    // A fragment whose size is smaller than its own header
+    let mut body = encode_fragment(1, 1, 0, &[]);
+    body[10..12].copy_from_slice(&4u16.to_le_bytes());
+    let dns = ethernet_frame(53, 50123, &[0u8; 32]);
+    let file = build_pcap(&[
+        (1, 0, dns),
+        (2, 0, ack_frame(5)),
+        (3, 0, fragment_frame(6, &body)),
+    ]);
+    let (packets, diagnostics) = stream(&file);
+
+    assert_eq!(packets, vec![0, 1]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::InvalidFragment);
+    assert_eq!(diagnostics[0].packet_index, Some(1));
+    assert_eq!(diagnostics[0].record_index, Some(2));
+}
+
+#[test]
+fn test_unknown_opcode_points_at_completing_packet() {
+    let body = encode_fragment(1, 1, 0, &[0xF0, 0xFF, 0xFF, 0xFF, 1, 2, 3, 4]);
+    let file = build_pcap(&[(1, 0, ack_frame(5)), (2, 0, fragment_frame(6, &body))]);
+    let (packets, diagnostics) = stream(&file);
+
+    assert_eq!(packets, vec![0, 1]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::UnknownOpcode);
+    assert_eq!(diagnostics[0].opcode, Some(0xFFFF_FFF0));
+    assert_eq!(diagnostics[0].packet_index, Some(1));
+    assert_eq!(diagnostics[0].record_index, Some(1));
+}
+
+#[test]
+fn test_incomplete_message_points_at_first_fragment() {
+    let body = encode_fragment(1, 2, 0, &[0xB0, 0xF7, 0, 0]);
+    let file = build_pcap(&[
+        (1, 0, ack_frame(5)),
+        (2, 0, fragment_frame(6, &body)),
+        (3, 0, ack_frame(7)),
+    ]);
+    let (packets, diagnostics) = stream(&file);
+
+    assert_eq!(packets, vec![0, 1, 2]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::IncompleteMessage);
+    assert_eq!(diagnostics[0].packet_index, Some(1));
+    assert_eq!(diagnostics[0].record_index, Some(1));
+    assert_eq!(diagnostics[0].timestamp, 2.0);
+}
+
+#[test]
+fn test_truncated_capture_points_at_unread_record() {
+    let mut file = build_pcap(&[(1, 0, ack_frame(5)), (2, 0, ack_frame(6))]);
+    file.truncate(file.len() - 10);
+    let (packets, diagnostics) = stream(&file);
+
+    assert_eq!(packets, vec![0]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::TruncatedCapture);
+    assert_eq!(diagnostics[0].packet_index, None);
+    assert_eq!(diagnostics[0].record_index, Some(1));
+}
@@ -0,0 +1,106 @@
+mod support;
+
+use common::diff::{DiffKind, DiffOptions, diff_captures, diff_values};
+use common::messages::ParsedMessage;
+use serde_json::{Value, json};
+use support::message;
+
+fn stack(id: usize, size: u32) -> ParsedMessage {
+    message(
+        id,
+        "Item_UpdateStackSize",
+        json!({ "Sequence": id, "ObjectId": 0x80000A01u32, "Amount": size }),
+    )
+}
+
+#[test]
+fn test_identical_captures() {
+    let left = vec![stack(0, 5), stack(1, 4)];
+    let diff = diff_captures(&left, &left, &DiffOptions::default());
+    assert!(diff.is_empty());
+    assert_eq!(diff.same, 2);
+}
+
+#[test]
+fn test_missing_extra_and_changed() {
+    let left = vec![
+        stack(0, 5),
+        message(1, "Effects_SoundEvent", json!({ "ObjectId": 1 })),
+        stack(2, 4),
+    ];
+    let right = vec![
+        stack(0, 5),
+        stack(1, 3),
+        message(2, "Communication_HearSpeech", json!({ "Message": "Hi" })),
+    ];
+    let diff = diff_captures(&left, &right, &DiffOptions::default());
+
+    assert_eq!(diff.count(DiffKind::Missing), 1);
+    assert_eq!(diff.count(DiffKind::Extra), 1);
+    assert_eq!(diff.count(DiffKind::Changed), 1);
+
+    // The second stack update on each side is paired, whatever lies between them
+    let changed = diff
+        .entries
+        .iter()
+        .find(|e| e.kind == DiffKind::Changed)
+        .unwrap();
+    assert_eq!((changed.left_id, changed.right_id), (Some(2), Some(1)));
+    assert_eq!(changed.occurrence, 1);
+    let mut paths: Vec<&str> = changed.fields.iter().map(|f| f.path.as_str()).collect();
+    paths.sort_unstable();
+    assert_eq!(paths, ["Amount", "Sequence"]);
+
+    let missing = &diff.entries[0];
+    assert_eq!(missing.kind, DiffKind::Missing);
+    assert_eq!(missing.message_type, "Effects_SoundEvent");
+    assert_eq!(missing.right_id, None);
+}
+
+#[test]
+fn test_missing_message_in_the_middle() {
+    let options = DiffOptions {
+        ignore_fields: vec!["Sequence".to_string()],
+    };
+    let left = vec![stack(0, 5), stack(1, 4), stack(2, 3), stack(3, 2)];
+    let right = vec![stack(10, 5), stack(11, 3), stack(12, 2)];
+    let diff = diff_captures(&left, &right, &options);
+
+    // Only the missing update is reported; the ones after it still find their partners
+    assert_eq!(diff.same, 3);
+    assert_eq!(diff.entries.len(), 1);
+    let missing = &diff.entries[0];
+    assert_eq!(missing.kind, DiffKind::Missing);
+    assert_eq!((missing.left_id, missing.right_id), (Some(1), None));
+    assert_eq!(missing.occurrence, 1);
+}
+
+#[test]
+fn test_ignored_fields() {
+    let options = DiffOptions {
+        ignore_fields: vec!["Sequence".to_string()],
+    };
+    let diff = diff_captures(&[stack(0, 5)], &[stack(7, 5)], &options);
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn test_field_diffs() {
+    let left = json!({ "A": { "B": [1, 2, 3], "C": "x" }, "D": true });
+    let right = json!({ "A": { "B": [1, 5], "E": null }, "D": true });
+    let fields = diff_values(&left, &right, &DiffOptions::default());
+
+    let summary: Vec<(&str, Option<&Value>, Option<&Value>)> = fields
+        .iter()
+        .map(|f| (f.path.as_str(), f.left.as_ref(), f.right.as_ref()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("A.B[1]", Some(&json!(2)), Some(&json!(5))),
+            ("A.B[2]", Some(&json!(3)), None),
+            ("A.C", Some(&json!("x")), None),
+            ("A.E", None, Some(&Value::Null)),
+        ]
+    );
+}
@@ -0,0 +1,77 @@
+mod support;
+
+use common::export::{ExportFormat, export_messages, export_weenies};
+use common::messages::ParsedMessage;
+use common::weenie::Weenie;
+use serde_json::{Value, json};
+
+fn message(id: usize, data: Value) -> ParsedMessage {
+    ParsedMessage {
+        opcode: "F7B0".to_string(),
+        timestamp: 100.5,
+        ..support::message(id, "Item_SetAppraiseInfo", data)
+    }
+}
+
+fn text(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap()
+}
+
+#[test]
+fn test_json_and_jsonl_use_serde_names() {
+    let first = message(1, json!({ "ObjectId": 5 }));
+    let second = message(2, json!({ "ObjectId": 6 }));
+    let messages = [&first, &second];
+
+    let json: Value =
+        serde_json::from_slice(&export_messages(&messages, ExportFormat::Json).unwrap()).unwrap();
+    assert_eq!(json[1]["Id"], 2);
+    assert_eq!(json[0]["Data"]["ObjectId"], 5);
+
+    let jsonl = text(export_messages(&messages, ExportFormat::Jsonl).unwrap());
+    let lines: Vec<&str> = jsonl.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let line: Value = serde_json::from_str(lines[1]).unwrap();
+    assert_eq!(line["Type"], "Item_SetAppraiseInfo");
+}
+
+#[test]
+fn test_message_csv_flattens_data() {
+    let first = message(
+        1,
+        json!({ "Name": "Sword, Long", "Note": "a \"b\"", "Spells": [10, 20] }),
+    );
+    let second = message(2, json!({ "Burden": 150, "Name": "Shield" }));
+    let csv = text(export_messages(&[&first, &second], ExportFormat::Csv).unwrap());
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(
+        lines[0],
+        "Id,Type,Direction,OpCode,Timestamp,Data.Name,Data.Note,Data.Spells[0],Data.Spells[1],Data.Burden"
+    );
+    assert_eq!(
+        lines[1],
+        "1,Item_SetAppraiseInfo,Recv,F7B0,100.5,\"Sword, Long\",\"a \"\"b\"\"\",10,20,"
+    );
+    assert_eq!(
+        lines[2],
+        "2,Item_SetAppraiseInfo,Recv,F7B0,100.5,Shield,,,,150"
+    );
+}
+
+#[test]
+fn test_weenie_csv() {
+    let mut weenie = Weenie::new(0x80000A01, 100.0, 1);
+    weenie.name = Some("Pack".to_string());
+    weenie.int_properties.insert("StackSize".to_string(), 7);
+    let csv = text(export_weenies(&[&weenie], ExportFormat::Csv).unwrap());
+    let lines: Vec<&str> = csv.lines().collect();
+
+    // Message IDs and history stay out of the columns
+    assert_eq!(
+        lines[0],
+        "ObjectId,Name,FirstSeen,LastUpdated,MessageCount,IntProperties.StackSize"
+    );
+    assert!(lines[1].starts_with(&format!("{},Pack,100", 0x80000A01u32)));
+    assert!(lines[1].ends_with(",7"));
+}
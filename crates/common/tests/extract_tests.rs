@@ -0,0 +1,112 @@
+mod support;
+
+use common::extract::{FieldPath, Segment, extract, format_values};
+use common::messages::ParsedMessage;
+use serde_json::{Value, json};
+
+fn message(id: usize, data: Value) -> ParsedMessage {
+    support::message(id, "Magic_UpdateEnchantment", data)
+}
+
+fn select(path: &str, value: &Value) -> Vec<Value> {
+    FieldPath::parse(path)
+        .unwrap_or_else(|e| panic!("`{path}` failed to parse: {e}"))
+        .select(value)
+        .into_iter()
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn test_parse_syntaxes() {
+    let expected = vec![
+        Segment::Key("Enchantment".to_string()),
+        Segment::Key("Id".to_string()),
+        Segment::Key("SpellId".to_string()),
+    ];
+    for path in [
+        "Enchantment.Id.SpellId",
+        "$.Enchantment.Id.SpellId",
+        ".Enchantment.Id.SpellId",
+        "$['Enchantment'][\"Id\"].SpellId",
+    ] {
+        assert_eq!(
+            FieldPath::parse(path).unwrap().segments(),
+            expected,
+            "{path}"
+        );
+    }
+
+    let path = FieldPath::parse("$.Items[*]..Name[2]").unwrap();
+    assert_eq!(
+        path.segments(),
+        [
+            Segment::Key("Items".to_string()),
+            Segment::Wildcard,
+            Segment::Descendant("Name".to_string()),
+            Segment::Index(2),
+        ]
+    );
+    assert!(FieldPath::parse(".").unwrap().is_empty());
+    assert!(FieldPath::parse("$").unwrap().is_empty());
+}
+
+#[test]
+fn test_parse_errors() {
+    for path in ["Items[", "Items[x]", "Items..", "Items.", "a b]"] {
+        assert!(FieldPath::parse(path).is_err(), "`{path}` should not parse");
+    }
+    assert_eq!(FieldPath::parse("Items[").unwrap_err().position, 5);
+}
+
+#[test]
+fn test_select() {
+    let data = json!({
+        "ObjectId": 1,
+        "Items": [
+            { "Name": "Pantaloons", "Stats": { "Name": "inner" } },
+            { "Name": "Sword" },
+        ],
+    });
+    assert_eq!(select("ObjectId", &data), [json!(1)]);
+    assert_eq!(select("objectid", &data), [json!(1)]);
+    assert_eq!(
+        select("Items.Name", &data),
+        [json!("Pantaloons"), json!("Sword")]
+    );
+    assert_eq!(select("Items[1].Name", &data), [json!("Sword")]);
+    assert_eq!(
+        select("Items[*].Name", &data),
+        [json!("Pantaloons"), json!("Sword")]
+    );
+    assert_eq!(
+        select("..Name", &data),
+        [json!("Pantaloons"), json!("inner"), json!("Sword")]
+    );
+    assert_eq!(select(".", &data), vec![data.clone()]);
+    assert!(select("Items[5]", &data).is_empty());
+    assert!(select("Missing.Name", &data).is_empty());
+}
+
+#[test]
+fn test_extract_table() {
+    let messages = vec![
+        message(0, json!({ "Enchantment": { "Id": { "SpellId": 2053 } } })),
+        message(1, json!({ "ObjectId": 7 })),
+        message(2, json!({ "Enchantment": { "Id": { "SpellId": 17 } } })),
+    ];
+    let path = FieldPath::parse("Enchantment.Id.SpellId").unwrap();
+    let rows = extract(&messages, &path);
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].message_id, 0);
+    assert_eq!(rows[0].timestamp, 100.0);
+    assert_eq!(rows[0].values, [json!(2053)]);
+    assert_eq!(rows[1].message_id, 2);
+    assert_eq!(rows[1].values, [json!(17)]);
+
+    assert_eq!(
+        format_values(&[json!("Sword"), json!(3), json!({ "a": 1 })]),
+        "Sword, 3, {\"a\":1}"
+    );
+}
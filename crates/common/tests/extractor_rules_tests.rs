@@ -0,0 +1,139 @@
+mod support;
+
+use common::extractor_rules::ExtractorRegistry;
+use common::messages::ParsedMessage;
+use serde_json::{Value, json};
+
+fn message(message_type: &str, data: Value) -> ParsedMessage {
+    support::message(1, message_type, data)
+}
+
+fn stack_size_changed() -> ParsedMessage {
+    message(
+        "Item_UpdateStackSize",
+        json!({
+            "S2C": {
+                "ItemUpdateStackSize": {
+                    "ObjectId": 0x80000A01u32,
+                    "Amount": 7,
+                    "NewValue": 700,
+                },
+            },
+        }),
+    )
+}
+
+const STACK_SIZE_RULES: &str = r#"
+[[rule]]
+message_type = "Item_UpdateStackSize"
+root = "S2C.ItemUpdateStackSize"
+object_id = "ObjectId"
+property = [
+    { kind = "Int", name = "StackSize", value = "Amount" },
+    { kind = "Int", name = "Value", value = "NewValue" },
+]
+"#;
+
+#[test]
+fn test_builtin_rules_load() {
+    let rules = ExtractorRegistry::builtin();
+    assert!(rules.covers("Item_CreateObject"));
+    assert!(rules.covers("Qualities_PrivateUpdateInt"));
+    assert!(!rules.covers("Item_UpdateStackSize"));
+}
+
+#[test]
+fn test_toml_rules() {
+    let rules = ExtractorRegistry::from_toml(STACK_SIZE_RULES).unwrap();
+    let updates = rules.extract(&stack_size_changed(), None);
+
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].object_id, 0x80000A01);
+    assert_eq!(updates[0].int_properties["StackSize"], 7);
+    assert_eq!(updates[0].int_properties["Value"], 700);
+}
+
+#[test]
+fn test_json_rules_match_toml() {
+    let rules = ExtractorRegistry::from_json(
+        r#"{
+            "rule": [{
+                "message_type": "Item_UpdateStackSize",
+                "root": "S2C.ItemUpdateStackSize",
+                "object_id": "ObjectId",
+                "property": [{ "kind": "Int", "name": "StackSize", "value": "Amount" }]
+            }]
+        }"#,
+    )
+    .unwrap();
+    let updates = rules.extract(&stack_size_changed(), None);
+    assert_eq!(updates[0].int_properties["StackSize"], 7);
+}
+
+#[test]
+fn test_keyed_and_player_rules() {
+    let rules = ExtractorRegistry::from_toml(
+        r#"
+[[rule]]
+message_type = "Qualities_PrivateUpdateInt"
+root = "S2C.QualitiesPrivateUpdateInt"
+property = [{ kind = "Int", key = "Key", value = "Value" }]
+"#,
+    )
+    .unwrap();
+    let update = message(
+        "Qualities_PrivateUpdateInt",
+        json!({ "S2C": { "QualitiesPrivateUpdateInt": { "Key": "Level", "Value": 12 } } }),
+    );
+
+    // Without a logged-in character there's nobody to attribute it to
+    assert!(rules.extract(&update, None).is_empty());
+    let updates = rules.extract(&update, Some(0x50000001));
+    assert_eq!(updates[0].object_id, 0x50000001);
+    assert_eq!(updates[0].int_properties["Level"], 12);
+}
+
+#[test]
+fn test_merge_replaces_rules_for_covered_types() {
+    let mut rules = ExtractorRegistry::builtin().as_ref().clone();
+    let builtin_types = rules.len();
+    rules.merge(ExtractorRegistry::from_toml(STACK_SIZE_RULES).unwrap());
+
+    assert_eq!(rules.len(), builtin_types + 1);
+    assert_eq!(rules.extract(&stack_size_changed(), None).len(), 1);
+
+    // A rule for a built-in type replaces the built-in rules for it
+    rules.merge(
+        ExtractorRegistry::from_toml(
+            r#"
+[[rule]]
+message_type = "Item_DeleteObject"
+object_id = "S2C.ItemDeleteObject.ObjectId"
+"#,
+        )
+        .unwrap(),
+    );
+    let delete = message(
+        "Item_DeleteObject",
+        json!({ "S2C": { "ItemDeleteObject": { "ObjectId": 0x80000A01u32 } } }),
+    );
+    assert_eq!(rules.extract(&delete, None)[0].lifecycle, None);
+}
+
+#[test]
+fn test_invalid_rules() {
+    // A property needs somewhere to read its value from
+    let error = ExtractorRegistry::from_toml(
+        r#"
+[[rule]]
+message_type = "Item_UpdateStackSize"
+object_id = "ObjectId"
+property = [{ kind = "Int", name = "StackSize" }]
+"#,
+    )
+    .unwrap_err();
+    assert!(format!("{error:#}").contains("Item_UpdateStackSize"));
+
+    assert!(ExtractorRegistry::from_toml("[[rule]]\nobject_id = \"[\"").is_err());
+    assert!(ExtractorRegistry::from_toml("[[rule]]\nmessage_type = \"X\"\nbogus = 1").is_err());
+}
@@ -0,0 +1,125 @@
+use common::messages::field_offsets::{FieldOffsets, field_at, map_field_offsets};
+use serde_json::json;
+
+/// Bytes of a message with an opcode, an object id, a String16L name and a float
+fn build_message() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0xF745u32.to_le_bytes()); // opcode
+    bytes.extend_from_slice(&0x5000_0001u32.to_le_bytes()); // object id
+    bytes.extend_from_slice(&5u16.to_le_bytes());
+    bytes.extend_from_slice(b"Drudg");
+    bytes.push(0); // align to 4 bytes
+    bytes.extend_from_slice(&1.25f32.to_le_bytes());
+    bytes.extend_from_slice(&1u32.to_le_bytes()); // flag
+    bytes
+}
+
+#[test]
+fn test_map_leaf_offsets() {
+    let bytes = build_message();
+    let data = json!({
+        "OpCode": "F745",
+        "ObjectId": 0x5000_0001u32,
+        "Name": "Drudg",
+        "Scale": 1.25,
+        "Flag": 1,
+    });
+
+    let spans = map_field_offsets(&data, &bytes);
+    let span = |path: &str| {
+        let span = spans.iter().find(|s| s.path == path).unwrap();
+        (span.start, span.end)
+    };
+
+    assert_eq!(span("OpCode"), (0, 4));
+    assert_eq!(span("ObjectId"), (4, 8));
+    assert_eq!(span("Name"), (8, 15));
+    assert_eq!(span("Scale"), (16, 20));
+    assert_eq!(span("Flag"), (20, 24));
+}
+
+#[test]
+fn test_small_values_do_not_steal_distinctive_bytes() {
+    // 0x01 appears inside the object id before the real flag field
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0x0100_0001u32.to_le_bytes());
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+
+    // "A" sorts before "B", so the flag is visited first
+    let data = json!({ "AFlag": 1, "BObjectId": 0x0100_0001u32 });
+    let spans = map_field_offsets(&data, &bytes);
+
+    let flag = spans.iter().find(|s| s.path == "AFlag").unwrap();
+    assert_eq!((flag.start, flag.end), (4, 8));
+}
+
+#[test]
+fn test_container_spans_cover_children() {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0x1234u32.to_le_bytes());
+    bytes.extend_from_slice(&0xAAAA_BBBBu32.to_le_bytes());
+    bytes.extend_from_slice(&0xCCCC_DDDDu32.to_le_bytes());
+
+    let data = json!({
+        "Header": 0x1234,
+        "Items": [ { "Id": 0xAAAA_BBBBu32 }, { "Id": 0xCCCC_DDDDu32 } ],
+    });
+    let spans = map_field_offsets(&data, &bytes);
+    let paths: Vec<&str> = spans.iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "Header",
+            "Items",
+            "Items[0]",
+            "Items[0].Id",
+            "Items[1]",
+            "Items[1].Id"
+        ]
+    );
+
+    let items = &spans[1];
+    assert_eq!((items.start, items.end), (4, 12));
+
+    // The innermost field wins when looking up a byte
+    assert_eq!(field_at(&spans, 9).unwrap().path, "Items[1].Id");
+    assert!(field_at(&spans, 12).is_none());
+}
+
+#[test]
+fn test_unlocatable_fields_are_omitted() {
+    let bytes = 0x1234u32.to_le_bytes();
+    let data = json!({ "Kind": "SomeEnumName", "Value": 0x1234 });
+    let spans = map_field_offsets(&data, &bytes);
+
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].path, "Value");
+}
+
+#[test]
+fn test_ambiguous_values_are_omitted() {
+    // The count's bytes appear twice, so either could be the field
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0x1234u32.to_le_bytes());
+    bytes.extend_from_slice(&0xAAAA_BBBBu32.to_le_bytes());
+    bytes.extend_from_slice(&0x1234u32.to_le_bytes());
+
+    let data = json!({ "Count": 0x1234, "Id": 0xAAAA_BBBBu32 });
+    let spans = map_field_offsets(&data, &bytes);
+
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].path, "Id");
+}
+
+#[test]
+fn test_field_offsets_look_up_by_path_and_byte() {
+    let offsets = FieldOffsets::new(
+        &json!({ "Header": 0x1234, "Id": 0xAAAA_BBBBu32 }),
+        &[0x34, 0x12, 0, 0, 0xBB, 0xBB, 0xAA, 0xAA],
+    );
+
+    assert_eq!(offsets.spans().len(), 2);
+    assert_eq!(offsets.get("Id").map(|s| (s.start, s.end)), Some((4, 8)));
+    assert!(offsets.get("Missing").is_none());
+    assert_eq!(offsets.at(1).unwrap().path, "Header");
+}
@@ -0,0 +1,85 @@
+mod support;
+
+use common::fragment::{FRAGMENT_HEADER_SIZE, FragmentReassembler, read_fragments};
+use support::encode_fragment;
+
+#[test]
+fn test_read_multiple_fragments() {
+    let mut body = encode_fragment(1, 1, 0, &[0xB0, 0xF7, 0, 0, 1, 2]);
+    body.extend(encode_fragment(2, 1, 0, &[0xC8, 0x02, 0, 0]));
+
+    let fragments = read_fragments(&body, 20).unwrap();
+    assert_eq!(fragments.len(), 2);
+    assert_eq!(fragments[0].sequence, 1);
+    assert_eq!(fragments[0].offset, 20);
+    assert_eq!(fragments[0].data, vec![0xB0, 0xF7, 0, 0, 1, 2]);
+    assert_eq!(fragments[1].sequence, 2);
+    assert_eq!(fragments[1].offset, 20 + FRAGMENT_HEADER_SIZE + 6);
+    assert_eq!(fragments[1].group, 5);
+}
+
+#[test]
+fn test_read_truncated_fragment_fails() {
+    let mut body = encode_fragment(1, 1, 0, &[1, 2, 3, 4]);
+    body.truncate(body.len() - 1);
+    assert!(read_fragments(&body, 0).is_err());
+}
+
+#[test]
+fn test_reassemble_out_of_order_fragments() {
+    let body: Vec<u8> = [
+        encode_fragment(7, 2, 1, &[3, 4]),
+        encode_fragment(7, 2, 0, &[0xB0, 0xF7, 0, 0, 1, 2]),
+    ]
+    .concat();
+    let fragments = read_fragments(&body, 0).unwrap();
+
+    let mut reassembler = FragmentReassembler::new();
+    assert!(reassembler.add("Recv", &fragments[0]).is_none());
+    let message = reassembler
+        .add("Recv", &fragments[1])
+        .expect("second fragment should complete the message");
+
+    assert_eq!(message.sequence, 7);
+    assert_eq!(message.data, vec![0xB0, 0xF7, 0, 0, 1, 2, 3, 4]);
+    assert_eq!(message.opcode(), Some(0xF7B0));
+
+    // A retransmitted fragment of a completed message is ignored
+    assert!(reassembler.add("Recv", &fragments[1]).is_none());
+}
+
+#[test]
+fn test_reassembly_is_per_direction() {
+    let body = encode_fragment(3, 2, 0, &[1, 2]);
+    let fragments = read_fragments(&body, 0).unwrap();
+
+    let mut reassembler = FragmentReassembler::new();
+    assert!(reassembler.add("Send", &fragments[0]).is_none());
+    assert!(reassembler.add("Recv", &fragments[0]).is_none());
+}
+
+#[test]
+fn test_received_counts_distinct_fragments() {
+    let body: Vec<u8> = [
+        encode_fragment(9, 3, 0, &[0xB0, 0xF7, 0, 0]),
+        encode_fragment(9, 3, 0, &[0xB0, 0xF7, 0, 0]),
+        encode_fragment(9, 3, 2, &[1]),
+        encode_fragment(9, 3, 1, &[2]),
+    ]
+    .concat();
+    let fragments = read_fragments(&body, 0).unwrap();
+
+    let mut reassembler = FragmentReassembler::new();
+    assert_eq!(reassembler.received("Recv", &fragments[0]), 0);
+    reassembler.add("Recv", &fragments[0]);
+    assert_eq!(reassembler.received("Recv", &fragments[0]), 1);
+
+    // A duplicate of a fragment already held doesn't count twice
+    reassembler.add("Recv", &fragments[1]);
+    assert_eq!(reassembler.received("Recv", &fragments[1]), 1);
+
+    reassembler.add("Recv", &fragments[2]);
+    assert_eq!(reassembler.received("Recv", &fragments[2]), 2);
+    assert!(reassembler.add("Recv", &fragments[3]).is_some());
+    assert_eq!(reassembler.received("Recv", &fragments[3]), 3);
+}
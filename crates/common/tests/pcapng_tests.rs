@@ -0,0 +1,275 @@
+mod support;
+
+use common::capture::{
+    CaptureReader, CaptureRecord, LINKTYPE_ETHERNET, LINKTYPE_LINUX_SLL, LINKTYPE_LINUX_SLL2,
+    LINKTYPE_RAW, PcapReader, trim,
+};
+use common::diagnostics::DiagnosticKind;
+use common::pcapng::PcapngReader;
+use common::stream::ParseEvent;
+use common::{PacketParser, ParsedPacket};
+use support::{ack_payload, ethernet_frame, ip_packet};
+
+/// A pcapng block of `block_type` around `body`, padded to 32 bits
+fn block(block_type: u32, body: &[u8], big_endian: bool) -> Vec<u8> {
+    let u32_bytes = |v: u32| {
+        if big_endian {
+            v.to_be_bytes()
+        } else {
+            v.to_le_bytes()
+        }
+    };
+    let mut body = body.to_vec();
+    body.resize(body.len().next_multiple_of(4), 0);
+    let length = (body.len() + 12) as u32;
+
+    let mut block = Vec::new();
+    block.extend_from_slice(&u32_bytes(block_type));
+    block.extend_from_slice(&u32_bytes(length));
+    block.extend_from_slice(&body);
+    block.extend_from_slice(&u32_bytes(length));
+    block
+}
+
+fn section_header() -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes()); // version major
+    body.extend_from_slice(&0u16.to_le_bytes()); // version minor
+    body.extend_from_slice(&(-1i64).to_le_bytes()); // section length unknown
+    block(0x0A0D_0D0A, &body, false)
+}
+
+/// An interface description, with an `if_tsresol` option if `tsresol` is given
+fn interface(link_type: u16, tsresol: Option<u8>) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&link_type.to_le_bytes());
+    body.extend_from_slice(&[0, 0]);
+    body.extend_from_slice(&65535u32.to_le_bytes());
+    if let Some(tsresol) = tsresol {
+        body.extend_from_slice(&9u16.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&[tsresol, 0, 0, 0]);
+        body.extend_from_slice(&[0u8; 4]); // opt_endofopt
+    }
+    block(1, &body, false)
+}
+
+fn enhanced_packet(interface_id: u32, timestamp: u64, data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&interface_id.to_le_bytes());
+    body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+    body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+    body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    body.extend_from_slice(data);
+    block(6, &body, false)
+}
+
+#[test]
+fn test_read_enhanced_packets() {
+    let mut file = section_header();
+    file.extend(interface(1, None));
+    file.extend(block(5, &[0u8; 20], false)); // statistics, skipped
+    file.extend(enhanced_packet(0, 10_000_500, &[1, 2, 3]));
+    file.extend(enhanced_packet(0, 11_000_000, &[4]));
+
+    let records: Vec<_> = PcapngReader::new(file.as_slice())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].data, vec![1, 2, 3]);
+    assert_eq!(records[0].timestamp(), 10.0005);
+    assert_eq!(records[0].link_type, LINKTYPE_ETHERNET);
+    assert_eq!(records[1].ts_sec, 11);
+    assert_eq!(records[1].orig_len, 1);
+}
+
+#[test]
+fn test_per_interface_resolution_and_link_type() {
+    let mut file = section_header();
+    file.extend(interface(1, Some(9))); // nanoseconds
+    file.extend(interface(101, Some(0x80 | 10))); // 1/1024 s, raw IP
+    file.extend(enhanced_packet(0, 7_250_000_000, &[9]));
+    file.extend(enhanced_packet(1, 3 * 1024 + 512, &[8]));
+
+    let mut reader = PcapngReader::new(file.as_slice()).unwrap();
+    let first = reader.next_record().unwrap().unwrap();
+    assert_eq!((first.ts_sec, first.ts_usec), (7, 250_000));
+    assert_eq!(first.link_type, 1);
+    let second = reader.next_record().unwrap().unwrap();
+    assert_eq!((second.ts_sec, second.ts_usec), (3, 500_000));
+    assert_eq!(second.link_type, 101);
+    assert!(reader.next_record().unwrap().is_none());
+    assert_eq!(reader.bytes_read(), file.len() as u64);
+}
+
+#[test]
+fn test_big_endian_section() {
+    let mut body = Vec::new();
+    body.extend_from_slice(&0x1A2B_3C4Du32.to_be_bytes());
+    body.extend_from_slice(&[0, 1, 0, 0]);
+    body.extend_from_slice(&[0xFF; 8]);
+    let mut file = block(0x0A0D_0D0A, &body, true);
+    file.extend(block(1, &[0, 1, 0, 0, 0, 0, 0xFF, 0xFF], true));
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&0u32.to_be_bytes());
+    packet.extend_from_slice(&0u32.to_be_bytes());
+    packet.extend_from_slice(&2_000_001u32.to_be_bytes());
+    packet.extend_from_slice(&2u32.to_be_bytes());
+    packet.extend_from_slice(&2u32.to_be_bytes());
+    packet.extend_from_slice(&[7, 7]);
+    file.extend(block(6, &packet, true));
+
+    let record = PcapngReader::new(file.as_slice())
+        .unwrap()
+        .next_record()
+        .unwrap()
+        .unwrap();
+    assert_eq!((record.ts_sec, record.ts_usec), (2, 1));
+    assert_eq!(record.data, vec![7, 7]);
+}
+
+#[test]
+fn test_undescribed_interface_is_an_error() {
+    let mut file = section_header();
+    file.extend(enhanced_packet(0, 0, &[1]));
+    let mut reader = PcapngReader::new(file.as_slice()).unwrap();
+    assert!(reader.next_record().is_err());
+}
+
+#[test]
+fn test_truncated_block_is_an_error() {
+    let mut file = section_header();
+    file.extend(interface(1, None));
+    file.extend(enhanced_packet(0, 0, &[1, 2, 3, 4]));
+    file.truncate(file.len() - 6);
+    let mut reader = PcapngReader::new(file.as_slice()).unwrap();
+    assert!(reader.next_record().is_err());
+}
+
+#[test]
+fn test_capture_reader_detects_format() {
+    let mut file = section_header();
+    file.extend(interface(1, None));
+    file.extend(enhanced_packet(0, 1_000_000, &[1]));
+    let reader = CaptureReader::new(file.as_slice()).unwrap();
+    assert!(matches!(reader, CaptureReader::Pcapng(_)));
+    assert_eq!(reader.count(), 1);
+
+    let mut pcap = Vec::new();
+    pcap.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+    pcap.extend_from_slice(&[2, 0, 4, 0]);
+    pcap.extend_from_slice(&[0u8; 8]);
+    pcap.extend_from_slice(&65535u32.to_le_bytes());
+    pcap.extend_from_slice(&1u32.to_le_bytes());
+    let reader = CaptureReader::new(pcap.as_slice()).unwrap();
+    assert!(matches!(reader, CaptureReader::Pcap(_)));
+
+    assert!(CaptureReader::new(&[0u8; 32][..]).is_err());
+}
+
+#[test]
+fn test_stream_decodes_cooked_and_raw_ip_interfaces() {
+    let ip = |sequence| ip_packet(9000, 50123, &ack_payload(sequence));
+
+    // Linux cooked header: packet type, ARPHRD, address length, address, protocol
+    let mut sll = vec![0, 0, 0, 1, 0, 6];
+    sll.extend_from_slice(&[0u8; 8]);
+    sll.extend_from_slice(&0x0800u16.to_be_bytes());
+    sll.extend(ip(5));
+
+    // Cooked v2 header: protocol, reserved, interface, ARPHRD, packet type, address
+    let mut sll2 = 0x0800u16.to_be_bytes().to_vec();
+    sll2.extend_from_slice(&[0u8; 18]);
+    sll2.extend(ip(6));
+
+    let mut file = section_header();
+    file.extend(interface(LINKTYPE_LINUX_SLL as u16, None));
+    file.extend(interface(LINKTYPE_LINUX_SLL2 as u16, None));
+    file.extend(interface(LINKTYPE_RAW as u16, None));
+    file.extend(interface(105, None)); // 802.11, which isn't decoded
+    file.extend(enhanced_packet(0, 1_000_000, &sll));
+    file.extend(enhanced_packet(1, 2_000_000, &sll2));
+    file.extend(enhanced_packet(2, 3_000_000, &ip(7)));
+    file.extend(enhanced_packet(3, 4_000_000, &[0u8; 64]));
+    file.extend(enhanced_packet(3, 5_000_000, &[0u8; 64]));
+
+    let mut sequences = Vec::new();
+    let mut diagnostics = Vec::new();
+    for event in PacketParser::new().stream(file.as_slice()).unwrap() {
+        match event {
+            ParseEvent::Packet(packet) => {
+                // The datagram is found within each frame as captured
+                assert_eq!(
+                    packet.raw_payload(&file),
+                    ack_payload(packet.header.sequence)
+                );
+                sequences.push(packet.header.sequence);
+            }
+            ParseEvent::Diagnostic(diagnostic) => diagnostics.push(diagnostic),
+            _ => {}
+        }
+    }
+
+    assert_eq!(sequences, vec![5, 6, 7]);
+    // Reported once, at the first frame of the unsupported interface
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::UnsupportedLinkType);
+    assert_eq!(diagnostics[0].record_index, Some(3));
+    assert_eq!(diagnostics[0].packet_index, None);
+}
+
+#[test]
+fn test_trim_writes_mixed_link_types_as_ethernet() {
+    let ip = |sequence| ip_packet(9000, 50123, &ack_payload(sequence));
+    let mut sll = vec![0, 0, 0, 1, 0, 6];
+    sll.extend_from_slice(&[0u8; 8]);
+    sll.extend_from_slice(&0x0800u16.to_be_bytes());
+    sll.extend(ip(6));
+
+    let mut file = section_header();
+    file.extend(interface(LINKTYPE_ETHERNET as u16, None));
+    file.extend(interface(LINKTYPE_LINUX_SLL as u16, None));
+    file.extend(interface(LINKTYPE_RAW as u16, None));
+    file.extend(enhanced_packet(
+        0,
+        1_000_000,
+        &ethernet_frame(9000, 50123, &ack_payload(5)),
+    ));
+    file.extend(enhanced_packet(1, 2_000_000, &sll));
+    file.extend(enhanced_packet(2, 3_000_000, &ip(7)));
+
+    let packets = |file: &[u8]| -> Vec<ParsedPacket> {
+        PacketParser::new()
+            .stream(file)
+            .unwrap()
+            .filter_map(|event| match event {
+                ParseEvent::Packet(packet) => Some(packet),
+                _ => None,
+            })
+            .collect()
+    };
+    let original = packets(&file);
+    assert_eq!(original.len(), 3);
+
+    let trimmed = trim(&file, original.iter().map(|p| p.record_index)).unwrap();
+    let mut reader = PcapReader::new(trimmed.as_slice()).unwrap();
+    assert_eq!(reader.link_type(), LINKTYPE_ETHERNET);
+    let records: Vec<CaptureRecord> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+    let stamps: Vec<u32> = records.iter().map(|r| r.ts_sec).collect();
+    assert_eq!(stamps, [1, 2, 3]);
+    // Frames that were already Ethernet are written as captured
+    assert_eq!(
+        records[0].data,
+        ethernet_frame(9000, 50123, &ack_payload(5))
+    );
+
+    let reread = packets(&trimmed);
+    assert_eq!(reread.len(), 3);
+    for (before, after) in original.iter().zip(&reread) {
+        assert_eq!(after.header.sequence, before.header.sequence);
+        assert_eq!(after.raw_payload(&trimmed), before.raw_payload(&file));
+    }
+}
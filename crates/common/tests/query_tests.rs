@@ -0,0 +1,152 @@
+mod support;
+
+use common::extract::FieldPath;
+use common::messages::ParsedMessage;
+use common::query::{Expr, Field, Op, Query};
+use serde_json::json;
+
+const START: f64 = 1000.0;
+
+fn message(
+    id: usize,
+    message_type: &str,
+    direction: &str,
+    opcode: &str,
+    data: serde_json::Value,
+) -> ParsedMessage {
+    ParsedMessage {
+        direction: direction.to_string(),
+        opcode: opcode.to_string(),
+        timestamp: START + id as f64 * 10.0,
+        ..support::message(id, message_type, data)
+    }
+}
+
+fn enchantment(id: usize, object_id: u32) -> ParsedMessage {
+    message(
+        id,
+        "Magic_UpdateEnchantment",
+        "Recv",
+        "02C2",
+        json!({
+            "ObjectId": object_id,
+            "Enchantment": { "Id": { "SpellId": 2053 }, "Duration": 1800.0 },
+        }),
+    )
+}
+
+fn matches(query: &str, message: &ParsedMessage) -> bool {
+    Query::parse(query)
+        .unwrap_or_else(|e| panic!("`{query}` failed to parse: {e}"))
+        .matches(message, START)
+}
+
+#[test]
+fn test_parse_field_comparisons() {
+    let query = Query::parse("type:Magic_UpdateEnchantment AND data.ObjectId==0x5000001A").unwrap();
+    let Expr::And(lhs, rhs) = query.expr() else {
+        panic!("expected AND, got {:?}", query.expr());
+    };
+    let Expr::Compare(type_cmp) = lhs.as_ref() else {
+        panic!("expected comparison");
+    };
+    assert_eq!(type_cmp.field, Field::Type);
+    assert_eq!(type_cmp.op, Op::Contains);
+    let Expr::Compare(data_cmp) = rhs.as_ref() else {
+        panic!("expected comparison");
+    };
+    assert_eq!(
+        data_cmp.field,
+        Field::Data(FieldPath::parse(".ObjectId").unwrap())
+    );
+    assert_eq!(data_cmp.op, Op::Eq);
+    assert_eq!(data_cmp.value.number, Some(0x5000001A as f64));
+}
+
+#[test]
+fn test_full_example_query() {
+    let query =
+        "type:Magic_UpdateEnchantment AND dir:recv AND data.ObjectId==0x5000001A AND ts>120";
+    assert!(matches(query, &enchantment(13, 0x5000001A)));
+    // Too early
+    assert!(!matches(query, &enchantment(12, 0x5000001A)));
+    // Other object
+    assert!(!matches(query, &enchantment(13, 0x5000001B)));
+}
+
+#[test]
+fn test_boolean_operators() {
+    let m = enchantment(1, 7);
+    assert!(matches("type:Movement OR dir:recv", &m));
+    assert!(!matches("type:Movement OR dir:send", &m));
+    assert!(matches("NOT dir:send", &m));
+    assert!(matches("!(type:Movement || id>5)", &m));
+    // Adjacent terms are ANDed
+    assert!(matches("dir:recv id==1", &m));
+    assert!(!matches("dir:recv id==2", &m));
+    // AND binds tighter than OR
+    assert!(matches("id==2 AND dir:send OR opcode==0x02C2", &m));
+    assert!(!matches("id==2 AND (dir:send OR opcode==02C2)", &m));
+}
+
+#[test]
+fn test_data_paths() {
+    let m = message(
+        1,
+        "Item_SetState",
+        "Recv",
+        "F74B",
+        json!({
+            "Items": [ { "Name": "Pantaloons" }, { "Name": "Sword" } ],
+            "Target": "0x80000001",
+        }),
+    );
+    assert!(matches("data.Items.Name:sword", &m));
+    assert!(matches("data.Items[0].Name==pantaloons", &m));
+    assert!(!matches("data.Items[0].Name==sword", &m));
+    assert!(matches("data.*.Name:pant", &m));
+    // Hex strings compare as numbers
+    assert!(matches("data.Target==2147483649", &m));
+    assert!(matches("data.Items.Name!=Shield", &m));
+    assert!(!matches("data.Items.Name!=Sword", &m));
+    // `data:` searches the whole message data
+    assert!(matches("data:pantaloons", &m));
+    assert!(!matches("data.Missing:anything", &m));
+}
+
+#[test]
+fn test_free_text_terms() {
+    let m = enchantment(1, 0x5000001A);
+    assert!(matches("UpdateEnchantment", &m));
+    assert!(matches("\"update\" dir:recv", &m));
+    // Numbers match the same representations as the plain search box
+    assert!(matches("0x5000001A", &m));
+    assert!(!matches("Movement", &m));
+    assert!(Query::parse("").unwrap().is_empty());
+}
+
+#[test]
+fn test_parse_errors() {
+    for query in [
+        "color:red",
+        "(dir:recv",
+        "dir:recv)",
+        "ts>soon",
+        "type:",
+        "type:\"unclosed",
+        "dir:recv AND",
+        "data>5",
+    ] {
+        assert!(Query::parse(query).is_err(), "`{query}` should not parse");
+    }
+
+    let err = Query::parse("dir:recv AND color:red").unwrap_err();
+    assert_eq!(err.position, 13);
+}
+
+#[test]
+fn test_invalid_query_falls_back_to_text() {
+    let (query, error) = Query::parse_or_text("Enchantment (");
+    assert!(error.is_some());
+    assert_eq!(query.expr(), &Expr::Text("Enchantment (".to_string()));
+}
@@ -0,0 +1,100 @@
+use common::Tab;
+use common::session::{
+    Bookmark, BookmarkTarget, CaptureHasher, DEFAULT_BOOKMARK_COLOR, SESSION_VERSION, Session,
+    capture_hash,
+};
+
+#[test]
+fn test_capture_hash() {
+    assert_eq!(
+        capture_hash(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+
+    let mut hasher = CaptureHasher::default();
+    hasher.update(b"a");
+    hasher.update(b"");
+    hasher.update(b"bc");
+    assert_eq!(hasher.finish(), capture_hash(b"abc"));
+}
+
+#[test]
+fn test_session_round_trip() {
+    let mut session = Session::new(capture_hash(b"abc"), "capture.pcap");
+    session.marked_messages = vec![3, 7];
+    session.marked_packets = vec![12];
+    session
+        .bookmarks
+        .push(Bookmark::new(BookmarkTarget::Message(7), "Sword appears"));
+    let mut bookmark = Bookmark::new(BookmarkTarget::Object(0x80000A01), "Pack");
+    bookmark.color = [200, 60, 60];
+    bookmark.note = "Stack size is wrong here".to_string();
+    session.bookmarks.push(bookmark);
+    session.filter.tab = Tab::Weenies;
+    session.filter.search_query = "type:Item".to_string();
+    session.filter.time_range = Some((100.5, 130.0));
+    session.filter.selected_message = Some(7);
+
+    let json = session.to_json().unwrap();
+    assert!(json.contains("\"CaptureHash\""));
+    assert_eq!(Session::from_json(&json).unwrap(), session);
+}
+
+#[test]
+fn test_minimal_session() {
+    let session = Session::from_json(r#"{ "Version": 1, "CaptureHash": "ABC123" }"#).unwrap();
+    assert!(session.is_for("abc123"));
+    assert!(session.bookmarks.is_empty());
+    assert_eq!(session.filter.tab, Tab::Messages);
+}
+
+#[test]
+fn test_bookmark_defaults() {
+    let session = Session::from_json(
+        r#"{
+            "Version": 2,
+            "CaptureHash": "abc",
+            "Bookmarks": [{ "Label": "Login", "Target": { "Message": 2 } }]
+        }"#,
+    )
+    .unwrap();
+    let bookmark = &session.bookmarks[0];
+    assert_eq!(bookmark.target, BookmarkTarget::Message(2));
+    assert_eq!(bookmark.color, DEFAULT_BOOKMARK_COLOR);
+    assert!(bookmark.note.is_empty());
+}
+
+#[test]
+fn test_version_1_session_upgrades() {
+    let session = Session::from_json(
+        r#"{
+            "Version": 1,
+            "CaptureHash": "abc",
+            "Bookmarks": [{ "Name": "Login", "MessageId": 2 }],
+            "Notes": { "2": "Slow to arrive", "9": "Wrong stack size" }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(session.version, SESSION_VERSION);
+
+    let login = &session.bookmarks[0];
+    assert_eq!(login.label, "Login");
+    assert_eq!(login.target, BookmarkTarget::Message(2));
+    assert_eq!(login.note, "Slow to arrive");
+    assert_eq!(login.color, DEFAULT_BOOKMARK_COLOR);
+
+    // A note without a bookmark gets one of its own
+    let noted = &session.bookmarks[1];
+    assert_eq!(noted.target, BookmarkTarget::Message(9));
+    assert_eq!(noted.note, "Wrong stack size");
+}
+
+#[test]
+fn test_newer_session_refused() {
+    let json = format!(
+        r#"{{ "Version": {}, "CaptureHash": "abc" }}"#,
+        SESSION_VERSION + 1
+    );
+    assert!(Session::from_json(&json).is_err());
+    assert!(Session::from_json("not json").is_err());
+}
@@ -0,0 +1,115 @@
+//! Fixtures shared by the integration tests
+
+#![allow(dead_code)]
+
+use common::fragment::FRAGMENT_HEADER_SIZE;
+use common::messages::ParsedMessage;
+use serde_json::Value;
+
+/// A received message of `message_type` carrying `data`, sent `100 + id` seconds in
+pub fn message(id: usize, message_type: &str, data: Value) -> ParsedMessage {
+    ParsedMessage {
+        id,
+        message_type: message_type.to_string(),
+        data,
+        direction: "Recv".to_string(),
+        opcode: "F745".to_string(),
+        timestamp: 100.0 + id as f64,
+        raw_bytes: Vec::new(),
+        field_offsets: None,
+    }
+}
+
+/// Wrap a UDP payload in Ethernet/IPv4/UDP headers
+pub fn ethernet_frame(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::new();
+
+    // Ethernet: destination, source, ethertype IPv4
+    frame.extend_from_slice(&[0u8; 12]);
+    frame.extend_from_slice(&0x0800u16.to_be_bytes());
+    frame.extend_from_slice(&ip_packet(src_port, dst_port, payload));
+    frame
+}
+
+/// Wrap a UDP payload in IPv4/UDP headers
+pub fn ip_packet(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::new();
+
+    // IPv4 header (20 bytes, protocol UDP)
+    let total_len = (20 + 8 + payload.len()) as u16;
+    packet.extend_from_slice(&[0x45, 0x00]);
+    packet.extend_from_slice(&total_len.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 0, 64, 17, 0, 0]);
+    packet.extend_from_slice(&[127, 0, 0, 1, 127, 0, 0, 1]);
+
+    // UDP header
+    packet.extend_from_slice(&src_port.to_be_bytes());
+    packet.extend_from_slice(&dst_port.to_be_bytes());
+    packet.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+    packet.extend_from_slice(&[0, 0]);
+
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// An AC packet carrying only an ack of `sequence - 1`
+pub fn ack_payload(sequence: u32) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&sequence.to_le_bytes());
+    payload.extend_from_slice(&0x4000u32.to_le_bytes()); // AckSequence
+    payload.extend_from_slice(&[0u8; 8]); // checksum, recipient, time
+    payload.extend_from_slice(&4u16.to_le_bytes()); // size
+    payload.extend_from_slice(&[0u8; 2]); // iteration
+    payload.extend_from_slice(&(sequence - 1).to_le_bytes());
+    payload
+}
+
+/// Encode a blob fragment with its 16-byte header
+pub fn encode_fragment(sequence: u32, count: u16, index: u16, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&sequence.to_le_bytes());
+    bytes.extend_from_slice(&0x8000_0000u32.to_le_bytes()); // id
+    bytes.extend_from_slice(&count.to_le_bytes());
+    bytes.extend_from_slice(&((FRAGMENT_HEADER_SIZE + data.len()) as u16).to_le_bytes());
+    bytes.extend_from_slice(&index.to_le_bytes());
+    bytes.extend_from_slice(&5u16.to_le_bytes()); // group
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// An Ethernet frame from the server carrying an AC packet of blob fragments
+pub fn fragment_frame(sequence: u32, body: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&sequence.to_le_bytes());
+    payload.extend_from_slice(&0x0004u32.to_le_bytes()); // BlobFragments
+    payload.extend_from_slice(&[0u8; 8]); // checksum, recipient, time
+    payload.extend_from_slice(&(body.len() as u16).to_le_bytes());
+    payload.extend_from_slice(&[0u8; 2]); // iteration
+    payload.extend_from_slice(body);
+    ethernet_frame(9000, 50123, &payload)
+}
+
+/// An Ethernet frame from the server carrying an AC ack packet
+pub fn ack_frame(sequence: u32) -> Vec<u8> {
+    ethernet_frame(9000, 50123, &ack_payload(sequence))
+}
+
+/// A little-endian microsecond pcap file of Ethernet frames, from (ts_sec, ts_usec, frame)
+pub fn build_pcap(records: &[(u32, u32, Vec<u8>)]) -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+    file.extend_from_slice(&2u16.to_le_bytes()); // version major
+    file.extend_from_slice(&4u16.to_le_bytes()); // version minor
+    file.extend_from_slice(&[0u8; 8]); // thiszone, sigfigs
+    file.extend_from_slice(&65535u32.to_le_bytes()); // snaplen
+    file.extend_from_slice(&1u32.to_le_bytes()); // Ethernet
+
+    for (ts_sec, ts_usec, data) in records {
+        file.extend_from_slice(&ts_sec.to_le_bytes());
+        file.extend_from_slice(&ts_usec.to_le_bytes());
+        file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        file.extend_from_slice(data);
+    }
+    file
+}
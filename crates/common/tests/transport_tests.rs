@@ -0,0 +1,121 @@
+mod support;
+
+use common::PacketHeaderFlags;
+use common::capture::{LINKTYPE_ETHERNET, LINKTYPE_NULL};
+use common::transport::{
+    OptionalHeaders, PACKET_HEADER_SIZE, PacketHeader, extract_udp, to_ethernet,
+};
+use support::{ethernet_frame, ip_packet};
+
+/// Build an AC packet with an ack and a time sync optional header
+fn build_ack_time_sync_packet() -> Vec<u8> {
+    let flags = PacketHeaderFlags::ACK_SEQUENCE | PacketHeaderFlags::TIME_SYNC;
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&42u32.to_le_bytes()); // sequence
+    payload.extend_from_slice(&flags.bits().to_le_bytes());
+    payload.extend_from_slice(&0xDEADBEEFu32.to_le_bytes()); // checksum
+    payload.extend_from_slice(&7u16.to_le_bytes()); // recipient
+    payload.extend_from_slice(&3u16.to_le_bytes()); // time
+    payload.extend_from_slice(&12u16.to_le_bytes()); // size
+    payload.extend_from_slice(&1u16.to_le_bytes()); // iteration
+    payload.extend_from_slice(&41u32.to_le_bytes()); // ack sequence
+    payload.extend_from_slice(&1234.5f64.to_le_bytes()); // time sync
+    payload
+}
+
+#[test]
+fn test_extract_udp_from_ethernet_frame() {
+    let payload = build_ack_time_sync_packet();
+    let frame = ethernet_frame(9000, 50123, &payload);
+
+    let datagram = extract_udp(&frame).expect("frame should contain a UDP datagram");
+    assert_eq!(datagram.src_port, 9000);
+    assert_eq!(datagram.dst_port, 50123);
+    assert_eq!(datagram.payload, payload.as_slice());
+    assert_eq!(datagram.direction(), "Recv");
+
+    let frame = ethernet_frame(50123, 9000, &payload);
+    assert_eq!(extract_udp(&frame).unwrap().direction(), "Send");
+}
+
+#[test]
+fn test_extract_udp_rejects_non_ipv4() {
+    let mut frame = ethernet_frame(9000, 50123, &build_ack_time_sync_packet());
+    // Change the ethertype to ARP
+    frame[12] = 0x08;
+    frame[13] = 0x06;
+    assert!(extract_udp(&frame).is_none());
+}
+
+#[test]
+fn test_packet_header_read() {
+    let payload = build_ack_time_sync_packet();
+    let header = PacketHeader::read(&payload).unwrap();
+
+    assert_eq!(header.sequence, 42);
+    assert!(header.flags.contains(PacketHeaderFlags::ACK_SEQUENCE));
+    assert!(header.flags.contains(PacketHeaderFlags::TIME_SYNC));
+    assert_eq!(header.checksum, 0xDEADBEEF);
+    assert_eq!(header.recipient_id, 7);
+    assert_eq!(header.time, 3);
+    assert_eq!(header.size, 12);
+    assert_eq!(header.iteration, 1);
+}
+
+#[test]
+fn test_packet_header_too_short() {
+    assert!(PacketHeader::read(&[0u8; PACKET_HEADER_SIZE - 1]).is_err());
+}
+
+#[test]
+fn test_read_datagram_checks_the_size() {
+    let mut payload = build_ack_time_sync_packet();
+    assert_eq!(PacketHeader::read_datagram(&payload).unwrap().sequence, 42);
+
+    payload.push(0);
+    assert!(PacketHeader::read_datagram(&payload).is_err());
+}
+
+#[test]
+fn test_optional_headers_read() {
+    let payload = build_ack_time_sync_packet();
+    let header = PacketHeader::read(&payload).unwrap();
+    let optional = OptionalHeaders::read(header.flags, &payload[PACKET_HEADER_SIZE..]).unwrap();
+
+    assert_eq!(optional.ack_sequence, Some(41));
+    assert_eq!(optional.time_sync, Some(1234.5));
+    assert_eq!(optional.echo_request, None);
+    assert_eq!(optional.body_offset, 12);
+}
+
+#[test]
+fn test_header_serializes_flag_names() {
+    let payload = build_ack_time_sync_packet();
+    let header = PacketHeader::read(&payload).unwrap();
+    let json = serde_json::to_value(&header).unwrap();
+
+    assert_eq!(json["Sequence"], 42);
+    assert_eq!(json["Flags"], "Ack|TimeSync");
+}
+
+#[test]
+fn test_to_ethernet_rewrites_loopback_frames() {
+    let payload = build_ack_time_sync_packet();
+    let frame = ethernet_frame(9000, 50123, &payload);
+    assert_eq!(
+        to_ethernet(LINKTYPE_ETHERNET, &frame).unwrap().as_ref(),
+        frame
+    );
+
+    // Loopback frames start with AF_INET in the capturing host's byte order
+    for family in [2u32.to_le_bytes(), 2u32.to_be_bytes()] {
+        let mut loopback = family.to_vec();
+        loopback.extend(ip_packet(9000, 50123, &payload));
+        let rewritten = to_ethernet(LINKTYPE_NULL, &loopback).unwrap();
+        assert_eq!(rewritten.as_ref(), frame);
+    }
+
+    // AF_INET6 and unknown link types aren't rewritten
+    assert!(to_ethernet(LINKTYPE_NULL, &[30, 0, 0, 0, 0x60]).is_none());
+    assert!(to_ethernet(147, &frame).is_none());
+}
@@ -0,0 +1,123 @@
+use common::PacketHeaderFlags;
+use common::packet_flags::format_packet_flags;
+use common::value_names::{ValueKind, ValueNames, flag_names, spells_from_csv, spells_from_json};
+use common::weenie::PropertyKind;
+
+#[test]
+fn test_field_kinds() {
+    assert_eq!(
+        ValueKind::of_field(
+            "S2C.ItemCreateObject.WeenieDescription.ItemType",
+            "ItemType"
+        ),
+        Some(ValueKind::ItemType)
+    );
+    assert_eq!(
+        ValueKind::of_field("Enchantment.Id.SpellId", "SpellId"),
+        Some(ValueKind::Spell)
+    );
+    assert_eq!(
+        ValueKind::of_field("S2C.QualitiesPrivateUpdateInt64.Key", "Key"),
+        Some(ValueKind::Property(PropertyKind::Int64))
+    );
+    assert_eq!(
+        ValueKind::of_field("S2C.QualitiesUpdateInstanceId.Key", "Key"),
+        Some(ValueKind::Property(PropertyKind::InstanceId))
+    );
+    assert_eq!(
+        ValueKind::of_field("Header.Flags", "Flags"),
+        Some(ValueKind::PacketFlags)
+    );
+    assert_eq!(ValueKind::of_field("S2C.Other.Flags", "Flags"), None);
+    // A key outside a quality update could be anything
+    assert_eq!(ValueKind::of_field("S2C.Other.Key", "Key"), None);
+    assert_eq!(ValueKind::of_field("S2C.Other.Value", "Value"), None);
+}
+
+#[test]
+fn test_property_table_keys() {
+    let path = "S2C.Item_SetAppraiseInfo.IntProperties.Table";
+    assert_eq!(
+        ValueKind::of_map_key(path),
+        Some(ValueKind::Property(PropertyKind::Int))
+    );
+    assert_eq!(
+        ValueKind::of_map_key("S2C.Item_SetAppraiseInfo.Int64Properties.Table"),
+        Some(ValueKind::Property(PropertyKind::Int64))
+    );
+    assert_eq!(
+        ValueKind::of_map_key("S2C.Item_SetAppraiseInfo.DataIdProperties.Table"),
+        Some(ValueKind::Property(PropertyKind::DataId))
+    );
+    assert_eq!(
+        ValueKind::of_map_key("S2C.Item_SetAppraiseInfo.IntProperties"),
+        None
+    );
+    assert_eq!(ValueKind::of_map_key("S2C.Other.Table"), None);
+
+    let names = ValueNames::new();
+    assert_eq!(
+        names.describe_key(path, "12"),
+        Some("StackSize".to_string())
+    );
+    // Keys already written as names are left alone
+    assert_eq!(names.describe_key(path, "StackSize"), None);
+    assert_eq!(names.describe_key("S2C.Other.Table", "12"), None);
+}
+
+#[test]
+fn test_flag_names() {
+    let bits = (PacketHeaderFlags::RETRANSMISSION | PacketHeaderFlags::ACK_SEQUENCE).bits();
+    assert_eq!(
+        flag_names::<PacketHeaderFlags>(bits),
+        "Retransmission|AckSequence"
+    );
+    assert_eq!(flag_names::<PacketHeaderFlags>(0), "None");
+}
+
+#[test]
+fn test_packet_flags() {
+    let flags = PacketHeaderFlags::RETRANSMISSION | PacketHeaderFlags::ACK_SEQUENCE;
+    assert_eq!(format_packet_flags(flags), "Retrans|Ack");
+    assert_eq!(format_packet_flags(PacketHeaderFlags::empty()), "None");
+    assert_eq!(
+        ValueNames::new().describe("Header.Flags", "Flags", flags.bits() as u64),
+        Some("Retrans|Ack".to_string())
+    );
+}
+
+#[test]
+fn test_item_type() {
+    let names = ValueNames::new();
+    assert_eq!(
+        names.describe("WeenieDescription.ItemType", "ItemType", 0x2),
+        Some("Armor".to_string())
+    );
+    assert_eq!(
+        names.describe("WeenieDescription.Value", "Value", 0x2),
+        None
+    );
+}
+
+#[test]
+fn test_spell_tables() {
+    let spells =
+        spells_from_csv("id,name\n# comment\n1, Strength Other I\n0x2,\"Strength Self I\"\n")
+            .unwrap();
+    assert_eq!(spells[&1], "Strength Other I");
+    assert_eq!(spells[&2], "Strength Self I");
+    assert!(spells_from_csv("1,A\nnot a number,B").is_err());
+
+    let spells =
+        spells_from_json(r#"{ "1": "Strength Other I", "0x2": "Strength Self I" }"#).unwrap();
+    assert_eq!(spells.len(), 2);
+
+    let names = ValueNames::with_spells(spells);
+    assert_eq!(names.spell_count(), 2);
+    assert_eq!(
+        names.describe("Enchantment.Id.SpellId", "SpellId", 2),
+        Some("Strength Self I".to_string())
+    );
+    // Spells missing from the table stay numbers
+    assert_eq!(names.describe("Enchantment.Id.SpellId", "SpellId", 3), None);
+}
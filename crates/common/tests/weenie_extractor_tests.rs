@@ -0,0 +1,142 @@
+mod support;
+
+use common::messages::ParsedMessage;
+use common::weenie::{LifecycleKind, WeenieDatabase};
+use common::weenie_extractor::{WeenieExtractor, extract_weenie_updates};
+use serde_json::json;
+use support::message;
+
+fn create_object(id: usize) -> ParsedMessage {
+    message(
+        id,
+        "Item_CreateObject",
+        json!({
+            "S2C": {
+                "ItemCreateObject": {
+                    "ObjectId": 0x80000A01u32,
+                    "WeenieDescription": {
+                        "Name": "Pyreal Mote",
+                        "WeenieClassId": 8331,
+                        "Icon": 0x06002B10u32,
+                        "Type": "Misc",
+                        "Value": 100,
+                        "StackSize": 5,
+                        "ContainerId": 0x50000001u32,
+                        "UseRadius": 1.5,
+                    },
+                    "PhysicsDescription": {
+                        "SetupId": 0x02000181u32,
+                        "Scale": 0.8,
+                        "Position": {
+                            "ObjCellId": 0xA9B4001Fu32,
+                            "Frame": { "Origin": { "X": 84.0, "Y": 12.5, "Z": 94.25 } },
+                        },
+                    },
+                },
+            },
+        }),
+    )
+}
+
+#[test]
+fn test_create_object() {
+    let updates = extract_weenie_updates(&create_object(1));
+    assert_eq!(updates.len(), 1);
+    let update = &updates[0];
+
+    assert_eq!(update.object_id, 0x80000A01);
+    assert_eq!(update.lifecycle, Some(LifecycleKind::Created));
+    assert_eq!(update.name.as_deref(), Some("Pyreal Mote"));
+    assert_eq!(update.int_properties["WeenieClassId"], 8331);
+    assert_eq!(update.int_properties["Value"], 100);
+    assert_eq!(update.int_properties["StackSize"], 5);
+    // Enums decoded to names stay readable
+    assert_eq!(update.string_properties["ItemType"], "Misc");
+    assert_eq!(update.data_id_properties["Icon"], 0x06002B10);
+    assert_eq!(update.data_id_properties["Setup"], 0x02000181);
+    assert_eq!(update.instance_id_properties["Container"], 0x50000001);
+    assert_eq!(update.float_properties["UseRadius"], 1.5);
+    assert_eq!(update.float_properties["DefaultScale"], 0.8);
+    assert_eq!(
+        update.string_properties["Position"],
+        "0xA9B4001F [84.00 12.50 94.25]"
+    );
+}
+
+#[test]
+fn test_update_and_delete_object() {
+    let update = message(
+        2,
+        "Item_UpdateObject",
+        json!({
+            "S2C": {
+                "ItemUpdateObject": {
+                    "ObjectId": 0x80000A01u32,
+                    "WeenieDescription": { "Name": "Pyreal Mote", "StackSize": 3 },
+                },
+            },
+        }),
+    );
+    let delete = message(
+        3,
+        "Item_DeleteObject",
+        json!({ "S2C": { "ItemDeleteObject": { "ObjectId": 0x80000A01u32 } } }),
+    );
+
+    let mut db = WeenieDatabase::new();
+    for m in [create_object(1), update, delete] {
+        for update in extract_weenie_updates(&m) {
+            db.add_or_update(update);
+        }
+    }
+
+    let weenie = db.get(0x80000A01).unwrap();
+    assert_eq!(weenie.int_properties["StackSize"], 3);
+    assert_eq!(weenie.created_at(), Some(101.0));
+    assert_eq!(weenie.deleted_at(), Some(103.0));
+    assert_eq!(weenie.lifecycle.len(), 2);
+
+    // Still around before it was deleted
+    assert_eq!(
+        db.state_at(102.0).get(0x80000A01).unwrap().deleted_at(),
+        None
+    );
+}
+
+#[test]
+fn test_private_updates_apply_to_player() {
+    let private_update = |id| {
+        message(
+            id,
+            "Qualities_PrivateUpdateInt",
+            json!({
+                "S2C": {
+                    "QualitiesPrivateUpdateInt": {
+                        "Sequence": 4,
+                        "Key": "EncumbranceVal",
+                        "Value": 1250,
+                    },
+                },
+            }),
+        )
+    };
+    let login = message(
+        2,
+        "Login_CreatePlayer",
+        json!({ "S2C": { "LoginCreatePlayer": { "CharacterId": 0x50000001u32 } } }),
+    );
+
+    let mut extractor = WeenieExtractor::new();
+    // Nobody to attribute it to before login
+    assert!(extractor.extract(&private_update(1)).is_empty());
+    extractor.extract(&login);
+    assert_eq!(extractor.player_id(), Some(0x50000001));
+
+    let updates = extractor.extract(&private_update(3));
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].object_id, 0x50000001);
+    assert_eq!(updates[0].int_properties["EncumbranceVal"], 1250);
+
+    // Other messages go through the usual extraction
+    assert_eq!(extractor.extract(&create_object(4)).len(), 1);
+}
@@ -0,0 +1,208 @@
+use common::weenie::{LifecycleKind, PropertyKind, PropertyValue, WeenieDatabase, WeenieUpdate};
+
+fn stack_size(message_id: usize, timestamp: f64, value: i32) -> WeenieUpdate {
+    let mut update = WeenieUpdate::new(0x80000001, timestamp, message_id);
+    update.int_properties.insert("StackSize".to_string(), value);
+    update
+}
+
+#[test]
+fn test_history_records_each_change() {
+    let mut db = WeenieDatabase::new();
+    db.add_or_update(stack_size(1, 10.0, 5));
+    db.add_or_update(stack_size(2, 20.0, 5));
+    db.add_or_update(stack_size(3, 30.0, 4));
+
+    let weenie = db.get(0x80000001).unwrap();
+    assert_eq!(weenie.int_properties["StackSize"], 4);
+
+    // The repeated value isn't a change
+    let history = db.history(0x80000001);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].kind, PropertyKind::Int);
+    assert_eq!(history[0].property, "StackSize");
+    assert_eq!(history[0].old_value, None);
+    assert_eq!(history[0].new_value, PropertyValue::Int(5));
+    assert_eq!(history[0].message_id, 1);
+    assert_eq!(history[1].old_value, Some(PropertyValue::Int(5)));
+    assert_eq!(history[1].new_value, PropertyValue::Int(4));
+    assert_eq!(history[1].timestamp, 30.0);
+    assert_eq!(history[1].message_id, 3);
+}
+
+#[test]
+fn test_first_update_counts_its_message_once() {
+    let mut db = WeenieDatabase::new();
+    db.add_or_update(stack_size(1, 10.0, 5));
+
+    let weenie = db.get(0x80000001).unwrap();
+    assert_eq!(weenie.message_count, 1);
+    assert_eq!(weenie.message_ids, [1]);
+    assert_eq!(weenie.message_timestamps, [10.0]);
+    assert_eq!(weenie.int_properties["StackSize"], 5);
+
+    db.add_or_update(stack_size(2, 20.0, 4));
+    let weenie = db.get(0x80000001).unwrap();
+    assert_eq!(weenie.message_count, 2);
+    assert_eq!(weenie.message_ids, [1, 2]);
+}
+
+#[test]
+fn test_history_per_property() {
+    let mut db = WeenieDatabase::new();
+    let mut update = WeenieUpdate::new(7, 1.0, 1);
+    update.name = Some("Pack".to_string());
+    update.int_properties.insert("Value".to_string(), 100);
+    update
+        .data_id_properties
+        .insert("Icon".to_string(), 0x06001234);
+    db.add_or_update(update);
+
+    let mut update = WeenieUpdate::new(7, 2.0, 2);
+    update.name = Some("Pack".to_string());
+    update.int_properties.insert("Value".to_string(), 250);
+    db.add_or_update(update);
+
+    let weenie = db.get(7).unwrap();
+    let values: Vec<_> = weenie
+        .property_history(PropertyKind::Int, "Value")
+        .map(|c| c.new_value.clone())
+        .collect();
+    assert_eq!(values, [PropertyValue::Int(100), PropertyValue::Int(250)]);
+    assert_eq!(
+        weenie.property_history(PropertyKind::Name, "Name").count(),
+        1
+    );
+    assert_eq!(PropertyValue::DataId(0x06001234).to_string(), "0x06001234");
+    assert!(db.history(8).is_empty());
+}
+
+#[test]
+fn test_state_at() {
+    let mut db = WeenieDatabase::new();
+    let mut update = stack_size(1, 10.0, 5);
+    update.name = Some("Arrows".to_string());
+    db.add_or_update(update);
+    db.add_or_update(stack_size(2, 20.0, 4));
+    let mut update = WeenieUpdate::new(0x80000002, 25.0, 3);
+    update.bool_properties.insert("Open".to_string(), true);
+    db.add_or_update(update);
+    db.add_or_update(stack_size(4, 30.0, 3));
+
+    // Nothing had been seen yet
+    assert_eq!(db.state_at(5.0).count(), 0);
+
+    let early = db.state_at(15.0);
+    assert_eq!(early.count(), 1);
+    let arrows = early.get(0x80000001).unwrap();
+    assert_eq!(arrows.name.as_deref(), Some("Arrows"));
+    assert_eq!(arrows.int_properties["StackSize"], 5);
+    assert_eq!(arrows.last_updated, 10.0);
+    assert_eq!(arrows.history.len(), 2);
+
+    let middle = db.state_at(25.0);
+    assert_eq!(middle.count(), 2);
+    assert_eq!(
+        middle.get(0x80000001).unwrap().int_properties["StackSize"],
+        4
+    );
+    assert!(middle.get(0x80000002).unwrap().bool_properties["Open"]);
+
+    // The latest state matches the database itself
+    let latest = db.state_at(f64::INFINITY);
+    let arrows = latest.get(0x80000001).unwrap();
+    let current = db.get(0x80000001).unwrap();
+    assert_eq!(arrows.int_properties, current.int_properties);
+    assert_eq!(arrows.message_ids, current.message_ids);
+    assert_eq!(arrows.message_count, current.message_count);
+    assert_eq!(arrows.last_updated, current.last_updated);
+}
+
+#[test]
+fn test_message_cross_references() {
+    let mut db = WeenieDatabase::new();
+    db.add_or_update(stack_size(1, 10.0, 5));
+    db.add_or_update(WeenieUpdate::new(0x80000002, 10.0, 1));
+    db.add_or_update(stack_size(2, 20.0, 4));
+
+    let objects: Vec<u32> = db
+        .objects_in_message(1)
+        .iter()
+        .map(|w| w.object_id)
+        .collect();
+    assert_eq!(objects, [0x80000001, 0x80000002]);
+    assert_eq!(db.objects_in_message(2).len(), 1);
+    assert!(db.objects_in_message(3).is_empty());
+
+    let arrows = db.get(0x80000001).unwrap();
+    assert_eq!(arrows.messages(), [(1, 10.0), (2, 20.0)]);
+
+    // Messages after the instant aren't referenced yet
+    let early = db.state_at(15.0);
+    assert_eq!(early.objects_in_message(1).len(), 2);
+    assert!(early.objects_in_message(2).is_empty());
+}
+
+#[test]
+fn test_state_at_leaves_out_deleted_objects() {
+    let mut db = WeenieDatabase::new();
+    let lifecycle = |message_id, timestamp, kind| {
+        let mut update = stack_size(message_id, timestamp, 5);
+        update.lifecycle = Some(kind);
+        update
+    };
+    db.add_or_update(lifecycle(1, 10.0, LifecycleKind::Created));
+    db.add_or_update(lifecycle(2, 20.0, LifecycleKind::Deleted));
+    db.add_or_update(lifecycle(3, 30.0, LifecycleKind::Created));
+
+    assert!(db.state_at(15.0).get(0x80000001).is_some());
+    assert!(db.state_at(20.0).get(0x80000001).is_none());
+    assert!(db.state_at(25.0).get(0x80000001).is_none());
+    assert!(db.state_at(25.0).objects_in_message(1).is_empty());
+
+    // Created again after it was deleted
+    let recreated = db.state_at(30.0);
+    assert_eq!(recreated.get(0x80000001).unwrap().lifecycle.len(), 3);
+}
+
+#[test]
+fn test_recreated_object_starts_over() {
+    let mut db = WeenieDatabase::new();
+    let mut update = stack_size(1, 10.0, 5);
+    update.name = Some("Arrows".to_string());
+    update
+        .bool_properties
+        .insert("Attackable".to_string(), true);
+    update.lifecycle = Some(LifecycleKind::Created);
+    db.add_or_update(update);
+    let mut update = WeenieUpdate::new(0x80000001, 20.0, 2);
+    update.lifecycle = Some(LifecycleKind::Deleted);
+    db.add_or_update(update);
+    let mut update = stack_size(3, 30.0, 5);
+    update.name = Some("Bolts".to_string());
+    update.lifecycle = Some(LifecycleKind::Created);
+    db.add_or_update(update);
+
+    let current = db.get(0x80000001).unwrap();
+    assert_eq!(current.name.as_deref(), Some("Bolts"));
+    assert_eq!(current.int_properties["StackSize"], 5);
+    assert!(current.bool_properties.is_empty());
+    assert_eq!(current.message_count, 3);
+
+    let before = db.state_at(15.0);
+    let arrows = before.get(0x80000001).unwrap();
+    assert_eq!(arrows.name.as_deref(), Some("Arrows"));
+    assert!(arrows.bool_properties["Attackable"]);
+    assert_eq!(arrows.message_ids, [1]);
+    assert_eq!(arrows.message_count, 1);
+
+    let after = db.state_at(35.0);
+    let bolts = after.get(0x80000001).unwrap();
+    assert_eq!(bolts.name.as_deref(), Some("Bolts"));
+    assert_eq!(bolts.int_properties, current.int_properties);
+    assert!(bolts.bool_properties.is_empty());
+    assert_eq!(bolts.message_ids, [1, 2, 3]);
+    assert_eq!(bolts.message_count, 3);
+    // The old life stays in the history
+    assert_eq!(bolts.history.len(), current.history.len());
+}